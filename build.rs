@@ -1,5 +1,8 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
     tonic_prost_build::configure()
+        //served by the reflection service.
+        .file_descriptor_set_path(out_dir.join("redstone_descriptor.bin"))
         .bytes("redstone.GetResponseChunk.data")
         .bytes("redstone.PutRequest.data")
         .compile_protos(&["proto/redstone.proto"], &["proto"])?;
@@ -11,7 +11,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Loading cluster config from {}", config_path);
 
     let file_config = ClusterClientFileConfig::load(&config_path)?;
-    let (nodes, runtime_config) = file_config.into_runtime();
+    let (nodes, runtime_config) = file_config.into_runtime()?;
     if nodes.is_empty() {
         return Err("No nodes provided.".into());
     }
@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use redstone::cluster::distributed_client::DistributedClient;
 use redstone::cluster::node::Node;
-use redstone::transport::grpc::server::start_server;
+use redstone::transport::grpc::server::{spawn_server, ServerHandle, ServerOptions};
 use redstone::tensor::meta::{TensorMeta, DType, StorageLayout};
 
 use std::time::{Duration, Instant};
@@ -74,24 +74,20 @@ impl TensorSize {
     }
 }
 
-async fn spawn_cluster(base_port: u16) -> Vec<Node> {
+async fn spawn_cluster(base_port: u16) -> (Vec<Node>, Vec<ServerHandle>) {
     let ports = [base_port, base_port + 1, base_port + 2];
     let mut nodes = Vec::new();
+    let mut servers = Vec::new();
 
     for port in ports {
-        let addr = format!("127.0.0.1:{}", port);
-        let server_addr = addr.clone();
-
-        tokio::spawn(async move {
-            start_server(server_addr, CACHE_SIZE)
-                .await
-                .expect("Server failed");
-        });
-        nodes.push(Node::new(addr, format!("node-{}", port)));
+        let server = spawn_server(format!("127.0.0.1:{}", port), CACHE_SIZE, ServerOptions::default())
+            .await
+            .expect("Server failed");
+        nodes.push(Node::new(server.local_addr().to_string(), format!("node-{}", port)));
+        servers.push(server);
     }
 
-    sleep(Duration::from_secs(2)).await;
-    nodes
+    (nodes, servers)
 }
 
 fn make_tensor(elements: usize) -> (TensorMeta, Vec<u8>) {
@@ -484,7 +480,7 @@ async fn main() {
         println!("Benchmarking {}", label);
         println!("Starting cluster on ports {}-{}...", port_seed, port_seed + 2);
 
-        let nodes = spawn_cluster(port_seed).await;
+        let (nodes, servers) = spawn_cluster(port_seed).await;
         let client = Arc::new(DistributedClient::new_default(nodes));
         let run_id = format!("{}_port{}", label, port_seed);
 
@@ -544,7 +540,9 @@ async fn main() {
         }
 
         port_seed += 10;
-        sleep(Duration::from_secs(1)).await;
+        for server in servers {
+            server.shutdown().await.expect("Server shutdown failed");
+        }
     }
 
     println!("\nAll benchmarks completed");
@@ -0,0 +1,107 @@
+// Command line tool to move tensors between files and a Redstone cluster.
+
+use std::path::{Path, PathBuf};
+use clap::{Parser, Subcommand};
+use redstone::cluster::config::config_file::ClusterClientFileConfig;
+use redstone::cluster::distributed_client::DistributedClient;
+use redstone::interop::npy;
+use redstone::interop::safetensors::{self, SafetensorsOptions};
+
+#[derive(Parser, Debug)]
+#[command(name = "redstone_cli")]
+#[command(about = "Import and export tensors between files and a Redstone cluster", long_about = None)]
+struct Args {
+    /// cluster client config file.
+    #[arg(short, long, default_value = "client_config.toml")]
+    config: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Put every tensor of a .npy, .npz or .safetensors file into the cluster.
+    Import {
+        path: PathBuf,
+        /// key for a .npy file, defaults to the file name without extension.
+        #[arg(short, long)]
+        key: Option<String>,
+        /// prefix added to the array names of a .npz or .safetensors file.
+        #[arg(short, long, default_value = "")]
+        prefix: String,
+    },
+    /// Write cached keys into a .npy, .npz or .safetensors file.
+    Export {
+        path: PathBuf,
+        #[arg(required = true, num_args = 1..)]
+        keys: Vec<String>,
+        /// prefix stripped from the keys to form the array names of a .npz or .safetensors file.
+        #[arg(short, long, default_value = "")]
+        prefix: String,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+
+    let (nodes, runtime_config) = ClusterClientFileConfig::load(&args.config)?.into_runtime()?;
+    if nodes.is_empty() {
+        return Err("No nodes provided.".into());
+    }
+    let client = DistributedClient::new_with_config(nodes, runtime_config);
+
+    match args.command {
+        Command::Import { path, key, prefix } => {
+            let keys = match extension(&path).as_str() {
+                "npy" => {
+                    let key = key.unwrap_or_else(|| file_stem(&path));
+                    npy::import_npy_into_cluster(&path, &client, &key).await?;
+                    vec![key]
+                }
+                "npz" => npy::import_npz_into_cluster(&path, &client, &prefix).await?,
+                "safetensors" => {
+                    let options = SafetensorsOptions::with_prefix(prefix);
+                    safetensors::import_into_cluster(&path, &client, &options).await?
+                }
+                other => return Err(format!("Unsupported file extension: {}", other).into()),
+            };
+            for key in &keys {
+                println!("Imported {}", key);
+            }
+            println!("Imported {} tensors from {}", keys.len(), path.display());
+        }
+        Command::Export { path, keys, prefix } => {
+            match extension(&path).as_str() {
+                "npy" => {
+                    if keys.len() != 1 {
+                        return Err("A .npy file holds exactly one tensor, pass a single key.".into());
+                    }
+                    npy::export_npy_from_cluster(&client, &keys[0], &path).await?;
+                }
+                "npz" => npy::export_npz_from_cluster(&client, &keys, &path, &prefix).await?,
+                "safetensors" => {
+                    let options = SafetensorsOptions::with_prefix(prefix);
+                    safetensors::export_from_cluster(&client, &keys, &path, &options).await?;
+                }
+                other => return Err(format!("Unsupported file extension: {}", other).into()),
+            }
+            println!("Exported {} tensors to {}", keys.len(), path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -1,33 +1,180 @@
 // Binary to run the Redstone cache server
 
-use redstone::transport::grpc::server::start_server;
-use std::env;
+use clap::Parser;
+use redstone::cache::put_options::NonFinitePolicy;
+use redstone::transport::grpc::server::{shutdown_signal, spawn_server, ShutdownOutcome};
+use redstone::transport::grpc::server_config::{parse_duration, parse_size, AuthConfig, ServerConfig, TlsConfig};
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Flags override the values of the config file, which override the defaults.
+#[derive(Parser, Debug)]
+#[command(name = "redstone_server")]
+#[command(about = "Run a Redstone cache server", long_about = None)]
+struct Args {
+    /// server config file, see `server_config` for the format.
+    #[arg(short, long)]
+    config: Option<String>,
+    /// validate the config, read the TLS and token files, print the effective config and exit.
+    #[arg(long)]
+    check_config: bool,
+
+    /// socket address to listen on.
+    #[arg(short, long)]
+    listen: Option<String>,
+    /// bytes of tensor data to cache, like 8GiB.
+    #[arg(short, long, value_parser = parse_size)]
+    memory_limit: Option<u64>,
+    /// payload bytes per message of a streamed get.
+    #[arg(long, value_parser = parse_size)]
+    chunk_size: Option<u64>,
+    /// how long a shutdown waits for open calls, like 30s.
+    #[arg(long, value_parser = parse_duration)]
+    drain_timeout: Option<Duration>,
+    /// time between background checksum scrubs, 0s disables scrubbing.
+    #[arg(long, value_parser = parse_duration)]
+    scrub_interval: Option<Duration>,
+    /// allow, reject or flag puts of floating point tensors containing NaN or infinities.
+    #[arg(long)]
+    non_finite: Option<NonFinitePolicy>,
+    /// store byte-identical payloads once across keys.
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    dedup: Option<bool>,
+    /// serve gRPC reflection.
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    reflection: Option<bool>,
+    /// report serving to health checks once started, false keeps the server out of rotation.
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    serving_on_start: Option<bool>,
+
+    /// HTTP/2 flow control window of each stream.
+    #[arg(long, value_parser = parse_size)]
+    stream_window: Option<u64>,
+    /// HTTP/2 flow control window of each connection.
+    #[arg(long, value_parser = parse_size)]
+    connection_window: Option<u64>,
+    /// largest request message accepted.
+    #[arg(long, value_parser = parse_size)]
+    max_decoding_message_size: Option<u64>,
+    /// largest response message sent.
+    #[arg(long, value_parser = parse_size)]
+    max_encoding_message_size: Option<u64>,
+    /// streams a client may open on one connection.
+    #[arg(long)]
+    max_concurrent_streams: Option<u32>,
+    /// requests served at once per connection.
+    #[arg(long)]
+    concurrency_limit_per_connection: Option<usize>,
+
+    /// PEM certificate chain to serve TLS with, needs --tls-key.
+    #[arg(long, requires = "tls_key")]
+    tls_cert: Option<String>,
+    /// PEM private key of --tls-cert.
+    #[arg(long, requires = "tls_cert")]
+    tls_key: Option<String>,
+    /// PEM CA bundle client certificates must be signed by, enables mutual TLS.
+    #[arg(long)]
+    tls_client_ca: Option<String>,
+    /// TOML file of the bearer tokens to accept.
+    #[arg(long)]
+    token_file: Option<String>,
+}
+
+impl Args {
+    fn apply(self, config: &mut ServerConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let transport = &mut config.transport;
+        set(&mut config.listen, self.listen);
+        set(&mut config.memory_limit, self.memory_limit);
+        set(&mut config.chunk_size, self.chunk_size);
+        set(&mut config.drain_timeout, self.drain_timeout);
+        set(&mut config.scrub_interval, self.scrub_interval);
+        set(&mut config.non_finite, self.non_finite);
+        set(&mut config.dedup, self.dedup);
+        set(&mut config.reflection, self.reflection);
+        set(&mut config.serving_on_start, self.serving_on_start);
+        set(&mut transport.stream_window, self.stream_window);
+        set(&mut transport.connection_window, self.connection_window);
+        set(&mut transport.max_decoding_message_size, self.max_decoding_message_size);
+        set(&mut transport.max_encoding_message_size, self.max_encoding_message_size);
+        if self.max_concurrent_streams.is_some() {
+            transport.max_concurrent_streams = self.max_concurrent_streams;
+        }
+        if self.concurrency_limit_per_connection.is_some() {
+            transport.concurrency_limit_per_connection = self.concurrency_limit_per_connection;
+        }
+
+        if let (Some(cert_path), Some(key_path)) = (self.tls_cert, self.tls_key) {
+            let client_ca_path = config.tls.take().and_then(|tls| tls.client_ca_path);
+            config.tls = Some(TlsConfig { cert_path, key_path, client_ca_path });
+        }
+        if let Some(client_ca) = self.tls_client_ca {
+            let tls = config.tls.as_mut().ok_or("--tls-client-ca needs a TLS certificate and key")?;
+            tls.client_ca_path = Some(client_ca);
+        }
+        if let Some(token_file) = self.token_file {
+            config.auth = Some(AuthConfig { token_file });
+        }
+        Ok(())
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
 
-const DEFAULT_CACHE_SIZE : u64= 1024 * 1024 * 1024;
-const DEFAULT_SERVER_PORT : &str = "127.0.0.1:50051";
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args: Vec<String> = env::args().collect();
-    let addr = if args.len() > 1 {
-        args[1].clone()
-    } else {
-        DEFAULT_SERVER_PORT.to_string()
+async fn main() -> Result<ExitCode, Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    let check_config = args.check_config;
+    let mut config = match &args.config {
+        Some(path) => ServerConfig::load(path).map_err(|e| format!("failed to read config '{}': {}", path, e))?,
+        None => ServerConfig::default(),
     };
-
-    let cache_size = if args.len() > 2 {
-        args[2].parse::<u64>()
-            .expect("Invalid cache size")
-    } else {
-        DEFAULT_CACHE_SIZE
+    let options = match args.apply(&mut config).and_then(|()| config.server_options()) {
+        Ok(options) => options,
+        Err(e) if check_config => {
+            eprintln!("Invalid config: {}", e);
+            return Ok(ExitCode::FAILURE);
+        }
+        Err(e) => return Err(e),
     };
+    if check_config {
+        print!("{}", config.to_toml()?);
+        return Ok(ExitCode::SUCCESS);
+    }
 
     println!("Starting Redstone cache server...");
-    println!("Address: {}", addr);
+    println!("Address: {}", config.listen);
     println!("Cache size: {} bytes ({:.2} GB)",
-             cache_size,
-             cache_size as f64 / 1024.0 / 1024.0 / 1024.0);
+             config.memory_limit,
+             config.memory_limit as f64 / 1024.0 / 1024.0 / 1024.0);
+    if let Some(interval) = options.scrub_interval {
+        println!("Scrub interval: {:?}", interval);
+    }
+    println!("Non-finite policy: {:?}", options.non_finite);
+    println!("Deduplication: {}", if options.dedup { "on" } else { "off" });
+    println!("TLS: {}", match &options.tls {
+        Some(tls) if tls.client_ca.is_some() => "mutual",
+        Some(_) => "on",
+        None => "off",
+    });
+    if let Some(auth) = &options.auth {
+        println!("Tokens: {:?}", auth);
+    }
 
-    start_server(addr, cache_size).await?;
+    let signal = shutdown_signal()?;
+    let mut server = spawn_server(config.listen.clone(), config.memory_limit, options).await?;
+    println!("Listening on {}", server.local_addr());
+    server.on_shutdown(|cache| println!("Stopped with {} entries cached", cache.get_stats().entries));
+    let outcome = server.run_until(async {
+        signal.await;
+        println!("Shutting down, draining open calls...");
+    }).await?;
+    if let ShutdownOutcome::TimedOut(timeout) = outcome {
+        eprintln!("Drain timeout of {:?} elapsed, closed the remaining connections", timeout);
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    Ok(ExitCode::SUCCESS)
+}
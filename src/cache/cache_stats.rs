@@ -1,11 +1,19 @@
 #[derive(Debug, Clone, PartialEq)]
 pub struct CacheStats {
     pub entries: u64,
+    /// physical bytes held by the cache, each shared payload counted once.
     pub memory_used: u64,
+    /// decoded size of all entries, larger than `memory_used` when entries are compressed, quantized or
+    /// deduplicated.
+    pub logical_bytes: u64,
+    /// stored bytes of entries that share an identical payload with another entry, see `Cache::with_dedup`.
+    pub deduplicated_bytes: u64,
     pub memory_limit: u64,
     pub hits: u64,
     pub misses: u64,
     pub evictions: u64,
+    /// entries dropped by the scrubber because their checksum no longer matched.
+    pub corruptions: u64,
 }
 
 impl CacheStats {
@@ -25,6 +33,11 @@ impl CacheStats {
             self.memory_used as f64 / self.memory_limit as f64
         }
     }
+
+    /// Bytes saved by storing entries encoded rather than raw and by sharing identical payloads.
+    pub fn bytes_saved(&self) -> u64 {
+        self.logical_bytes.saturating_sub(self.memory_used)
+    }
 }
 
 impl From<crate::transport::grpc::client::CacheStats> for CacheStats {
@@ -32,10 +45,13 @@ impl From<crate::transport::grpc::client::CacheStats> for CacheStats {
         Self {
             entries: value.entries,
             memory_used: value.memory_used,
+            logical_bytes: value.logical_bytes,
+            deduplicated_bytes: value.deduplicated_bytes,
             memory_limit: value.memory_limit,
             hits: value.hits,
             misses: value.misses,
             evictions: value.evictions,
+            corruptions: value.corruptions,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,15 @@
+use crate::tensor::meta::DType;
+
+/// Optional behaviour for a single get. `GetOptions::default()` is what a plain get does.
+#[derive(Debug, Clone, Default)]
+pub struct GetOptions {
+    /// ship a compressed tensor as stored and let the client decode it, instead of decoding on the server.
+    pub decode_on_client: bool,
+    /// return sparse tensors as dense row-major tensors. Dense tensors are returned unchanged.
+    pub densify: bool,
+    /// only fetch this row of a ragged tensor, as a dense tensor.
+    pub row: Option<usize>,
+    /// cast the tensor to this dtype before it is returned, the cast runs on the server so only the
+    /// cast bytes are sent over the wire.
+    pub desired_dtype: Option<DType>,
+}
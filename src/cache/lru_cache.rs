@@ -4,11 +4,15 @@ use std::ptr::NonNull;
 use std::sync::{Arc};
 use parking_lot::RwLock;
 pub(crate) use crate::cache::cache_stats::CacheStats;
+use crate::tensor::checksum;
 use crate::tensor::tensor::Tensor;
 use crate::error::cache_error::CacheError;
+use crate::cache::put_options::PutMode;
 static APPROX_LRU: f32 = 0.9;
 pub struct Cache {
     inner: RwLock<CacheInner>,
+    //store byte-identical payloads once, see `Cache::with_dedup`.
+    dedup: bool,
 }
 
 /// its safe to implement this since all interactions with Cache inner happen under RwLock.
@@ -22,28 +26,72 @@ impl Cache {
 
     /// Constructor for the cache.
     pub fn new(max_size: u64) -> Result<Self, CacheError> {
+        Self::build(max_size, false)
+    }
+
+    /// Constructor for a cache that stores byte-identical payloads once. Entries whose stored bytes match
+    /// an existing entry share its payload, which is reference counted across keys and accounted once,
+    /// so only the first copy counts against `max_size`.
+    pub fn with_dedup(max_size: u64) -> Result<Self, CacheError> {
+        Self::build(max_size, true)
+    }
+
+    fn build(max_size: u64, dedup: bool) -> Result<Self, CacheError> {
         if max_size == 0 {
             return Err(CacheError::InvalidSize);
         }
         Ok(Self {
+            dedup,
             inner: RwLock::new(CacheInner {
                 map: HashMap::new(),
+                views: HashMap::new(),
+                views_by_source: HashMap::new(),
+                payloads: dedup.then(HashMap::new),
+                payload_hashes: HashMap::new(),
+                deduplicated_size_bytes: 0,
+                last_version: 0,
                 head: None,
                 tail: None,
                 current_cache_size_bytes: 0,
+                logical_size_bytes: 0,
                 max_cache_size_bytes: max_size,
                 hits: 0,
                 misses: 0,
                 evictions: 0,
+                corruptions: 0,
             })
         })
     }
 
     /// Insert a kv pair into the cache, it fails if the key already exists.
     /// This is to preserve the guarantee of write once read many times, simplifying operations.
-    pub fn put(&self, key: String, tensor: Tensor) -> Result<(), CacheError> {
+    /// Returns the version the tensor was stored under.
+    pub fn put(&self, key: String, tensor: Tensor) -> Result<u64, CacheError> {
+        self.put_with_mode(key, tensor, PutMode::IfAbsent)
+    }
+
+    /// Insert a kv pair into the cache, replacing an existing entry if `mode` allows it. The check and the
+    /// replacement happen under one write lock, so of two puts racing on the same version only one wins.
+    /// A replaced entry is removed along with its views. Returns the version the tensor was stored under.
+    pub fn put_with_mode(&self, key: String, tensor: Tensor, mode: PutMode) -> Result<u64, CacheError> {
+        //hash outside the lock, a raw payload already carries its hash as the checksum.
+        let hash = self.dedup.then(|| {
+            if tensor.encoding().is_raw() {
+                tensor.checksum()
+            } else {
+                checksum::compute(tensor.get_data())
+            }
+        });
         let mut inner = self.inner.write();
-        inner.put(key, tensor)
+        inner.put(key, tensor, hash, mode)
+    }
+
+    /// Stores `key` as a view of `source` with a different shape. The view shares the payload of its
+    /// source without copying it, takes no memory of its own and is removed when its source is deleted
+    /// or evicted. Reading a view counts as a use of its source for eviction.
+    pub fn create_view(&self, source: &str, key: String, shape: Vec<usize>) -> Result<(), CacheError> {
+        let mut inner = self.inner.write();
+        inner.create_view(source, key, shape)
     }
 
     /// Get the value for a given key, it returns None in case no entry exists in the cache for the
@@ -60,10 +108,19 @@ impl Cache {
         inner.remove(key)
     }
 
+    /// Returns the tensor for a given key like `get`, but without counting a hit or miss and without
+    /// moving the entry in the LRU order, so it is evicted as if it had not been read.
+    pub fn peek(&self, key: &str) -> Option<Arc<Tensor>> {
+        let inner = self.inner.read();
+        inner.views.get(key).map(|(_, view)| view)
+            .or_else(|| inner.map.get(key).map(|(tensor, _, _)| tensor))
+            .cloned()
+    }
+
     /// Checks if an entry exists for a given key.
     pub fn exists(&self, key: &str) -> bool {
         let inner = self.inner.read();
-        inner.map.contains_key(key)
+        inner.map.contains_key(key) || inner.views.contains_key(key)
     }
 
     /// Clears the entire cache, all existing entries are dropped.
@@ -76,13 +133,70 @@ impl Cache {
     pub fn stats(&self) -> CacheStats {
         let inner = self.inner.read();
         CacheStats {
-            entries: inner.map.len() as u64,
+            entries: (inner.map.len() + inner.views.len()) as u64,
             memory_used: inner.current_cache_size_bytes,
+            logical_bytes: inner.logical_size_bytes,
+            deduplicated_bytes: inner.deduplicated_size_bytes,
             memory_limit: inner.max_cache_size_bytes,
             hits: inner.hits,
             misses: inner.misses,
             evictions: inner.evictions,
+            corruptions: inner.corruptions,
+        }
+    }
+
+    /// Deletes every entry whose attribute `key` is set to `value`, returning the deleted keys. Views
+    /// of deleted entries are deleted and returned as well.
+    pub fn delete_by_tag(&self, key: &str, value: &str) -> Vec<String> {
+        let mut inner = self.inner.write();
+        let mut matching: Vec<String> = inner.map
+            .iter()
+            .filter(|(_, (tensor, _, _))| tensor.has_tag(key, value))
+            .map(|(k, _)| k.clone())
+            .collect();
+        let views: Vec<String> = matching
+            .iter()
+            .filter_map(|k| inner.views_by_source.get(k))
+            .flatten()
+            .cloned()
+            .collect();
+        for k in &matching {
+            inner.remove(k);
         }
+        matching.extend(views);
+        matching
+    }
+
+    /// Verifies the checksum of every entry and removes the corrupted ones, returning their keys.
+    /// Checksums are recomputed on a snapshot of the entries outside the lock, so reads and writes are
+    /// only blocked while the corrupted keys are removed.
+    pub fn scrub(&self) -> Vec<String> {
+        let snapshot: Vec<(String, Arc<Tensor>)> = {
+            let inner = self.inner.read();
+            inner.map.iter().map(|(key, (tensor, _, _))| (key.clone(), Arc::clone(tensor))).collect()
+        };
+        let corrupted: Vec<_> = snapshot
+            .into_iter()
+            .filter(|(_, tensor)| !tensor.verify_checksum())
+            .collect();
+        if corrupted.is_empty() {
+            return Vec::new();
+        }
+
+        let mut inner = self.inner.write();
+        let mut removed = Vec::with_capacity(corrupted.len());
+        for (key, tensor) in corrupted {
+            if inner.remove_corrupted(&key, &tensor) {
+                removed.push(key);
+            }
+        }
+        removed
+    }
+
+    /// Removes `key` because `tensor`, read from it earlier, turned out to be corrupted, and counts it in
+    /// `CacheStats::corruptions`. Returns false and leaves the key alone if it was deleted or replaced since.
+    pub fn remove_corrupted(&self, key: &str, tensor: &Arc<Tensor>) -> bool {
+        self.inner.write().remove_corrupted(key, tensor)
     }
 
 }
@@ -106,6 +220,15 @@ impl LruNode {
 struct CacheInner {
     /// actual cache
     map: HashMap<String, (Arc<Tensor>, NonNull<LruNode>,u64)>,
+    /// reshaped views of entries in `map`, view key -> (source key, view). Views have no LruNode and
+    /// no size, they live as long as their source.
+    views: HashMap<String, (String, Arc<Tensor>)>,
+    /// source key -> keys of its views.
+    views_by_source: HashMap<String, Vec<String>>,
+    /// content hash -> payloads shared by every entry with identical stored bytes, None when dedup is off.
+    payloads: Option<HashMap<u64, Vec<SharedPayload>>>,
+    /// key -> content hash of its payload, only filled when dedup is on.
+    payload_hashes: HashMap<String, u64>,
 
     /// for least and most recently used
     head: Option<NonNull<LruNode>>,
@@ -114,23 +237,53 @@ struct CacheInner {
     /// for eviction
     current_cache_size_bytes: u64,
     max_cache_size_bytes: u64,
+    /// decoded size of all entries, differs from the stored size for compressed or quantized entries.
+    logical_size_bytes: u64,
+    /// stored size of entries that share a payload with another entry instead of holding their own.
+    deduplicated_size_bytes: u64,
+    /// version given to the last stored entry, never reset so a key never sees a version twice.
+    last_version: u64,
 
     /// metrics
     hits: u64,
     misses: u64,
     evictions: u64,
+    corruptions: u64,
+}
+
+/// A payload stored once for every entry whose stored bytes are identical.
+struct SharedPayload {
+    data: Bytes,
+    refs: usize,
 }
 
 impl CacheInner {
     ///Implements the actual put operation on the cache. Returns CacheError::KeyAlreadyExists in case
-    /// the key already exists in the cache. Returns Ok() otherwise.
-    pub fn put(&mut self, key: String, tensor: Tensor) -> Result<(), CacheError> {
-        if self.map.contains_key(&key) {
-            return Err(CacheError::KeyAlreadyExists);
+    /// the key already exists in the cache and `mode` does not allow replacing it. Returns the new version
+    /// otherwise. `hash` is the content hash of the stored payload when dedup is on.
+    pub fn put(&mut self, key: String, tensor: Tensor, hash: Option<u64>, mode: PutMode) -> Result<u64, CacheError> {
+        let current = self.version_of(&key);
+        match (mode, current) {
+            (PutMode::IfAbsent, Some(_)) => return Err(CacheError::KeyAlreadyExists),
+            (PutMode::IfVersion(_), None) => return Err(CacheError::KeyNotFound(key)),
+            (PutMode::IfVersion(expected), Some(actual)) if expected != actual => {
+                return Err(CacheError::VersionMismatch { expected, actual });
+            }
+            _ => {}
+        }
+        let stored_size = tensor.byte_size() as u64;
+        if current.is_some() {
+            //a replacement that can never fit must not drop the entry it replaces.
+            if stored_size > self.max_cache_size_bytes {
+                return Err(CacheError::OutOfMemory);
+            }
+            self.remove(&key);
         }
+        let shared = hash.and_then(|hash| self.find_payload(hash, tensor.get_data()));
+        //a shared payload is already accounted for, so it never needs room.
+        let tensor_size = if shared.is_some() { 0 } else { stored_size };
         //check if adding the tensor exceeds cache size, if it does ; keep dropping the LRU key
         // till we are able to insert the new key at the head.
-        let tensor_size = tensor.byte_size() as u64;
         while tensor_size + self.current_cache_size_bytes > self.max_cache_size_bytes && self.tail.is_some() {
             //keep evicting LRU key.
             self.evict_key();
@@ -140,17 +293,105 @@ impl CacheInner {
         if self.current_cache_size_bytes + tensor_size > self.max_cache_size_bytes {
             return Err(CacheError::OutOfMemory);
         }
+        self.last_version += 1;
+        let tensor = tensor.with_version(self.last_version);
+        let tensor = match shared {
+            Some(data) => {
+                self.deduplicated_size_bytes += stored_size;
+                tensor.with_shared_payload(data)
+            }
+            None => tensor,
+        };
+        if let Some(hash) = hash {
+            self.retain_payload(&key, hash, tensor.get_data());
+        }
         let node = Box::new(LruNode::new(key.clone()));
 
         let node_ptr = unsafe { NonNull::new_unchecked(Box::into_raw(node)) };
         self.attach_node_to_head(node_ptr);
-        self.map.insert(key, (Arc::new(tensor), node_ptr, tensor_size));
+        self.logical_size_bytes += tensor.logical_byte_size() as u64;
+        self.map.insert(key, (Arc::new(tensor), node_ptr, stored_size));
         self.current_cache_size_bytes += tensor_size;
+        Ok(self.last_version)
+    }
+
+    /// Returns the version of the entry or view stored under `key`.
+    fn version_of(&self, key: &str) -> Option<u64> {
+        self.views.get(key).map(|(_, view)| view.version())
+            .or_else(|| self.map.get(key).map(|(tensor, _, _)| tensor.version()))
+    }
+
+    /// Returns the stored payload whose bytes equal `data`, if any.
+    fn find_payload(&self, hash: u64, data: &Bytes) -> Option<Bytes> {
+        self.payloads.as_ref()?
+            .get(&hash)?
+            .iter()
+            .find(|payload| payload.data == *data)
+            .map(|payload| payload.data.clone())
+    }
+
+    /// Records that `key` holds `data`, which is either a payload returned by `find_payload` or a new one.
+    fn retain_payload(&mut self, key: &str, hash: u64, data: &Bytes) {
+        let Some(payloads) = self.payloads.as_mut() else { return };
+        let bucket = payloads.entry(hash).or_default();
+        match bucket.iter_mut().find(|payload| same_payload(&payload.data, data)) {
+            Some(payload) => payload.refs += 1,
+            None => bucket.push(SharedPayload { data: data.clone(), refs: 1 }),
+        }
+        self.payload_hashes.insert(key.to_string(), hash);
+    }
+
+    /// Drops the reference `key` holds on its payload and returns the bytes this frees, which is zero
+    /// while other entries still share the payload.
+    fn release_payload(&mut self, key: &str, tensor: &Tensor, size: u64) -> u64 {
+        let (Some(payloads), Some(hash)) = (self.payloads.as_mut(), self.payload_hashes.remove(key)) else {
+            return size;
+        };
+        let Some(bucket) = payloads.get_mut(&hash) else { return size };
+        let Some(position) = bucket.iter().position(|payload| same_payload(&payload.data, tensor.get_data())) else {
+            return size;
+        };
+        bucket[position].refs -= 1;
+        if bucket[position].refs > 0 {
+            self.deduplicated_size_bytes -= size;
+            return 0;
+        }
+        bucket.swap_remove(position);
+        if bucket.is_empty() {
+            payloads.remove(&hash);
+        }
+        size
+    }
+
+    /// Implements the view creation for the cache. Views of views share the payload of the original source.
+    fn create_view(&mut self, source: &str, key: String, shape: Vec<usize>) -> Result<(), CacheError> {
+        if self.map.contains_key(&key) || self.views.contains_key(&key) {
+            return Err(CacheError::KeyAlreadyExists);
+        }
+        let (root, base) = match (self.views.get(source), self.map.get(source)) {
+            (Some((root, view)), _) => (root.clone(), Arc::clone(view)),
+            (None, Some((tensor, _, _))) => (source.to_string(), Arc::clone(tensor)),
+            (None, None) => return Err(CacheError::KeyNotFound(source.to_string())),
+        };
+        let view = base.view(shape)?;
+        self.last_version += 1;
+        let view = view.with_version(self.last_version);
+        self.views_by_source.entry(root.clone()).or_default().push(key.clone());
+        self.views.insert(key, (root, Arc::new(view)));
         Ok(())
     }
 
     ///Implements the get operation for the cache.
     fn get(&mut self, key: &str) -> Option<Arc<Tensor>> {
+        //reading a view keeps its source alive.
+        if let Some((source, view)) = self.views.get(key) {
+            let view = Arc::clone(view);
+            let node_ptr = self.map[source].1;
+            self.detach_node(node_ptr);
+            self.attach_node_to_head(node_ptr);
+            self.hits += 1;
+            return Some(view);
+        }
         let entry = self.map.get(key);
         //miss path
         if entry.is_none() {
@@ -169,17 +410,47 @@ impl CacheInner {
         Some(tensor)
     }
 
+    //skips keys that were deleted or replaced while the caller did not hold the lock.
+    fn remove_corrupted(&mut self, key: &str, tensor: &Arc<Tensor>) -> bool {
+        let unchanged = self.views.get(key).map(|(_, view)| view)
+            .or_else(|| self.map.get(key).map(|(current, _, _)| current))
+            .is_some_and(|current| Arc::ptr_eq(current, tensor));
+        if unchanged {
+            self.remove(key);
+            self.corruptions += 1;
+        }
+        unchanged
+    }
+
     fn remove(&mut self, key: &str) -> Option<Arc<Tensor>> {
+        if let Some((source, view)) = self.views.remove(key) {
+            if let Some(keys) = self.views_by_source.get_mut(&source) {
+                keys.retain(|k| k != key);
+                if keys.is_empty() {
+                    self.views_by_source.remove(&source);
+                }
+            }
+            return Some(view);
+        }
         if let Some((tensor, _node_ptr, _node_size)) = self.map.remove(key) {
             self.detach_node(_node_ptr);
             unsafe { Box::from_raw(_node_ptr.as_ptr()) };
-            self.current_cache_size_bytes -= _node_size;
+            self.current_cache_size_bytes -= self.release_payload(key, &tensor, _node_size);
+            self.logical_size_bytes -= tensor.logical_byte_size() as u64;
+            self.remove_views_of(key);
             Some(tensor)
         } else {
             None
         }
     }
 
+    /// Removes every view of `source`, called when the source leaves the cache.
+    fn remove_views_of(&mut self, source: &str) {
+        for key in self.views_by_source.remove(source).unwrap_or_default() {
+            self.views.remove(&key);
+        }
+    }
+
  /// evict least recently used key
     pub fn evict_key(&mut self) {
         if let Some(tail_ptr) = self.tail {
@@ -188,8 +459,10 @@ impl CacheInner {
             if let Some((_tensor,_node_ptr,_node_size)) = self.map.remove(&key) {
                 //reduce size of the cache and modify metrics
                 self.evictions += 1;
-                self.current_cache_size_bytes -= _node_size;
+                self.current_cache_size_bytes -= self.release_payload(&key, &_tensor, _node_size);
+                self.logical_size_bytes -= _tensor.logical_byte_size() as u64;
             }
+            self.remove_views_of(&key);
             self.detach_node(tail_ptr);
             //move tail memory to heap so its dropped at end of the method.
             unsafe {Box::from_raw(tail_ptr.as_ptr())};
@@ -255,12 +528,25 @@ impl CacheInner {
         }
 
         self.map.clear();
+        self.views.clear();
+        self.views_by_source.clear();
+        if let Some(payloads) = self.payloads.as_mut() {
+            payloads.clear();
+        }
+        self.payload_hashes.clear();
+        self.deduplicated_size_bytes = 0;
         self.current_cache_size_bytes = 0;
+        self.logical_size_bytes = 0;
         self.head = None;
         self.tail = None;
     }
 }
 
+//entries sharing a payload hold clones of the same `Bytes`, so comparing the allocation is enough.
+fn same_payload(a: &Bytes, b: &Bytes) -> bool {
+    a.as_ptr() == b.as_ptr() && a.len() == b.len()
+}
+
 impl Drop for CacheInner {
     fn drop(&mut self) {
         self.clear();
@@ -319,6 +605,52 @@ mod tests {
         assert_eq!(result, Err(CacheError::KeyAlreadyExists));
     }
 
+    #[test]
+    fn test_put_modes_and_versions() {
+        let cache = Cache::new(1000).unwrap();
+
+        let first = cache.put("key1".to_string(), make_tensor()).unwrap();
+        assert_eq!(cache.get("key1").unwrap().version(), first);
+        assert_eq!(cache.put("key1".to_string(), make_tensor()), Err(CacheError::KeyAlreadyExists));
+
+        let second = cache.put_with_mode("key1".to_string(), make_tensor(), PutMode::Overwrite).unwrap();
+        assert!(second > first);
+        let stale = cache.put_with_mode("key1".to_string(), make_tensor(), PutMode::IfVersion(first));
+        assert_eq!(stale, Err(CacheError::VersionMismatch { expected: first, actual: second }));
+        let third = cache.put_with_mode("key1".to_string(), make_tensor(), PutMode::IfVersion(second)).unwrap();
+        assert!(third > second);
+        assert_eq!(cache.get("key1").unwrap().version(), third);
+
+        let missing = cache.put_with_mode("key2".to_string(), make_tensor(), PutMode::IfVersion(third));
+        assert_eq!(missing, Err(CacheError::KeyNotFound("key2".to_string())));
+        //a key put again after a delete never reuses an old version.
+        cache.delete("key1");
+        assert!(cache.put("key1".to_string(), make_tensor()).unwrap() > third);
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.memory_used, 64);
+    }
+
+    #[test]
+    fn test_overwrite_drops_views_and_keeps_entry_that_cannot_fit() {
+        let cache = Cache::new(100).unwrap();
+        cache.put("key1".to_string(), make_tensor()).unwrap();
+        cache.create_view("key1", "flat".to_string(), vec![16]).unwrap();
+
+        let oversized = Tensor::new(
+            TensorMeta::new(DType::F32, vec![32], StorageLayout::RowMajor).unwrap(),
+            bytes::Bytes::from(vec![0u8; 128]),
+        ).unwrap();
+        let result = cache.put_with_mode("key1".to_string(), oversized, PutMode::Overwrite);
+        assert_eq!(result, Err(CacheError::OutOfMemory));
+        assert!(cache.exists("key1") && cache.exists("flat"));
+
+        cache.put_with_mode("key1".to_string(), make_tensor(), PutMode::Overwrite).unwrap();
+        assert!(cache.exists("key1"));
+        assert!(!cache.exists("flat"));
+    }
+
     #[test]
     fn test_cache_get_missing_returns_none() {
         let cache = Cache::new(64).unwrap();
@@ -395,6 +727,25 @@ mod tests {
         assert!(cache.get("key3").is_some());
     }
 
+    #[test]
+    fn test_peek_leaves_lru_order_and_stats() {
+        let cache = Cache::new(150).unwrap();
+
+        cache.put("key1".to_string(), make_tensor()).unwrap();
+        cache.put("key2".to_string(), make_tensor()).unwrap();
+
+        //unlike a get, peeking key1 does not save it from eviction.
+        assert!(cache.peek("key1").is_some());
+        assert!(cache.peek("missing").is_none());
+        cache.put("key3".to_string(), make_tensor()).unwrap();
+
+        assert!(cache.peek("key1").is_none());
+        assert!(cache.peek("key2").is_some());
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 0);
+    }
+
     #[test]
     fn test_stats_tracking() {
         let cache = Cache::new(200).unwrap();
@@ -429,6 +780,42 @@ mod tests {
         assert_eq!(stats.entries, 0);
     }
 
+    #[test]
+    fn test_zero_byte_entries_are_accounted() {
+        let cache = Cache::new(64).unwrap();
+        let meta = TensorMeta::new(DType::F32, vec![0], StorageLayout::RowMajor).unwrap();
+        cache.put("empty".to_string(), Tensor::new(meta, bytes::Bytes::new()).unwrap()).unwrap();
+        cache.put("full".to_string(), make_tensor()).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 2);
+        assert_eq!(stats.memory_used, 64);
+
+        cache.delete("empty");
+        assert_eq!(cache.stats().memory_used, 64);
+    }
+
+    #[test]
+    fn test_delete_by_tag() {
+        let cache = Cache::new(1000).unwrap();
+        let tagged = |version: &str| {
+            make_tensor().with_attributes(
+                std::collections::HashMap::from([("model_version".to_string(), version.to_string())])
+            )
+        };
+        cache.put("old1".to_string(), tagged("v1")).unwrap();
+        cache.put("old2".to_string(), tagged("v1")).unwrap();
+        cache.put("new".to_string(), tagged("v2")).unwrap();
+        cache.put("untagged".to_string(), make_tensor()).unwrap();
+
+        let mut deleted = cache.delete_by_tag("model_version", "v1");
+        deleted.sort();
+        assert_eq!(deleted, vec!["old1".to_string(), "old2".to_string()]);
+        assert!(cache.exists("new"));
+        assert!(cache.exists("untagged"));
+        assert_eq!(cache.stats().memory_used, 128);
+    }
+
     #[test]
     fn test_clear() {
         let cache = Cache::new(200).unwrap();
@@ -445,6 +832,41 @@ mod tests {
         assert_eq!(stats.memory_used, 0);
     }
 
+    #[test]
+    fn test_scrub_removes_corrupted_entries() {
+        let cache = Cache::new(200).unwrap();
+        cache.put("good".to_string(), make_tensor()).unwrap();
+
+        let meta = TensorMeta::new(DType::F32, vec![4, 4], StorageLayout::RowMajor).unwrap();
+        let corrupted = Tensor::with_checksum(meta, bytes::Bytes::from(vec![0u8; 64]), 0xdead);
+        cache.put("bad".to_string(), corrupted).unwrap();
+
+        assert_eq!(cache.scrub(), vec!["bad".to_string()]);
+        assert!(cache.exists("good"));
+        assert!(!cache.exists("bad"));
+
+        let stats = cache.stats();
+        assert_eq!(stats.corruptions, 1);
+        assert_eq!(stats.memory_used, 64);
+    }
+
+    #[test]
+    fn test_remove_corrupted_skips_replaced_entries() {
+        let cache = Cache::new(200).unwrap();
+        cache.put("key".to_string(), make_tensor()).unwrap();
+        let stale = cache.get("key").unwrap();
+        cache.put_with_mode("key".to_string(), make_tensor(), PutMode::Overwrite).unwrap();
+
+        assert!(!cache.remove_corrupted("key", &stale));
+        assert!(cache.exists("key"));
+        assert_eq!(cache.stats().corruptions, 0);
+
+        let current = cache.get("key").unwrap();
+        assert!(cache.remove_corrupted("key", &current));
+        assert!(!cache.exists("key"));
+        assert_eq!(cache.stats().corruptions, 1);
+    }
+
     #[test]
     fn test_single_large_tensor_oom() {
         let cache = Cache::new(50).unwrap(); // Only 50 bytes
@@ -454,4 +876,97 @@ mod tests {
         let result = cache.put("huge".to_string(), tensor);
         assert_eq!(result, Err(CacheError::OutOfMemory));
     }
+
+    #[test]
+    fn test_views_share_payload_and_follow_source() {
+        let cache = Cache::new(150).unwrap();
+        cache.put("flat".to_string(), make_tensor()).unwrap();
+        cache.create_view("flat", "cube".to_string(), vec![2, 2, 4]).unwrap();
+        cache.create_view("cube", "row".to_string(), vec![16]).unwrap();
+
+        let (flat, cube) = (cache.get("flat").unwrap(), cache.get("cube").unwrap());
+        assert_eq!(cube.get_metadata().shape(), &[2, 2, 4]);
+        assert_eq!(cube.get_data().as_ptr(), flat.get_data().as_ptr());
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.memory_used, 64);
+        assert_eq!(stats.logical_bytes, 64);
+
+        assert_eq!(cache.create_view("flat", "cube".to_string(), vec![16]), Err(CacheError::KeyAlreadyExists));
+        assert_eq!(cache.put("cube".to_string(), make_tensor()), Err(CacheError::KeyAlreadyExists));
+        assert_eq!(
+            cache.create_view("missing", "view".to_string(), vec![16]),
+            Err(CacheError::KeyNotFound("missing".to_string()))
+        );
+
+        //deleting a view keeps the source, deleting the source drops its remaining views.
+        cache.delete("row");
+        assert!(cache.exists("flat"));
+        cache.delete("flat");
+        assert!(!cache.exists("cube"));
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_view_reads_keep_source_and_eviction_drops_views() {
+        let cache = Cache::new(150).unwrap();
+        cache.put("key1".to_string(), make_tensor()).unwrap();
+        cache.put("key2".to_string(), make_tensor()).unwrap();
+        cache.create_view("key1", "view1".to_string(), vec![16]).unwrap();
+        cache.create_view("key2", "view2".to_string(), vec![16]).unwrap();
+
+        //reading the view makes key1 the most recently used, so key2 is evicted with its view.
+        cache.get("view1");
+        cache.put("key3".to_string(), make_tensor()).unwrap();
+        assert!(cache.exists("key1") && cache.exists("view1"));
+        assert!(!cache.exists("key2") && !cache.exists("view2"));
+        assert_eq!(cache.stats().evictions, 1);
+    }
+
+    fn make_filled_tensor(value: u8) -> Tensor {
+        let meta = TensorMeta::new(DType::U8, vec![64], StorageLayout::RowMajor).unwrap();
+        Tensor::new(meta, bytes::Bytes::from(vec![value; 64])).unwrap()
+    }
+
+    #[test]
+    fn test_dedup_stores_identical_payloads_once() {
+        let cache = Cache::with_dedup(150).unwrap();
+        //three keys fit in 150 bytes because two of them share one payload.
+        cache.put("a".to_string(), make_filled_tensor(1)).unwrap();
+        cache.put("b".to_string(), make_filled_tensor(1)).unwrap();
+        cache.put("c".to_string(), make_filled_tensor(2)).unwrap();
+
+        let (a, b) = (cache.get("a").unwrap(), cache.get("b").unwrap());
+        assert_eq!(a.get_data().as_ptr(), b.get_data().as_ptr());
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.memory_used, stats.logical_bytes), (3, 128, 192));
+        assert_eq!((stats.deduplicated_bytes, stats.bytes_saved()), (64, 64));
+
+        //the shared payload stays until its last key is gone.
+        cache.delete("a");
+        assert_eq!((cache.stats().memory_used, cache.stats().deduplicated_bytes), (128, 0));
+        assert_eq!(cache.get("b").unwrap().get_data().as_ref(), &[1u8; 64]);
+        cache.delete("b");
+        assert_eq!(cache.stats().memory_used, 64);
+
+        //identical payloads are not shared without dedup.
+        let plain = Cache::new(150).unwrap();
+        plain.put("a".to_string(), make_filled_tensor(1)).unwrap();
+        plain.put("b".to_string(), make_filled_tensor(1)).unwrap();
+        assert_eq!(plain.stats().memory_used, 128);
+    }
+
+    #[test]
+    fn test_dedup_eviction_frees_shared_payload_last() {
+        let cache = Cache::with_dedup(130).unwrap();
+        cache.put("a".to_string(), make_filled_tensor(1)).unwrap();
+        cache.put("b".to_string(), make_filled_tensor(1)).unwrap();
+        cache.put("c".to_string(), make_filled_tensor(2)).unwrap();
+
+        //evicting `a` frees nothing since `b` shares its payload, so `b` is evicted as well.
+        cache.put("d".to_string(), make_filled_tensor(3)).unwrap();
+        assert!(!cache.exists("a") && !cache.exists("b"));
+        let stats = cache.stats();
+        assert_eq!((stats.evictions, stats.memory_used, stats.deduplicated_bytes), (2, 128, 0));
+    }
 }
@@ -1,2 +1,5 @@
 pub mod lru_cache;
-pub mod cache_stats;
\ No newline at end of file
+pub mod cache_stats;
+pub mod get_options;
+pub mod put_options;
+pub mod scrubber;
\ No newline at end of file
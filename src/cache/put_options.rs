@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::tensor::codec::Encoding;
+
+/// Optional behaviour for a single put. `PutOptions::default()` is what a plain put does.
+#[derive(Debug, Clone, Default)]
+pub struct PutOptions {
+    /// checksum the writer computed over the payload, the put is rejected if the received bytes
+    /// hash to a different value.
+    pub expected_checksum: Option<u64>,
+    /// compression applied to the payload before it is stored. Memory is accounted by the stored size.
+    pub encoding: Encoding,
+    /// key/value tags stored with the tensor and returned on get, see `TensorCache::delete_by_tag`.
+    pub attributes: HashMap<String, String>,
+    /// what to do with a floating point tensor that contains NaN or infinite values.
+    pub non_finite: NonFinitePolicy,
+    /// whether the put may replace a tensor already stored under the key.
+    pub mode: PutMode,
+}
+
+/// How a put treats a key that is already cached. Every successful put stores the tensor under a new
+/// version, higher than any version the key had before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PutMode {
+    /// only insert a new key, fail with `CacheError::KeyAlreadyExists` if it is cached.
+    #[default]
+    IfAbsent,
+    /// insert the key or replace whatever is cached under it.
+    Overwrite,
+    /// replace the key only if it is cached at exactly this version, fail with `CacheError::KeyNotFound`
+    /// if it is not cached and `CacheError::VersionMismatch` if it is at another version.
+    IfVersion(u64),
+}
+
+/// Attribute set on tensors stored under `NonFinitePolicy::Flag`, its value reads like `nan=2,inf=1`.
+pub const NON_FINITE_ATTRIBUTE: &str = "redstone.non_finite";
+
+/// How a put treats floating point tensors containing NaN or infinities. Integer tensors are never checked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonFinitePolicy {
+    /// store the tensor as is, without scanning it.
+    #[default]
+    Allow,
+    /// fail the put with `CacheError::NonFinite`.
+    Reject,
+    /// store the tensor with the `NON_FINITE_ATTRIBUTE` attribute set.
+    Flag,
+}
+
+impl FromStr for NonFinitePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(Self::Allow),
+            "reject" => Ok(Self::Reject),
+            "flag" => Ok(Self::Flag),
+            other => Err(format!("unknown non-finite policy '{}', expected allow, reject or flag", other)),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+//! Background scrubber that periodically re-verifies the checksum of every cached tensor and drops the
+//! ones whose bytes no longer match, so corrupted payloads are never served. Dropped tensors are counted in
+//! `CacheStats::corruptions`, `spawn_scrubber` also reports every pass to a callback.
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use crate::TensorCache;
+
+/// Outcome of one scrubber pass.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrubPass {
+    /// keys dropped because their checksum no longer matched, empty when every tensor verified.
+    Completed(Vec<String>),
+    /// the pass panicked, the scrubber tries again on the next tick.
+    Failed(String),
+}
+
+/// Spawns the scrubber on the current tokio runtime. Every pass runs on the blocking pool since it
+/// hashes the whole cache, and its outcome is passed to `on_pass`. Abort the returned handle to stop it.
+pub fn spawn_scrubber<F>(cache: Arc<TensorCache>, interval: Duration, mut on_pass: F) -> JoinHandle<()>
+where
+    F: FnMut(ScrubPass) + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        //the first tick completes immediately, skip it so a pass does not run on startup.
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let cache = Arc::clone(&cache);
+            match tokio::task::spawn_blocking(move || cache.scrub()).await {
+                Ok(removed) => on_pass(ScrubPass::Completed(removed)),
+                Err(e) => on_pass(ScrubPass::Failed(e.to_string())),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_passes_are_reported_to_the_callback() {
+        let cache = Arc::new(TensorCache::new(1024).unwrap());
+        let meta = TensorMeta::new(DType::U8, vec![4], StorageLayout::RowMajor).unwrap();
+        cache.put("a".to_string(), meta, vec![1u8; 4].into()).unwrap();
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let scrubber = spawn_scrubber(Arc::clone(&cache), Duration::from_millis(10), move |pass| {
+            let _ = tx.send(pass);
+        });
+        assert_eq!(rx.recv().await, Some(ScrubPass::Completed(vec![])));
+        scrubber.abort();
+        assert_eq!(cache.get_stats().corruptions, 0);
+    }
+}
@@ -5,6 +5,8 @@ use std::{fs, time::Duration};
 use crate::cluster::distributed_client::DistributedClient;
 use super::runtime_config::ClusterClientConfig;
 use crate::cluster::node::Node;
+use crate::transport::grpc::client::DEFAULT_PUT_STREAM_THRESHOLD;
+use crate::transport::grpc::tls::{ClientTlsConfig, TlsIdentity};
 
 #[derive(Debug, Deserialize)]
 pub struct ClusterClientFileConfig {
@@ -12,6 +14,21 @@ pub struct ClusterClientFileConfig {
     pub max_retries: Option<u32>,
     pub timeout_ms: Option<u64>,
     pub virtual_node_count: Option<u32>,
+    pub tls: Option<TlsFileConfig>,
+    /// bearer token for nodes that require authentication.
+    pub token: Option<String>,
+    /// bytes above which puts are streamed, see `ConnectOptions::put_stream_threshold`.
+    pub put_stream_threshold: Option<usize>,
+}
+
+/// PEM files for connecting to the nodes over TLS. `cert_path` and `key_path` are only needed for nodes
+/// that require mutual TLS and must be set together.
+#[derive(Debug, Deserialize)]
+pub struct TlsFileConfig {
+    pub ca_path: String,
+    pub cert_path: Option<String>,
+    pub key_path: Option<String>,
+    pub domain: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,7 +45,8 @@ impl ClusterClientFileConfig {
         Ok(toml::from_str(&content)?)
     }
 
-    pub fn into_runtime(self) -> (Vec<Node>, ClusterClientConfig) {
+    /// Builds the nodes and client config, reading the TLS files if any.
+    pub fn into_runtime(self) -> Result<(Vec<Node>, ClusterClientConfig), Box<dyn std::error::Error>> {
         let nodes = self.nodes
             .into_iter()
             .map(|n| Node::new(n.address, n.name))
@@ -38,8 +56,26 @@ impl ClusterClientFileConfig {
             max_retries: self.max_retries.unwrap_or(3),
             timeout: Duration::from_millis(self.timeout_ms.unwrap_or(5000)),
             virtual_node_count: self.virtual_node_count.unwrap_or(50),
+            tls: self.tls.map(TlsFileConfig::load).transpose()?,
+            token: self.token,
+            put_stream_threshold: self.put_stream_threshold.unwrap_or(DEFAULT_PUT_STREAM_THRESHOLD),
         };
 
-        (nodes, config)
+        Ok((nodes, config))
+    }
+}
+
+impl TlsFileConfig {
+    fn load(self) -> Result<ClientTlsConfig, Box<dyn std::error::Error>> {
+        let identity = match (self.cert_path, self.key_path) {
+            (Some(cert), Some(key)) => Some(TlsIdentity::from_pem_files(cert, key)?),
+            (None, None) => None,
+            _ => return Err("tls.cert_path and tls.key_path must be set together".into()),
+        };
+        Ok(ClientTlsConfig {
+            ca: fs::read(&self.ca_path)?,
+            identity,
+            domain: self.domain,
+        })
     }
 }
\ No newline at end of file
@@ -1,7 +1,16 @@
+use crate::transport::grpc::client::DEFAULT_PUT_STREAM_THRESHOLD;
+use crate::transport::grpc::tls::ClientTlsConfig;
+
 pub struct ClusterClientConfig {
     pub max_retries: u32,
     pub timeout: std::time::Duration,
     pub virtual_node_count: u32,
+    /// connect to every node over TLS, plaintext when None.
+    pub tls: Option<ClientTlsConfig>,
+    /// bearer token sent to nodes that require authentication.
+    pub token: Option<String>,
+    /// payloads larger than this are put with the streaming put, see `ConnectOptions::put_stream_threshold`.
+    pub put_stream_threshold: usize,
 }
 
 impl ClusterClientConfig {
@@ -10,6 +19,9 @@ impl ClusterClientConfig {
             max_retries,
             timeout,
             virtual_node_count,
+            tls: None,
+            token: None,
+            put_stream_threshold: DEFAULT_PUT_STREAM_THRESHOLD,
         }
     }
 
@@ -18,8 +30,30 @@ impl ClusterClientConfig {
             max_retries: 3,
             timeout: std::time::Duration::from_secs(5),
             virtual_node_count: 150,
+            tls: None,
+            token: None,
+            put_stream_threshold: DEFAULT_PUT_STREAM_THRESHOLD,
         }
     }
+
+    /// Connects to every node over TLS with the given settings.
+    pub fn with_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Sends `token` as a bearer token with every call.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Puts payloads larger than `threshold` with the streaming put, it must stay below the nodes'
+    /// `max_decoding_message_size`.
+    pub fn with_put_stream_threshold(mut self, threshold: usize) -> Self {
+        self.put_stream_threshold = threshold;
+        self
+    }
 }
 impl Default for ClusterClientConfig {
     fn default() -> Self {
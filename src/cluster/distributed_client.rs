@@ -11,13 +11,20 @@ use parking_lot::RwLock;
 use std::collections::HashMap;
 use crate::cluster::config::runtime_config::ClusterClientConfig;
 use crate::cluster::node::Node;
-use crate::transport::grpc::client::RemoteCacheClient;
+use crate::transport::grpc::client::{ConnectOptions, RemoteCacheClient, TensorHead};
+use crate::transport::grpc::tensor_stream::TensorStream;
 use crate::cluster::ring::HashRing;
 
 use crate::tensor::tensor::Tensor;
 use crate::error::client_error::ClientError;
-use crate::tensor::meta::TensorMeta;
+use crate::tensor::meta::{DType, TensorMeta};
+use crate::tensor::stats::TensorStats;
 use crate::cache::cache_stats::CacheStats;
+use crate::cache::get_options::GetOptions;
+use crate::cache::put_options::PutOptions;
+
+//the nodes a batch of keys is sent to, each with the positions of the keys it owns.
+type OwnerGroups = Vec<(Arc<Node>, Vec<usize>)>;
 
 pub struct DistributedClient {
     //map servers node name to a single remoteCacheClient instance,
@@ -46,9 +53,13 @@ impl DistributedClient {
     }
 
     pub async fn get(&self, key: &str) ->Result<Option<Arc<Tensor>>, ClientError > {
+        self.get_with_options(key, GetOptions::default()).await
+    }
+
+    pub async fn get_with_options(&self, key: &str, options: GetOptions) ->Result<Option<Arc<Tensor>>, ClientError > {
         /* get a tensor from the cache */
         for trial in 0..self.client_config.max_retries {
-            match self.get_inner(key).await {
+            match self.get_inner(key, options.clone()).await {
                 Ok(data) => return Ok(data),
                 Err(e) if e.is_retryable() && trial < self.client_config.max_retries - 1 => {
                     tokio::time::sleep(std::time::Duration::from_millis(50 * (trial as u64 + 1))).await;
@@ -60,11 +71,42 @@ impl DistributedClient {
         Err(ClientError::MaxRetriesExceeded)
     }
 
-    pub async fn put(&self, key: String, meta: TensorMeta, data: Vec<u8>) -> Result<(), ClientError > {
+    /// Streams a tensor chunk by chunk from the node that owns it, see `RemoteCacheClient::get_stream`.
+    /// Opening the stream is retried and bounded by the timeout, reading its chunks is not.
+    pub async fn get_stream(&self, key: &str) -> Result<Option<TensorStream>, ClientError> {
+        for trial in 0..self.client_config.max_retries {
+            match self.get_stream_inner(key).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) if e.is_retryable() && trial < self.client_config.max_retries - 1 => {
+                    tokio::time::sleep(std::time::Duration::from_millis(50 * (trial as u64 + 1))).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(ClientError::MaxRetriesExceeded)
+    }
+
+    /// Fetches one row of a ragged tensor as a dense tensor.
+    pub async fn get_row(&self, key: &str, row: usize) ->Result<Option<Arc<Tensor>>, ClientError > {
+        self.get_with_options(key, GetOptions { row: Some(row), ..Default::default() }).await
+    }
+
+    /// Fetches a tensor cast to `dtype` by the node that owns it, see `GetOptions::desired_dtype`.
+    pub async fn get_as(&self, key: &str, dtype: DType) ->Result<Option<Arc<Tensor>>, ClientError > {
+        self.get_with_options(key, GetOptions { desired_dtype: Some(dtype), ..Default::default() }).await
+    }
+
+    /// Puts a tensor on the node that owns its key and returns the version it was stored under.
+    pub async fn put(&self, key: String, meta: TensorMeta, data: Vec<u8>) -> Result<u64, ClientError > {
+        self.put_with_options(key, meta, data, PutOptions::default()).await
+    }
+
+    pub async fn put_with_options(&self, key: String, meta: TensorMeta, data: Vec<u8>, options: PutOptions) -> Result<u64, ClientError > {
         /* inserts a key and tensor specified by the user */
         for trial in 0..self.client_config.max_retries {
-            match self.put_inner(&*key, meta.clone(), data.clone()).await {
-                Ok(..) => return Ok(()),
+            match self.put_inner(&*key, meta.clone(), data.clone(), options.clone()).await {
+                Ok(version) => return Ok(version),
                 Err(e) if e.is_retryable() && trial < self.client_config.max_retries - 1 => {
                     tokio::time::sleep(std::time::Duration::from_millis(50 * (trial as u64 + 1))).await;
                     continue;
@@ -90,6 +132,127 @@ impl DistributedClient {
         Err(ClientError::MaxRetriesExceeded)
     }
 
+    /// Deletes every tensor whose attribute `key` is set to `value` on all nodes of the ring,
+    /// returning the deleted keys.
+    pub async fn delete_by_tag(&self, key: &str, value: &str) -> Result<Vec<String>, ClientError> {
+        let nodes = self.ring.read().nodes();
+        let mut deleted = Vec::new();
+        for node in nodes {
+            let client = self.get_or_create_client(&node).await?;
+            let keys = tokio::time::timeout(
+                self.client_config.timeout,
+                client.delete_by_tag(key.to_string(), value.to_string()),
+            )
+                .await
+                .map_err(|_| ClientError::Timeout)??;
+            deleted.extend(keys);
+        }
+        Ok(deleted)
+    }
+
+    /// Stores `key` as a reshaped view of `source` on the node that owns both, sharing the source payload.
+    /// Both keys must be owned by the same node, which is guaranteed when they share a `{hash tag}` such as
+    /// `{emb}:flat` and `{emb}:3d`. Returns `ClientError::CrossNodeView` otherwise.
+    pub async fn create_view(&self, source: &str, key: &str, shape: Vec<usize>) -> Result<(), ClientError> {
+        let node = {
+            let ring = self.ring.read();
+            let source_node = ring.get_node(source).ok_or(ClientError::NoNodesAvailable)?;
+            let view_node = ring.get_node(key).ok_or(ClientError::NoNodesAvailable)?;
+            if source_node.name != view_node.name {
+                return Err(ClientError::CrossNodeView { source_key: source.to_string(), key: key.to_string() });
+            }
+            source_node.clone()
+        };
+        let client = self.get_or_create_client(&node).await?;
+        tokio::time::timeout(
+            self.client_config.timeout,
+            client.create_view(source.to_string(), key.to_string(), shape),
+        )
+            .await
+            .map_err(|_| ClientError::Timeout)?
+    }
+
+    /// Returns min, max, mean, std and the NaN and infinity counts of a tensor, computed by the node that
+    /// owns it. Returns None if the key is not cached.
+    pub async fn describe(&self, key: &str) -> Result<Option<TensorStats>, ClientError> {
+        let node = self.ring.read().get_node(key).ok_or(ClientError::NoNodesAvailable)?.clone();
+        let client = self.get_or_create_client(&node).await?;
+        tokio::time::timeout(self.client_config.timeout, client.describe(key.to_string()))
+            .await
+            .map_err(|_| ClientError::Timeout)?
+    }
+
+    /// Returns the metadata, sizes and attributes of a tensor from the node that owns it, without fetching
+    /// its payload. Returns None if the key is not cached. Only counts as a use for eviction if `touch` is set.
+    pub async fn head(&self, key: &str, touch: bool) -> Result<Option<TensorHead>, ClientError> {
+        let node = self.ring.read().get_node(key).ok_or(ClientError::NoNodesAvailable)?.clone();
+        let client = self.get_or_create_client(&node).await?;
+        tokio::time::timeout(self.client_config.timeout, client.head(key.to_string(), touch))
+            .await
+            .map_err(|_| ClientError::Timeout)?
+    }
+
+    /// Fetches many keys at once, with one `MultiGet` per owning node sent concurrently. Returns one result
+    /// per key in the order of `keys`, None for a key that is not cached. Fails as a whole if a node cannot
+    /// be reached in time. Batches are not retried.
+    pub async fn multi_get(&self, keys: &[String]) -> Result<Vec<Result<Option<Arc<Tensor>>, ClientError>>, ClientError> {
+        self.multi_get_with_options(keys, GetOptions::default()).await
+    }
+
+    /// Fetches many keys at once as `multi_get` does, applying `options` to every key as `get_with_options`
+    /// does.
+    pub async fn multi_get_with_options(&self, keys: &[String], options: GetOptions) -> Result<Vec<Result<Option<Arc<Tensor>>, ClientError>>, ClientError> {
+        let groups = self.group_by_owner(keys.iter().map(String::as_str))?;
+        let options = &options;
+        let batches = groups.into_iter().map(|(node, indices)| async move {
+            let client = self.get_or_create_client(&node).await?;
+            let batch = indices.iter().map(|&index| keys[index].clone()).collect();
+            let results = tokio::time::timeout(self.client_config.timeout, client.multi_get_with_options(batch, options.clone()))
+                .await
+                .map_err(|_| ClientError::Timeout)??;
+            Ok::<_, ClientError>(indices.into_iter().zip(results))
+        });
+        let batches = futures::future::try_join_all(batches).await?;
+        Ok(in_request_order(keys.len(), batches.into_iter().flatten()))
+    }
+
+    /// Puts many tensors at once, with one `MultiPut` per owning node sent concurrently. Returns the stored
+    /// version of each entry in order, a key that already exists fails alone. Fails as a whole if a node cannot be
+    /// reached in time. Batches are not retried, since a retried put of an inserted key would fail.
+    pub async fn multi_put(&self, entries: Vec<(String, TensorMeta, Vec<u8>)>) -> Result<Vec<Result<u64, ClientError>>, ClientError> {
+        let count = entries.len();
+        let groups = self.group_by_owner(entries.iter().map(|(key, _, _)| key.as_str()))?;
+        let mut entries: Vec<_> = entries.into_iter().map(Some).collect();
+        let batches = groups.into_iter().map(|(node, indices)| {
+            let batch: Vec<_> = indices.iter().filter_map(|&index| entries[index].take()).collect();
+            async move {
+                let client = self.get_or_create_client(&node).await?;
+                let results = tokio::time::timeout(self.client_config.timeout, client.multi_put(batch))
+                    .await
+                    .map_err(|_| ClientError::Timeout)??;
+                Ok::<_, ClientError>(indices.into_iter().zip(results))
+            }
+        });
+        let batches = futures::future::try_join_all(batches).await?;
+        Ok(in_request_order(count, batches.into_iter().flatten()))
+    }
+
+    /// Deletes many keys at once, with one `MultiDelete` per owning node sent concurrently. Returns, in the
+    /// order of `keys`, whether each key existed.
+    pub async fn multi_delete(&self, keys: &[String]) -> Result<Vec<bool>, ClientError> {
+        let groups = self.group_by_owner(keys.iter().map(String::as_str))?;
+        let batches = groups.into_iter().map(|(node, indices)| async move {
+            let client = self.get_or_create_client(&node).await?;
+            let batch = indices.iter().map(|&index| keys[index].clone()).collect();
+            let deleted = tokio::time::timeout(self.client_config.timeout, client.multi_delete(batch))
+                .await
+                .map_err(|_| ClientError::Timeout)??;
+            Ok::<_, ClientError>(indices.into_iter().zip(deleted))
+        });
+        let batches = futures::future::try_join_all(batches).await?;
+        Ok(in_request_order(keys.len(), batches.into_iter().flatten()))
+    }
+
     pub async fn get_per_server_stats(&self) -> Result<Vec<CacheStats>, ClientError> {
         let clients: Vec<_> = {
             let guard = self.clients.read();
@@ -112,34 +275,43 @@ impl DistributedClient {
         Ok(stats_vec)
     }
 
-    async fn get_inner(&self,key: &str) ->Result<Option<Arc<Tensor>>, ClientError> {
+    async fn get_inner(&self,key: &str, options: GetOptions) ->Result<Option<Arc<Tensor>>, ClientError> {
         /* get which node to send the query to from ring.rs, then send it to the appropriate client */
         /* from self.clients */
-        let ring  = self.ring.read();
-        let selected_node = ring.get_node(key)
+        //the ring guard is released before awaiting, it is not Send.
+        let selected_node = self.ring.read()
+                                        .get_node(key)
                                         .ok_or(ClientError::NoNodesAvailable)?
                                         .clone();
         let mut client = self.get_or_create_client(&selected_node).await?;
 
         let result = tokio::time::timeout(
             self.client_config.timeout,
-            client.get(key.parse().unwrap()),
+            client.get_with_options(key.parse().unwrap(), options),
         )
             .await
             .map_err(|_| ClientError::Timeout)?;
         result
     }
 
-    async fn put_inner(&self, key: &str, meta: TensorMeta, data: Vec<u8>) ->Result<(), ClientError> {
-        let ring  = self.ring.read();
-        let selected_node = ring.get_node(key)
+    async fn get_stream_inner(&self, key: &str) -> Result<Option<TensorStream>, ClientError> {
+        let node = self.ring.read().get_node(key).ok_or(ClientError::NoNodesAvailable)?.clone();
+        let client = self.get_or_create_client(&node).await?;
+        tokio::time::timeout(self.client_config.timeout, client.get_stream(key.to_string()))
+            .await
+            .map_err(|_| ClientError::Timeout)?
+    }
+
+    async fn put_inner(&self, key: &str, meta: TensorMeta, data: Vec<u8>, options: PutOptions) ->Result<u64, ClientError> {
+        let selected_node = self.ring.read()
+            .get_node(key)
             .ok_or(ClientError::NoNodesAvailable)?
             .clone();
         let mut client = self.get_or_create_client(&selected_node).await?;
 
         let result = tokio::time::timeout(
             self.client_config.timeout,
-            client.put(key.to_string(), meta, data.clone()),
+            client.put_with_options(key.to_string(), meta, data.clone(), options),
         )
             .await
             .map_err(|_| ClientError::Timeout)?;
@@ -147,8 +319,8 @@ impl DistributedClient {
     }
 
     async fn delete_inner(&self,key: &str) ->Result<(), ClientError> {
-        let ring  = self.ring.read();
-        let selected_node = ring.get_node(key)
+        let selected_node = self.ring.read()
+            .get_node(key)
             .ok_or(ClientError::NoNodesAvailable)?
             .clone();
         let mut client = self.get_or_create_client(&selected_node).await?;
@@ -162,6 +334,34 @@ impl DistributedClient {
         result
     }
 
+    /// Probes the health service of every node in the ring. A node is healthy if it answers that it is
+    /// serving within the timeout, nodes that cannot be reached are reported unhealthy.
+    pub async fn check_health(&self) -> Vec<(Node, bool)> {
+        let nodes = self.ring.read().nodes();
+        let probes = nodes.into_iter().map(|node| async move {
+            let probe = async {
+                let client = self.get_or_create_client(&node).await?;
+                client.health().await
+            };
+            let healthy = matches!(tokio::time::timeout(self.client_config.timeout, probe).await, Ok(Ok(true)));
+            (Node::clone(&node), healthy)
+        });
+        futures::future::join_all(probes).await
+    }
+
+    /// Removes the nodes that fail `check_health` from the ring, so their keys are routed to the remaining
+    /// nodes, and returns them. Entries cached on a removed node are not moved, see `remove_node`.
+    pub async fn remove_unhealthy_nodes(&self) -> Result<Vec<Node>, ClientError> {
+        let mut removed = Vec::new();
+        for (node, healthy) in self.check_health().await {
+            if !healthy {
+                self.remove_node(node.clone()).await?;
+                removed.push(node);
+            }
+        }
+        Ok(removed)
+    }
+
     pub async fn add_node(&self, node: Node) {
         let mut ring = self.ring.write();
         ring.add_node(Arc::from(node));
@@ -180,12 +380,28 @@ impl DistributedClient {
     }
 
     // helper functions
+    //groups the positions of `keys` by the node that owns them, under a single read of the ring.
+    fn group_by_owner<'a>(&self, keys: impl Iterator<Item = &'a str>) -> Result<OwnerGroups, ClientError> {
+        let ring = self.ring.read();
+        let mut groups: HashMap<String, (Arc<Node>, Vec<usize>)> = HashMap::new();
+        for (index, key) in keys.enumerate() {
+            let node = ring.get_node(key).ok_or(ClientError::NoNodesAvailable)?;
+            groups.entry(node.name.clone()).or_insert_with(|| (Arc::clone(node), Vec::new())).1.push(index);
+        }
+        Ok(groups.into_values().collect())
+    }
+
     async fn get_or_create_client(&self, node: &Node) -> Result<RemoteCacheClient, ClientError> {
         if let Some(client) = self.clients.read().get(&node.name) {
             //happy path
             return Ok(client.clone());
         }
-        let new_client = RemoteCacheClient::connect(node.address.clone()).await?;
+        let options = ConnectOptions {
+            tls: self.client_config.tls.clone(),
+            token: self.client_config.token.clone(),
+            put_stream_threshold: self.client_config.put_stream_threshold,
+        };
+        let new_client = RemoteCacheClient::connect_with_options(node.address.clone(), options).await?;
 
         let mut clients = self.clients.write();
 
@@ -199,6 +415,15 @@ impl DistributedClient {
 
 
 }
+//places per-batch results back at the positions of the request, every position is answered by one batch.
+fn in_request_order<T>(count: usize, results: impl Iterator<Item = (usize, T)>) -> Vec<T> {
+    let mut ordered: Vec<Option<T>> = (0..count).map(|_| None).collect();
+    for (index, result) in results {
+        ordered[index] = Some(result);
+    }
+    ordered.into_iter().map(|result| result.expect("every key belongs to one batch")).collect()
+}
+
 #[cfg(test)]
 //these tests only test basic code functionality. integration tests are in distributed_client_integration.rs
 mod tests {
@@ -225,5 +450,20 @@ mod tests {
 
         assert!(selected.is_some());
     }
-}
 
+    #[test]
+    fn test_operation_futures_are_send() {
+        /* the ring lock must not be held across an await, so that operations can run on spawned tasks */
+        fn assert_send<T: Send>(_: T) {}
+        let client = DistributedClient::new_default(vec![Node::new("node1".to_string(), "127.0.0.1:50051".to_string())]);
+        let meta = TensorMeta::new(crate::tensor::meta::DType::U8, vec![1], crate::tensor::meta::StorageLayout::RowMajor).unwrap();
+        let keys = vec!["key".to_string()];
+
+        assert_send(client.get("key"));
+        assert_send(client.put("key".to_string(), meta.clone(), vec![0]));
+        assert_send(client.delete("key"));
+        assert_send(client.multi_get(&keys));
+        assert_send(client.multi_put(vec![("key".to_string(), meta, vec![0])]));
+        assert_send(client.multi_delete(&keys));
+    }
+}
@@ -26,12 +26,14 @@ impl HashRing {
         }
     }
 
+    /// Returns the node that owns `key`. Keys containing a non empty `{tag}` are placed by the tag alone,
+    /// so that related keys such as `{emb}:flat` and `{emb}:3d` are owned by the same node.
     pub fn get_node(&self,key: &str)  -> Option<&Arc<Node>> {
         //hash the key and go in a clockwise order in the ring, till u find a node.
         if self.ring.is_empty() {
             return None;
         }
-        let key_hash = self.hasher.hash(key);
+        let key_hash = self.hasher.hash(hash_tag(key));
         let node = {
             self.ring
                 .range(key_hash..)
@@ -69,11 +71,33 @@ impl HashRing {
         removed_any
     }
 
+    /// Returns every distinct node on the ring.
+    pub fn nodes(&self) -> Vec<Arc<Node>> {
+        let mut nodes: Vec<Arc<Node>> = Vec::new();
+        for node in self.ring.values() {
+            if !nodes.iter().any(|n| n.name == node.name) {
+                nodes.push(Arc::clone(node));
+            }
+        }
+        nodes
+    }
+
     pub fn len(&self) -> usize {
         self.ring.len()
     }
 }
 
+/// Returns the part of `key` between its first `{` and the next `}`, or the whole key if there is no
+/// such non empty tag. This follows the hash tag convention of redis cluster.
+fn hash_tag(key: &str) -> &str {
+    if let Some(start) = key.find('{')
+        && let Some(len) = key[start + 1..].find('}')
+        && len > 0 {
+        return &key[start + 1..start + 1 + len];
+    }
+    key
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +117,22 @@ mod tests {
         ring.add_node(Arc::new(node));
         assert_eq!(ring.len(), 3);
     }
+
+    #[test]
+    fn test_hash_tags_colocate_keys() {
+        assert_eq!(hash_tag("{emb}:flat"), "emb");
+        assert_eq!(hash_tag("user:{42}:{x}"), "42");
+        assert_eq!(hash_tag("{}:flat"), "{}:flat");
+        assert_eq!(hash_tag("plain"), "plain");
+
+        let mut ring = HashRing::new(50);
+        for i in 0..4 {
+            ring.add_node(Arc::new(Node::new(format!("http://127.0.0.1:500{}", i), format!("node{}", i))));
+        }
+        for i in 0..100 {
+            let flat = ring.get_node(&format!("{{batch{}}}:flat", i)).unwrap();
+            let shaped = ring.get_node(&format!("{{batch{}}}:shaped", i)).unwrap();
+            assert_eq!(flat.name, shaped.name);
+        }
+    }
 }
\ No newline at end of file
@@ -1,12 +1,13 @@
 use thiserror::Error;
+use crate::error::tensor_error::TensorError;
 
 #[derive(Error, Debug,PartialEq)]
 pub enum CacheError {
     #[error("Key already exists in cache")]
     KeyAlreadyExists,
 
-    #[error("Invalid tensor")]
-    InvalidTensor,
+    #[error("Invalid tensor: {0}")]
+    InvalidTensor(#[from] TensorError),
 
     #[error("Invalid tensor size")]
     InvalidSize,
@@ -14,6 +15,27 @@ pub enum CacheError {
     #[error("Out of memory, increase cache size to insert larger tensors")]
     OutOfMemory,
 
-    #[error("Invalid tensor metadata")]
-    InvalidTensorMetadata,
+    #[error("Tensor checksum mismatch, expected {expected:#018x} but payload hashes to {actual:#018x}")]
+    ChecksumMismatch { expected: u64, actual: u64 },
+
+    #[error("Failed to encode or decode tensor payload: {0}")]
+    Codec(String),
+
+    #[error("Unsupported encoding: {0}")]
+    UnsupportedEncoding(String),
+
+    #[error("Key not found in cache: {0}")]
+    KeyNotFound(String),
+
+    #[error("Tensor is not ragged")]
+    NotRagged,
+
+    #[error("Row {row} out of range for a ragged tensor with {rows} rows")]
+    RowOutOfRange { row: usize, rows: usize },
+
+    #[error("Key is at version {actual}, expected version {expected}")]
+    VersionMismatch { expected: u64, actual: u64 },
+
+    #[error("Tensor contains {nan} NaN and {inf} infinite values")]
+    NonFinite { nan: u64, inf: u64 },
 }
\ No newline at end of file
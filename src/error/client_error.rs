@@ -1,4 +1,7 @@
 use thiserror::Error;
+use crate::error::tensor_error::TensorError;
+use crate::proto::error_detail::Kind;
+use crate::transport::grpc::error_details;
 
 #[derive(Error, Debug)]
 pub enum ClientError {
@@ -15,10 +18,41 @@ pub enum ClientError {
     Transport(#[from] tonic::transport::Error),
 
     #[error("gRPC status error")]
-    GrpcStatus(#[from] tonic::Status),
+    GrpcStatus(tonic::Status),
+
+    #[error("Invalid tensor: {0}")]
+    InvalidTensor(#[from] TensorError),
 
     #[error("Server error: {0}")]
     ServerError(String),
+
+    #[error("View {key} and its source {source_key} are owned by different nodes, give both keys the same {{hash tag}}")]
+    CrossNodeView { source_key: String, key: String },
+
+    #[error("Checksum mismatch for key {key}, expected {expected:#018x} but received payload hashes to {actual:#018x}")]
+    ChecksumMismatch { key: String, expected: u64, actual: u64 },
+
+    #[error("Unauthenticated: {0}")]
+    Unauthenticated(String),
+
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
+    #[error("Key already exists: {0}")]
+    KeyAlreadyExists(String),
+
+    /// both sizes are zero when the server did not report them.
+    #[error("Cache is full, {requested_bytes} bytes do not fit a cache of {memory_limit} bytes")]
+    OutOfMemory { requested_bytes: u64, memory_limit: u64 },
+
+    #[error("Key {key} is at version {actual}, expected version {expected}")]
+    VersionMismatch { key: String, expected: u64, actual: u64 },
+
+    #[error("Tensor rejected for key {key}: {reason}")]
+    TensorRejected { key: String, reason: String },
+
+    #[error("Server unavailable: {0}")]
+    Unavailable(String),
 }
 
 impl ClientError {
@@ -26,13 +60,53 @@ impl ClientError {
         match self {
             ClientError::Timeout => true,
             ClientError::Transport(_) => true,
+            ClientError::Unavailable(_) => true,
+            //the stored bytes were verified on put, a mismatch on read means the transfer was corrupted.
+            ClientError::ChecksumMismatch { .. } => true,
             ClientError::GrpcStatus(status) => matches!(
                 status.code(),
                 tonic::Code::Unavailable
                     | tonic::Code::DeadlineExceeded
                     | tonic::Code::Internal
+                    | tonic::Code::DataLoss
             ),
             _ => false,
         }
     }
-}
\ No newline at end of file
+}
+
+//statuses that carry an `ErrorDetail` are surfaced as the matching variant, the rest by code, so a bare status
+//from a proxy or another server still maps to the typed variant where its code has one.
+impl From<tonic::Status> for ClientError {
+    fn from(status: tonic::Status) -> Self {
+        match status.code() {
+            tonic::Code::Unauthenticated => return ClientError::Unauthenticated(status.message().to_string()),
+            tonic::Code::PermissionDenied => return ClientError::PermissionDenied(status.message().to_string()),
+            tonic::Code::Unavailable => return ClientError::Unavailable(status.message().to_string()),
+            _ => {}
+        }
+        match error_details::error_detail(&status) {
+            Some(Kind::Tensor(_)) => match error_details::tensor_error(&status) {
+                Some(error) => ClientError::InvalidTensor(error),
+                None => ClientError::GrpcStatus(status),
+            },
+            Some(Kind::KeyExists(detail)) => ClientError::KeyAlreadyExists(detail.key),
+            Some(Kind::OutOfMemory(detail)) => ClientError::OutOfMemory {
+                requested_bytes: detail.requested_bytes,
+                memory_limit: detail.memory_limit,
+            },
+            Some(Kind::VersionMismatch(detail)) => ClientError::VersionMismatch {
+                key: detail.key,
+                expected: detail.expected,
+                actual: detail.actual,
+            },
+            Some(Kind::RejectedTensor(detail)) => ClientError::TensorRejected { key: detail.key, reason: detail.reason },
+            None => match status.code() {
+                tonic::Code::AlreadyExists => ClientError::KeyAlreadyExists(status.message().to_string()),
+                tonic::Code::ResourceExhausted => ClientError::OutOfMemory { requested_bytes: 0, memory_limit: 0 },
+                _ => ClientError::GrpcStatus(status),
+            },
+        }
+    }
+}
+
@@ -0,0 +1,31 @@
+use thiserror::Error;
+use crate::error::cache_error::CacheError;
+use crate::error::client_error::ClientError;
+
+/// Errors raised while moving tensors between redstone and on-disk tensor formats.
+#[derive(Error, Debug)]
+pub enum InteropError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid file format: {0}")]
+    Format(String),
+
+    #[error("Unsupported dtype: {0}")]
+    UnsupportedDType(String),
+
+    #[error("Unsupported storage layout for key: {0}")]
+    UnsupportedLayout(String),
+
+    #[error("Invalid tensor {name}: {reason}")]
+    InvalidTensor { name: String, reason: String },
+
+    #[error("Key not found in cache: {0}")]
+    KeyNotFound(String),
+
+    #[error("Cache error: {0}")]
+    Cache(#[from] CacheError),
+
+    #[error("Client error: {0}")]
+    Client(#[from] ClientError),
+}
@@ -1,2 +1,4 @@
 pub mod cache_error;
-pub mod client_error;
\ No newline at end of file
+pub mod client_error;
+pub mod interop_error;
+pub mod tensor_error;
\ No newline at end of file
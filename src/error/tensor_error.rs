@@ -0,0 +1,51 @@
+use thiserror::Error;
+
+/// Reasons a tensor or its metadata is rejected. Dimensions, indices and sizes are reported so that
+/// callers can tell exactly which part of a tensor is invalid.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TensorError {
+    #[error("Tensor size overflows usize")]
+    Overflow,
+
+    #[error("Data length does not match the metadata, expected {expected} bytes but got {actual}")]
+    LengthMismatch { expected: usize, actual: usize },
+
+    #[error("Shape must have at least one dimension")]
+    EmptyShape,
+
+    #[error("Dimension {index} must not be zero")]
+    ZeroDim { index: usize },
+
+    #[error("Expected {expected} dimensions but got {actual}")]
+    RankMismatch { expected: usize, actual: usize },
+
+    #[error("Unsupported dtype: {0}")]
+    UnsupportedDType(String),
+
+    #[error("Unsupported storage layout: {0}")]
+    UnsupportedLayout(String),
+
+    #[error("Unsupported tensor format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("{nnz} sparse values exceed the {elements} elements of the dense shape")]
+    TooManyValues { nnz: usize, elements: usize },
+
+    #[error("Index {index} out of bounds for dimension {dim} of size {extent}")]
+    IndexOutOfBounds { dim: usize, index: usize, extent: usize },
+
+    #[error("Negative index or offset at position {position}")]
+    NegativeIndex { position: usize },
+
+    #[error("Duplicate sparse coordinates")]
+    DuplicateIndex,
+
+    #[error("Column indices of row {row} are not sorted and distinct")]
+    UnsortedIndices { row: usize },
+
+    #[error("Offsets must start at 0, never decrease and end at the number of values, violated at position {position}")]
+    InvalidOffsets { position: usize },
+
+    #[error("Dense tensor of {size} bytes exceeds the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+}
@@ -0,0 +1,399 @@
+//! Readers and writers for NumPy `.npy` arrays and `.npz` archives.
+//! A `.npy` file is the magic string `\x93NUMPY`, a format version, a little endian header length and a
+//! python dict literal header of the form `{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }`,
+//! followed by the raw array bytes. `fortran_order` maps onto `StorageLayout::ColumnMajor`.
+//! A `.npz` file is a zip archive holding one `.npy` member per array, stored or deflated.
+//! Only little endian (or byte sized) dtypes that redstone can store are supported.
+
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use bytes::Bytes;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::TensorCache;
+use crate::cluster::distributed_client::DistributedClient;
+use crate::error::interop_error::InteropError;
+use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+use crate::tensor::tensor::Tensor;
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+/// header (including magic, version and length prefix) is padded so the data is aligned to this.
+const NPY_ALIGNMENT: usize = 64;
+
+/// A single array parsed from a `.npy` buffer.
+pub struct NpyArray {
+    pub meta: TensorMeta,
+    pub data: Bytes,
+}
+
+/// Parses a `.npy` buffer. The returned data is a zero-copy slice of `buffer`.
+pub fn read_npy(buffer: Bytes) -> Result<NpyArray, InteropError> {
+    if buffer.len() < 10 || &buffer[..6] != NPY_MAGIC {
+        return Err(InteropError::Format("missing NumPy magic string".into()));
+    }
+    let major = buffer[6];
+    //version 1 uses a 2 byte header length, versions 2 and 3 a 4 byte one.
+    let (header_len, header_start) = match major {
+        1 => (u16::from_le_bytes([buffer[8], buffer[9]]) as usize, 10),
+        2 | 3 if buffer.len() >= 12 => (u32::from_le_bytes([buffer[8], buffer[9], buffer[10], buffer[11]]) as usize, 12),
+        _ => return Err(InteropError::Format(format!("unsupported npy version {}", major))),
+    };
+    let data_start = header_start + header_len;
+    if buffer.len() < data_start {
+        return Err(InteropError::Format("truncated npy header".into()));
+    }
+    let header = std::str::from_utf8(&buffer[header_start..data_start])
+        .map_err(|_| InteropError::Format("npy header is not valid text".into()))?;
+    let header = parse_header(header)?;
+
+    let dtype = descr_to_dtype(&header.descr)?;
+    let layout = if header.fortran_order { StorageLayout::ColumnMajor } else { StorageLayout::RowMajor };
+    let meta = TensorMeta::new(dtype, header.shape, layout)
+        .map_err(|e| InteropError::InvalidTensor { name: "npy".into(), reason: e.to_string() })?;
+    let expected = meta.total_byte_size()
+        .map_err(|e| InteropError::InvalidTensor { name: "npy".into(), reason: e.to_string() })?;
+    if buffer.len() - data_start != expected {
+        return Err(InteropError::InvalidTensor {
+            name: "npy".into(),
+            reason: format!("expected {} data bytes, found {}", expected, buffer.len() - data_start),
+        });
+    }
+    Ok(NpyArray { meta, data: buffer.slice(data_start..) })
+}
+
+/// Serializes one tensor as a `.npy` buffer. BF16 tensors are rejected, numpy has no bfloat16 dtype.
+pub fn write_npy(meta: &TensorMeta, data: &[u8]) -> Result<Vec<u8>, InteropError> {
+    let shape = match meta.shape() {
+        [dim] => format!("({},)", dim),
+        dims => format!("({})", dims.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    let fortran_order = if *meta.layout() == StorageLayout::ColumnMajor { "True" } else { "False" };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': {}, 'shape': {}, }}",
+        dtype_to_descr(meta.dtype())?,
+        fortran_order,
+        shape,
+    );
+
+    //version 1 is used whenever the header length fits in a u16, which is what numpy does too.
+    let prefix_len = if header.len() + 11 <= u16::MAX as usize { 10 } else { 12 };
+    let padding = (NPY_ALIGNMENT - (prefix_len + header.len() + 1) % NPY_ALIGNMENT) % NPY_ALIGNMENT;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(prefix_len + header.len() + data.len());
+    out.extend_from_slice(NPY_MAGIC);
+    if prefix_len == 10 {
+        out.extend_from_slice(&[1, 0]);
+        out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    } else {
+        out.extend_from_slice(&[2, 0]);
+        out.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    }
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    Ok(out)
+}
+
+/// Parses a `.npz` archive into `(name, array)` pairs, names have the `.npy` suffix removed.
+pub fn read_npz(buffer: &[u8]) -> Result<Vec<(String, NpyArray)>, InteropError> {
+    let mut archive = ZipArchive::new(Cursor::new(buffer))
+        .map_err(|e| InteropError::Format(e.to_string()))?;
+    let mut arrays = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let mut member = archive.by_index(i).map_err(|e| InteropError::Format(e.to_string()))?;
+        let name = member.name().strip_suffix(".npy").unwrap_or(member.name()).to_string();
+        let mut contents = Vec::with_capacity(member.size() as usize);
+        member.read_to_end(&mut contents)?;
+        let array = read_npy(Bytes::from(contents)).map_err(|e| rename_invalid(e, &name))?;
+        arrays.push((name, array));
+    }
+    Ok(arrays)
+}
+
+/// Serializes `(name, tensor)` pairs into an uncompressed `.npz` archive, as `numpy.savez` does.
+pub fn write_npz(arrays: &[(String, Arc<Tensor>)]) -> Result<Vec<u8>, InteropError> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Stored)
+        .large_file(true);
+    for (name, tensor) in arrays {
+        ensure_dense(name, tensor)?;
+        writer.start_file(format!("{}.npy", name), options)
+            .map_err(|e| InteropError::Format(e.to_string()))?;
+        writer.write_all(&write_npy(tensor.get_metadata(), tensor.get_data())?)?;
+    }
+    let cursor = writer.finish().map_err(|e| InteropError::Format(e.to_string()))?;
+    Ok(cursor.into_inner())
+}
+
+/// Loads a `.npy` file into a local cache under `key`.
+pub fn import_npy_into_cache(path: impl AsRef<Path>, cache: &TensorCache, key: &str) -> Result<(), InteropError> {
+    let array = read_npy(Bytes::from(std::fs::read(path)?))?;
+    cache.put(key.to_string(), array.meta, array.data)?;
+    Ok(())
+}
+
+/// Loads every array of a `.npz` file into a local cache as `key_prefix + name` and returns the keys.
+pub fn import_npz_into_cache(path: impl AsRef<Path>, cache: &TensorCache, key_prefix: &str) -> Result<Vec<String>, InteropError> {
+    let mut keys = Vec::new();
+    for (name, array) in read_npz(&std::fs::read(path)?)? {
+        let key = format!("{}{}", key_prefix, name);
+        cache.put(key.clone(), array.meta, array.data)?;
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// Writes one cached tensor into a `.npy` file.
+pub fn export_npy_from_cache(cache: &TensorCache, key: &str, path: impl AsRef<Path>) -> Result<(), InteropError> {
+    let tensor = cache.get(key).ok_or_else(|| InteropError::KeyNotFound(key.to_string()))?;
+    ensure_dense(key, &tensor)?;
+    std::fs::write(path, write_npy(tensor.get_metadata(), tensor.get_data())?)?;
+    Ok(())
+}
+
+/// Writes cached tensors into a `.npz` file, array names are the keys with `key_prefix` stripped.
+pub fn export_npz_from_cache(cache: &TensorCache, keys: &[String], path: impl AsRef<Path>, key_prefix: &str) -> Result<(), InteropError> {
+    let mut arrays = Vec::with_capacity(keys.len());
+    for key in keys {
+        let tensor = cache.get(key).ok_or_else(|| InteropError::KeyNotFound(key.clone()))?;
+        arrays.push((strip_prefix(key, key_prefix), tensor));
+    }
+    std::fs::write(path, write_npz(&arrays)?)?;
+    Ok(())
+}
+
+/// Loads a `.npy` file into the cluster under `key`.
+pub async fn import_npy_into_cluster(path: impl AsRef<Path>, client: &DistributedClient, key: &str) -> Result<(), InteropError> {
+    let array = read_npy(Bytes::from(tokio::fs::read(path).await?))?;
+    client.put(key.to_string(), array.meta, array.data.to_vec()).await?;
+    Ok(())
+}
+
+/// Loads every array of a `.npz` file into the cluster as `key_prefix + name` and returns the keys.
+pub async fn import_npz_into_cluster(path: impl AsRef<Path>, client: &DistributedClient, key_prefix: &str) -> Result<Vec<String>, InteropError> {
+    let mut keys = Vec::new();
+    for (name, array) in read_npz(&tokio::fs::read(path).await?)? {
+        let key = format!("{}{}", key_prefix, name);
+        client.put(key.clone(), array.meta, array.data.to_vec()).await?;
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+/// Writes one tensor from the cluster into a `.npy` file.
+pub async fn export_npy_from_cluster(client: &DistributedClient, key: &str, path: impl AsRef<Path>) -> Result<(), InteropError> {
+    let tensor = client.get(key).await?.ok_or_else(|| InteropError::KeyNotFound(key.to_string()))?;
+    ensure_dense(key, &tensor)?;
+    tokio::fs::write(path, write_npy(tensor.get_metadata(), tensor.get_data())?).await?;
+    Ok(())
+}
+
+/// Writes tensors from the cluster into a `.npz` file, array names are the keys with `key_prefix` stripped.
+pub async fn export_npz_from_cluster(client: &DistributedClient, keys: &[String], path: impl AsRef<Path>, key_prefix: &str) -> Result<(), InteropError> {
+    let mut arrays = Vec::with_capacity(keys.len());
+    for key in keys {
+        let tensor = client.get(key).await?.ok_or_else(|| InteropError::KeyNotFound(key.clone()))?;
+        arrays.push((strip_prefix(key, key_prefix), tensor));
+    }
+    tokio::fs::write(path, write_npz(&arrays)?).await?;
+    Ok(())
+}
+
+struct NpyHeader {
+    descr: String,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+/// Parses the python dict literal header. Only the three keys numpy writes are understood.
+fn parse_header(header: &str) -> Result<NpyHeader, InteropError> {
+    let invalid = |what: &str| InteropError::Format(format!("invalid npy header, {}: {}", what, header.trim()));
+
+    let descr = dict_value(header, "descr").ok_or_else(|| invalid("missing descr"))?;
+    let descr = descr
+        .strip_prefix('\'')
+        .and_then(|d| d.split('\'').next())
+        .ok_or_else(|| invalid("descr is not a string"))?
+        .to_string();
+
+    let fortran_order = match dict_value(header, "fortran_order") {
+        Some(v) if v.starts_with("True") => true,
+        Some(v) if v.starts_with("False") => false,
+        _ => return Err(invalid("missing fortran_order")),
+    };
+
+    let shape = dict_value(header, "shape").ok_or_else(|| invalid("missing shape"))?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| invalid("shape is not a tuple"))?;
+    let shape = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.trim_end_matches('L').parse::<usize>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid("shape is not a tuple of integers"))?;
+
+    Ok(NpyHeader { descr, fortran_order, shape })
+}
+
+/// Returns the text following `'key':` in the header, with leading whitespace removed.
+fn dict_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let quoted = format!("'{}'", key);
+    let rest = &header[header.find(&quoted)? + quoted.len()..];
+    Some(rest.trim_start().strip_prefix(':')?.trim_start())
+}
+
+fn descr_to_dtype(descr: &str) -> Result<DType, InteropError> {
+    let native_little = cfg!(target_endian = "little");
+    let (order, code) = descr.split_at(1.min(descr.len()));
+    let little = match order {
+        "<" | "|" => true,
+        "=" => native_little,
+        _ => false,
+    };
+    match (little, code) {
+        (true, "f4") => Ok(DType::F32),
+        (true, "f8") => Ok(DType::F64),
+        (true, "i4") => Ok(DType::I32),
+        (true, "i8") => Ok(DType::I64),
+        (true, "u1") => Ok(DType::U8),
+        (true, "f2") => Ok(DType::F16),
+        _ => Err(InteropError::UnsupportedDType(descr.to_string())),
+    }
+}
+
+fn dtype_to_descr(dtype: &DType) -> Result<&'static str, InteropError> {
+    match dtype {
+        DType::F32 => Ok("<f4"),
+        DType::F64 => Ok("<f8"),
+        DType::I32 => Ok("<i4"),
+        DType::I64 => Ok("<i8"),
+        DType::U8 => Ok("|u1"),
+        DType::F16 => Ok("<f2"),
+        DType::BF16 => Err(InteropError::UnsupportedDType("BF16".to_string())),
+    }
+}
+
+//npy has no sparse or ragged representation, sparse tensors have to be densified before they are exported.
+fn ensure_dense(name: &str, tensor: &Tensor) -> Result<(), InteropError> {
+    if !tensor.get_metadata().is_dense() {
+        return Err(InteropError::UnsupportedLayout(name.to_string()));
+    }
+    Ok(())
+}
+
+fn strip_prefix(key: &str, key_prefix: &str) -> String {
+    key.strip_prefix(key_prefix).unwrap_or(key).to_string()
+}
+
+fn rename_invalid(error: InteropError, name: &str) -> InteropError {
+    match error {
+        InteropError::InvalidTensor { reason, .. } => InteropError::InvalidTensor { name: name.to_string(), reason },
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numpy_written_header() {
+        //header exactly as numpy 1.26 writes it for np.zeros((2, 3), dtype=np.float32, order='F').
+        let mut buffer = b"\x93NUMPY\x01\x00v\x00".to_vec();
+        let mut header = "{'descr': '<f4', 'fortran_order': True, 'shape': (2, 3), }".to_string();
+        header.push_str(&" ".repeat(118 - header.len() - 1));
+        header.push('\n');
+        buffer.extend_from_slice(header.as_bytes());
+        buffer.extend_from_slice(&[0u8; 24]);
+
+        let array = read_npy(Bytes::from(buffer)).unwrap();
+        assert_eq!(array.meta.dtype(), &DType::F32);
+        assert_eq!(array.meta.shape(), &[2, 3]);
+        assert_eq!(array.meta.layout(), &StorageLayout::ColumnMajor);
+        assert_eq!(array.data.len(), 24);
+    }
+
+    #[test]
+    fn npy_round_trip_is_aligned() {
+        let meta = TensorMeta::new(DType::I64, vec![3], StorageLayout::RowMajor).unwrap();
+        let data: Vec<u8> = (0..24).collect();
+        let buffer = write_npy(&meta, &data).unwrap();
+        assert_eq!((buffer.len() - data.len()) % NPY_ALIGNMENT, 0);
+
+        let array = read_npy(Bytes::from(buffer)).unwrap();
+        assert_eq!(array.meta.dtype(), &DType::I64);
+        assert_eq!(array.meta.shape(), &[3]);
+        assert_eq!(array.data.as_ref(), data.as_slice());
+    }
+
+    #[test]
+    fn scalar_and_empty_arrays_round_trip() {
+        let scalar = TensorMeta::new(DType::F64, vec![], StorageLayout::RowMajor).unwrap();
+        let array = read_npy(Bytes::from(write_npy(&scalar, &1.5f64.to_le_bytes()).unwrap())).unwrap();
+        assert!(array.meta.shape().is_empty());
+        assert_eq!(array.data.as_ref(), &1.5f64.to_le_bytes());
+
+        let empty = TensorMeta::new(DType::F32, vec![0, 4], StorageLayout::RowMajor).unwrap();
+        let array = read_npy(Bytes::from(write_npy(&empty, &[]).unwrap())).unwrap();
+        assert_eq!(array.meta.shape(), &[0, 4]);
+        assert!(array.data.is_empty());
+    }
+
+    #[test]
+    fn big_endian_and_unknown_dtypes_are_rejected() {
+        let meta = TensorMeta::new(DType::F32, vec![1], StorageLayout::RowMajor).unwrap();
+        let mut buffer = write_npy(&meta, &[0u8; 4]).unwrap();
+        let descr_at = buffer.windows(3).position(|w| w == b"<f4").unwrap();
+        buffer[descr_at] = b'>';
+        let result = read_npy(Bytes::from(buffer));
+        assert!(matches!(result, Err(InteropError::UnsupportedDType(d)) if d == ">f4"));
+    }
+
+    #[test]
+    fn length_mismatch_is_rejected() {
+        let meta = TensorMeta::new(DType::F32, vec![2], StorageLayout::RowMajor).unwrap();
+        let mut buffer = write_npy(&meta, &[0u8; 8]).unwrap();
+        buffer.pop();
+        assert!(matches!(read_npy(Bytes::from(buffer)), Err(InteropError::InvalidTensor { .. })));
+    }
+
+    #[test]
+    fn half_precision_round_trips_and_bf16_is_rejected() {
+        let meta = TensorMeta::new(DType::F16, vec![2], StorageLayout::RowMajor).unwrap();
+        let array = read_npy(Bytes::from(write_npy(&meta, &[0, 60, 0, 188]).unwrap())).unwrap();
+        assert_eq!(array.meta.dtype(), &DType::F16);
+        assert_eq!(array.data.as_ref(), &[0, 60, 0, 188]);
+
+        let meta = TensorMeta::new(DType::BF16, vec![2], StorageLayout::RowMajor).unwrap();
+        assert!(matches!(write_npy(&meta, &[0u8; 4]), Err(InteropError::UnsupportedDType(_))));
+    }
+
+    #[test]
+    fn npz_round_trip_against_cache() {
+        let cache = TensorCache::new(1024).unwrap();
+        let meta = TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::ColumnMajor).unwrap();
+        cache.put("dbg/a".to_string(), meta, Bytes::from(vec![1u8; 16])).unwrap();
+        let meta = TensorMeta::new(DType::U8, vec![5], StorageLayout::RowMajor).unwrap();
+        cache.put("dbg/b".to_string(), meta, Bytes::from(vec![2u8; 5])).unwrap();
+
+        let path = std::env::temp_dir().join(format!("redstone_npz_{}.npz", std::process::id()));
+        let keys = ["dbg/a".to_string(), "dbg/b".to_string()];
+        export_npz_from_cache(&cache, &keys, &path, "dbg/").unwrap();
+
+        let restored = TensorCache::new(1024).unwrap();
+        let imported = import_npz_into_cache(&path, &restored, "restored/").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(imported, vec!["restored/a".to_string(), "restored/b".to_string()]);
+        let a = restored.get("restored/a").unwrap();
+        assert_eq!(a.get_metadata().layout(), &StorageLayout::ColumnMajor);
+        assert_eq!(a.get_data().as_ref(), &[1u8; 16]);
+    }
+}
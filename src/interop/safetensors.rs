@@ -0,0 +1,241 @@
+//! Import and export of `.safetensors` checkpoints.
+//! A safetensors file is an 8 byte little endian header length, a JSON header mapping every tensor
+//! name to its dtype, shape and byte range, followed by the raw row major tensor bytes.
+//! Imports slice the file buffer directly, so every cached tensor shares the single file allocation
+//! instead of copying its byte range.
+//! Imports are not atomic across tensors, if a put fails part way the earlier tensors stay cached.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use bytes::Bytes;
+use ::safetensors::tensor::{Dtype, SafeTensors, TensorView};
+
+use crate::TensorCache;
+use crate::cluster::distributed_client::DistributedClient;
+use crate::error::interop_error::InteropError;
+use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+use crate::tensor::tensor::Tensor;
+
+/// Controls how tensor names in a file map onto cache keys.
+#[derive(Debug, Clone, Default)]
+pub struct SafetensorsOptions {
+    /// prepended to every tensor name on import and stripped from every key on export.
+    pub key_prefix: String,
+    /// skip tensors whose dtype redstone cannot store instead of failing the whole import.
+    pub skip_unsupported: bool,
+}
+
+impl SafetensorsOptions {
+    pub fn with_prefix(key_prefix: impl Into<String>) -> Self {
+        Self {
+            key_prefix: key_prefix.into(),
+            skip_unsupported: false,
+        }
+    }
+
+    fn key_for(&self, name: &str) -> String {
+        format!("{}{}", self.key_prefix, name)
+    }
+
+    fn name_for<'a>(&self, key: &'a str) -> &'a str {
+        key.strip_prefix(self.key_prefix.as_str()).unwrap_or(key)
+    }
+}
+
+/// A single tensor parsed out of a safetensors buffer, ready to be inserted under `key`.
+pub struct SafetensorsEntry {
+    pub key: String,
+    pub meta: TensorMeta,
+    pub data: Bytes,
+}
+
+/// Parses a safetensors buffer into cache entries, in the order the tensors appear in the file.
+pub fn read_safetensors(buffer: Bytes, options: &SafetensorsOptions) -> Result<Vec<SafetensorsEntry>, InteropError> {
+    let (header_len, metadata) = SafeTensors::read_metadata(&buffer)
+        .map_err(|e| InteropError::Format(e.to_string()))?;
+    //tensor data starts right after the length prefix and the JSON header.
+    let data_start = 8 + header_len;
+
+    let mut infos: Vec<_> = metadata.tensors().into_iter().collect();
+    infos.sort_by_key(|(_, info)| info.data_offsets.0);
+
+    let mut entries = Vec::with_capacity(infos.len());
+    for (name, info) in infos {
+        let dtype = match safetensors_to_dtype(info.dtype) {
+            Ok(dtype) => dtype,
+            Err(_) if options.skip_unsupported => continue,
+            Err(e) => return Err(e),
+        };
+        let meta = TensorMeta::new(dtype, info.shape.clone(), StorageLayout::RowMajor)
+            .map_err(|e| InteropError::InvalidTensor { name: name.clone(), reason: e.to_string() })?;
+        let (begin, end) = info.data_offsets;
+        let data = buffer.slice(data_start + begin..data_start + end);
+        entries.push(SafetensorsEntry {
+            key: options.key_for(&name),
+            meta,
+            data,
+        });
+    }
+    Ok(entries)
+}
+
+/// Serializes cached tensors into a single safetensors buffer. Tensor names are the keys with the
+/// configured prefix stripped.
+pub fn write_safetensors(tensors: &[(String, Arc<Tensor>)], options: &SafetensorsOptions) -> Result<Vec<u8>, InteropError> {
+    let mut views = HashMap::with_capacity(tensors.len());
+    for (key, tensor) in tensors {
+        let meta = tensor.get_metadata();
+        if *meta.layout() != StorageLayout::RowMajor || !meta.is_dense() {
+            return Err(InteropError::UnsupportedLayout(key.clone()));
+        }
+        let view = TensorView::new(dtype_to_safetensors(meta.dtype()), meta.shape().to_vec(), tensor.get_data())
+            .map_err(|e| InteropError::InvalidTensor { name: key.clone(), reason: e.to_string() })?;
+        views.insert(options.name_for(key).to_string(), view);
+    }
+    ::safetensors::serialize(views, &None).map_err(|e| InteropError::Format(e.to_string()))
+}
+
+/// Loads every tensor of a safetensors file into a local cache and returns the inserted keys.
+pub fn import_into_cache(path: impl AsRef<Path>, cache: &TensorCache, options: &SafetensorsOptions) -> Result<Vec<String>, InteropError> {
+    let buffer = Bytes::from(std::fs::read(path)?);
+    let mut keys = Vec::new();
+    for entry in read_safetensors(buffer, options)? {
+        cache.put(entry.key.clone(), entry.meta, entry.data)?;
+        keys.push(entry.key);
+    }
+    Ok(keys)
+}
+
+/// Loads every tensor of a safetensors file into the cluster and returns the inserted keys.
+pub async fn import_into_cluster(path: impl AsRef<Path>, client: &DistributedClient, options: &SafetensorsOptions) -> Result<Vec<String>, InteropError> {
+    let buffer = Bytes::from(tokio::fs::read(path).await?);
+    let mut keys = Vec::new();
+    for entry in read_safetensors(buffer, options)? {
+        client.put(entry.key.clone(), entry.meta, entry.data.to_vec()).await?;
+        keys.push(entry.key);
+    }
+    Ok(keys)
+}
+
+/// Writes the given keys of a local cache into one safetensors file.
+pub fn export_from_cache(cache: &TensorCache, keys: &[String], path: impl AsRef<Path>, options: &SafetensorsOptions) -> Result<(), InteropError> {
+    let mut tensors = Vec::with_capacity(keys.len());
+    for key in keys {
+        let tensor = cache.get(key).ok_or_else(|| InteropError::KeyNotFound(key.clone()))?;
+        tensors.push((key.clone(), tensor));
+    }
+    std::fs::write(path, write_safetensors(&tensors, options)?)?;
+    Ok(())
+}
+
+/// Writes the given keys of the cluster into one safetensors file.
+pub async fn export_from_cluster(client: &DistributedClient, keys: &[String], path: impl AsRef<Path>, options: &SafetensorsOptions) -> Result<(), InteropError> {
+    let mut tensors = Vec::with_capacity(keys.len());
+    for key in keys {
+        let tensor = client.get(key).await?.ok_or_else(|| InteropError::KeyNotFound(key.clone()))?;
+        tensors.push((key.clone(), tensor));
+    }
+    tokio::fs::write(path, write_safetensors(&tensors, options)?).await?;
+    Ok(())
+}
+
+fn safetensors_to_dtype(dtype: Dtype) -> Result<DType, InteropError> {
+    match dtype {
+        Dtype::F32 => Ok(DType::F32),
+        Dtype::F64 => Ok(DType::F64),
+        Dtype::I32 => Ok(DType::I32),
+        Dtype::I64 => Ok(DType::I64),
+        Dtype::U8 => Ok(DType::U8),
+        Dtype::F16 => Ok(DType::F16),
+        Dtype::BF16 => Ok(DType::BF16),
+        other => Err(InteropError::UnsupportedDType(format!("{:?}", other))),
+    }
+}
+
+fn dtype_to_safetensors(dtype: &DType) -> Dtype {
+    match dtype {
+        DType::F32 => Dtype::F32,
+        DType::F64 => Dtype::F64,
+        DType::I32 => Dtype::I32,
+        DType::I64 => Dtype::I64,
+        DType::U8 => Dtype::U8,
+        DType::F16 => Dtype::F16,
+        DType::BF16 => Dtype::BF16,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn f32_bytes(values: &[f32]) -> Bytes {
+        Bytes::from(values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>())
+    }
+
+    fn sample_cache() -> TensorCache {
+        let cache = TensorCache::new(1024).unwrap();
+        let weight = TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::RowMajor).unwrap();
+        cache.put("model/weight".to_string(), weight, f32_bytes(&[1.0, 2.0, 3.0, 4.0])).unwrap();
+        let ids = TensorMeta::new(DType::I64, vec![3], StorageLayout::RowMajor).unwrap();
+        cache.put("model/ids".to_string(), ids, Bytes::from(vec![7u8; 24])).unwrap();
+        cache
+    }
+
+    #[test]
+    fn round_trip_preserves_meta_and_bytes() {
+        let cache = sample_cache();
+        let keys = ["model/weight".to_string(), "model/ids".to_string()];
+        let tensors: Vec<_> = keys.iter().map(|k| (k.clone(), cache.get(k).unwrap())).collect();
+
+        let buffer = write_safetensors(&tensors, &SafetensorsOptions::with_prefix("model/")).unwrap();
+        let entries = read_safetensors(Bytes::from(buffer), &SafetensorsOptions::with_prefix("ckpt/")).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        for entry in entries {
+            let original = cache.get(&entry.key.replace("ckpt/", "model/")).unwrap();
+            assert_eq!(entry.meta.dtype(), original.get_metadata().dtype());
+            assert_eq!(entry.meta.shape(), original.get_metadata().shape());
+            assert_eq!(&entry.data, original.get_data());
+        }
+    }
+
+    #[test]
+    fn unsupported_dtype_is_rejected_or_skipped() {
+        let short = TensorView::new(Dtype::I16, vec![2], &[0u8; 4]).unwrap();
+        let ok = TensorView::new(Dtype::U8, vec![2], &[1u8; 2]).unwrap();
+        let buffer = Bytes::from(::safetensors::serialize(vec![("short", short), ("ok", ok)], &None).unwrap());
+
+        let result = read_safetensors(buffer.clone(), &SafetensorsOptions::default());
+        assert!(matches!(result, Err(InteropError::UnsupportedDType(_))));
+
+        let options = SafetensorsOptions { skip_unsupported: true, ..Default::default() };
+        let entries = read_safetensors(buffer, &options).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, "ok");
+    }
+
+    #[test]
+    fn file_import_and_export_against_cache() {
+        let cache = sample_cache();
+        let path = std::env::temp_dir().join(format!("redstone_safetensors_{}.safetensors", std::process::id()));
+        let keys = vec!["model/weight".to_string(), "model/ids".to_string()];
+        export_from_cache(&cache, &keys, &path, &SafetensorsOptions::with_prefix("model/")).unwrap();
+
+        let restored = TensorCache::new(1024).unwrap();
+        let mut imported = import_into_cache(&path, &restored, &SafetensorsOptions::with_prefix("model/")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        imported.sort();
+        assert_eq!(imported, vec!["model/ids".to_string(), "model/weight".to_string()]);
+        assert_eq!(restored.get("model/weight").unwrap().get_data(), cache.get("model/weight").unwrap().get_data());
+    }
+
+    #[test]
+    fn export_missing_key_fails() {
+        let cache = sample_cache();
+        let path = std::env::temp_dir().join("redstone_safetensors_missing.safetensors");
+        let result = export_from_cache(&cache, &["absent".to_string()], &path, &SafetensorsOptions::default());
+        assert!(matches!(result, Err(InteropError::KeyNotFound(_))));
+    }
+}
@@ -6,15 +6,21 @@ pub mod cache;
 pub mod transport;
 pub mod cluster;
 pub mod error;
+pub mod interop;
 
 use crate::cache::lru_cache::{Cache};
 use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+use crate::tensor::stats::TensorStats;
 use crate::tensor::tensor::Tensor;
 use crate::cache::lru_cache::CacheStats;
+use crate::cache::put_options::{NonFinitePolicy, PutOptions, NON_FINITE_ATTRIBUTE};
 use crate::error::cache_error::CacheError;
 
 pub mod proto {
     tonic::include_proto!("redstone");
+
+    /// encoded descriptors of redstone.proto, for the reflection service.
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("redstone_descriptor");
 }
 
 pub struct TensorCache {
@@ -28,43 +34,138 @@ impl TensorCache {
         })
     }
 
-    /// Inserts a tensor into the cache.
+    /// Creates a cache that stores byte-identical payloads once. Keys whose stored bytes match share one
+    /// reference counted payload that is accounted once, see `Cache::with_dedup`.
+    pub fn with_dedup(max_cache_size: u64) -> Result<Self, CacheError> {
+        Ok(Self {
+            cache: Cache::with_dedup(max_cache_size)?,
+        })
+    }
+
+    /// Inserts a tensor into the cache and returns the version it was stored under.
     /// It guarantees:
     /// 1. Immutable writes
     /// 2. Tensor validation before insertion, preventing corrupted writes
     /// 3. Atomic inserts
-    pub fn put(&self, key: String, meta: TensorMeta, data: Bytes, ) -> Result<(), CacheError> {
-        let data_bytes = Bytes::from(data);
-        let tensor = Tensor::new(meta,data_bytes)
-            .map_err(|_| CacheError::InvalidTensor)?;
-        self.cache.put(key, tensor)
+    pub fn put(&self, key: String, meta: TensorMeta, data: Bytes, ) -> Result<u64, CacheError> {
+        self.put_with_options(key, meta, data, PutOptions::default())
+    }
+
+    /// Inserts a tensor into the cache with the behaviour described by `options`, on top of the
+    /// guarantees of `put`. `options.mode` decides whether an existing key may be replaced.
+    pub fn put_with_options(&self, key: String, meta: TensorMeta, data: Bytes, options: PutOptions) -> Result<u64, CacheError> {
+        let tensor = Tensor::new(meta,data)?
+            .with_attributes(options.attributes);
+        if let Some(expected) = options.expected_checksum
+            && tensor.checksum() != expected {
+            return Err(CacheError::ChecksumMismatch { expected, actual: tensor.checksum() });
+        }
+        let tensor = self.check_non_finite(tensor, options.non_finite)?;
+        let tensor = if options.encoding.is_raw() {
+            tensor
+        } else {
+            tensor.encode(options.encoding)?
+        };
+        self.cache.put_with_mode(key, tensor, options.mode)
     }
 
-    ///     Retrieves a tensor by key.
+    //scans floating point tensors for NaN and infinities, the statistics stay cached with the tensor.
+    fn check_non_finite(&self, tensor: Tensor, policy: NonFinitePolicy) -> Result<Tensor, CacheError> {
+        if policy == NonFinitePolicy::Allow || !tensor.get_metadata().dtype().is_float() {
+            return Ok(tensor);
+        }
+        let stats = tensor.stats()?;
+        if stats.non_finite_count() == 0 {
+            return Ok(tensor);
+        }
+        match policy {
+            NonFinitePolicy::Reject => Err(CacheError::NonFinite { nan: stats.nan_count, inf: stats.inf_count }),
+            _ => Ok(tensor.with_attribute(NON_FINITE_ATTRIBUTE, format!("nan={},inf={}", stats.nan_count, stats.inf_count))),
+        }
+    }
+
+    ///     Retrieves a tensor by key, decoding it if it was stored compressed. A compressed tensor that fails
+    ///     to decode is dropped and counted in `CacheStats::corruptions`.
     ///     It guarantees:
     ///     1. Atomic reads
     ///     2. Idempotent reads
     pub fn get(&self, key: &str) -> Option<Arc<Tensor>> {
+        let tensor = self.get_stored(key)?;
+        if tensor.encoding().is_raw() {
+            return Some(tensor);
+        }
+        match tensor.decode() {
+            Ok(decoded) => Some(Arc::new(decoded)),
+            Err(_) => {
+                //the payload was encoded by us, failing to decode it means it is corrupted. It is dropped as
+                //`scrub` would, unless it was replaced in the meantime.
+                self.cache.remove_corrupted(key, &tensor);
+                None
+            }
+        }
+    }
+
+    /// Retrieves a tensor by key exactly as it is stored, which may be compressed.
+    pub fn get_stored(&self, key: &str) -> Option<Arc<Tensor>> {
         self.cache.get(key)
     }
 
+    /// Retrieves one row of a ragged tensor as a dense tensor. Returns `CacheError::NotRagged` if the
+    /// stored tensor is not ragged.
+    pub fn get_row(&self, key: &str, row: usize) -> Result<Option<Tensor>, CacheError> {
+        self.get_stored(key).map(|tensor| tensor.ragged_row(row)).transpose()
+    }
+
+    /// Retrieves a tensor by key as it is stored, to inspect its metadata and attributes. Unlike
+    /// `get_stored` it leaves the LRU order and hit counts alone unless `touch` is set.
+    pub fn head(&self, key: &str, touch: bool) -> Option<Arc<Tensor>> {
+        if touch {
+            self.get_stored(key)
+        } else {
+            self.cache.peek(key)
+        }
+    }
+
+    /// Returns min, max, mean, std and the NaN and infinity counts of a tensor. They are computed on the
+    /// first call and cached with the entry until it is deleted or evicted.
+    pub fn describe(&self, key: &str) -> Result<Option<TensorStats>, CacheError> {
+        self.get_stored(key).map(|tensor| tensor.stats()).transpose()
+    }
+
+    /// Stores `key` as a view of `source` with a different shape, sharing its payload without a copy.
+    /// The view is deleted or evicted together with its source, see `Cache::create_view`.
+    pub fn create_view(&self, source: &str, key: String, shape: Vec<usize>) -> Result<(), CacheError> {
+        self.cache.create_view(source, key, shape)
+    }
+
     /// Deletes a key value pair and returns the deleted tensor.
     pub fn delete(&self, key: &str) -> Option<Arc<Tensor>> {
         let deleted_tensor = self.cache.delete(key);
         Some(deleted_tensor?)
     }
 
+    /// Deletes every tensor whose attribute `key` is set to `value`, returning the deleted keys.
+    pub fn delete_by_tag(&self, key: &str, value: &str) -> Vec<String> {
+        self.cache.delete_by_tag(key, value)
+    }
+
     pub fn get_stats(&self) -> CacheStats {
         self.cache.stats()
     }
 
+    /// Verifies the checksum of every cached tensor and drops the ones whose bytes no longer match.
+    /// Returns the keys that were dropped.
+    pub fn scrub(&self) -> Vec<String> {
+        self.cache.scrub()
+    }
+
     /// Put method for f32 data type. It internally implements the core put method.
-    pub fn put_f32(&self, key: String, shape: Vec<usize>, mut data: Vec<f32>) -> Result<(), CacheError> {
+    pub fn put_f32(&self, key: String, shape: Vec<usize>, mut data: Vec<f32>) -> Result<u64, CacheError> {
         let meta = TensorMeta::new(
             DType::F32,
             shape,
             StorageLayout::RowMajor,
-        ).map_err(|_| CacheError::InvalidTensorMetadata)?;
+        )?;
 
         let len = data.len() * size_of::<f32>();
         let cap = data.capacity() * size_of::<f32>();
@@ -84,6 +185,9 @@ impl TensorCache {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tensor::codec::{Codec, Encoding, Shuffle};
+    use crate::tensor::quantize::Quantization;
+    use crate::error::tensor_error::TensorError;
 
     fn make_valid_meta() -> TensorMeta {
         TensorMeta::new(
@@ -115,7 +219,10 @@ mod tests {
         let data = vec![0u8; 15]; // invalid size
         let data_bytes = Bytes::from(data);
         let result = cache.put("bad".to_string(), meta, data_bytes);
-        assert_eq!(result, Err(CacheError::InvalidTensor));
+        assert_eq!(
+            result,
+            Err(CacheError::InvalidTensor(TensorError::LengthMismatch { expected: 16, actual: 15 }))
+        );
     }
 
     #[test]
@@ -135,6 +242,134 @@ mod tests {
         assert_eq!(result, Err(CacheError::KeyAlreadyExists));
     }
 
+    #[test]
+    fn checksum_mismatch_is_rejected() {
+        let cache = TensorCache::new(128).unwrap();
+
+        let options = PutOptions { expected_checksum: Some(42), ..Default::default() };
+        let result = cache.put_with_options("bad".to_string(), make_valid_meta(), Bytes::from(vec![0u8; 16]), options);
+        assert!(matches!(result, Err(CacheError::ChecksumMismatch { expected: 42, .. })));
+        assert!(cache.get("bad").is_none());
+    }
+
+    #[test]
+    fn compressed_put_accounts_stored_size() {
+        let cache = TensorCache::new(4096).unwrap();
+
+        let meta = TensorMeta::new(DType::F32, vec![256], StorageLayout::RowMajor).unwrap();
+        let options = PutOptions { encoding: Encoding::new(Codec::Zstd, Shuffle::Bit), ..Default::default() };
+        cache.put_with_options("sparse".to_string(), meta, Bytes::from(vec![0u8; 1024]), options).unwrap();
+
+        let stored = cache.get_stored("sparse").unwrap();
+        assert!(!stored.encoding().is_raw());
+        assert_eq!(cache.get_stats().memory_used, stored.byte_size() as u64);
+        assert!(cache.get_stats().memory_used < 1024);
+
+        let tensor = cache.get("sparse").unwrap();
+        assert!(tensor.encoding().is_raw());
+        assert_eq!(tensor.get_data().as_ref(), &[0u8; 1024]);
+    }
+
+    #[test]
+    fn corrupted_compressed_tensor_is_dropped_on_get() {
+        let cache = TensorCache::new(4096).unwrap();
+
+        let meta = TensorMeta::new(DType::F32, vec![256], StorageLayout::RowMajor).unwrap();
+        let tensor = Tensor::new(meta, Bytes::from(vec![0u8; 1024])).unwrap()
+            .encode(Encoding::new(Codec::Zstd, Shuffle::None))
+            .unwrap()
+            .with_corrupted_payload(Bytes::from(vec![0xff; 16]));
+        cache.cache.put("bad".to_string(), tensor).unwrap();
+
+        assert!(cache.get("bad").is_none());
+        assert!(cache.get_stored("bad").is_none());
+        let stats = cache.get_stats();
+        assert_eq!((stats.corruptions, stats.memory_used), (1, 0));
+    }
+
+    #[test]
+    fn quantized_put_reports_bytes_saved() {
+        let cache = TensorCache::new(4096).unwrap();
+
+        let meta = TensorMeta::new(DType::F32, vec![4, 64], StorageLayout::RowMajor).unwrap();
+        let values: Vec<f32> = (0..256).map(|i| (i as f32 * 0.37).sin()).collect();
+        let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let options = PutOptions {
+            encoding: Encoding::default().with_quantization(Quantization::Int8),
+            ..Default::default()
+        };
+        cache.put_with_options("weights".to_string(), meta, Bytes::from(data), options).unwrap();
+
+        let stats = cache.get_stats();
+        assert_eq!(stats.logical_bytes, 1024);
+        assert!(stats.memory_used < 300);
+        assert_eq!(stats.bytes_saved(), stats.logical_bytes - stats.memory_used);
+
+        let tensor = cache.get("weights").unwrap();
+        for (chunk, expected) in tensor.get_data().chunks_exact(4).zip(&values) {
+            let actual = f32::from_le_bytes(chunk.try_into().unwrap());
+            assert!((actual - expected).abs() < 0.01);
+        }
+
+        cache.delete("weights");
+        assert_eq!(cache.get_stats().logical_bytes, 0);
+    }
+
+    #[test]
+    fn ragged_rows_are_fetched_from_the_cache() {
+        let cache = TensorCache::new(1024).unwrap();
+        let rows: [&[u8]; 3] = [&[7; 5], &[8; 2], &[9; 9]];
+        let (meta, payload) = crate::tensor::ragged::pack_rows(DType::U8, &[], &rows).unwrap();
+        cache.put("batch".to_string(), meta, payload).unwrap();
+
+        let row = cache.get_row("batch", 1).unwrap().unwrap();
+        assert_eq!(row.get_metadata().shape(), &[2]);
+        assert_eq!(row.get_data().as_ref(), &[8, 8]);
+        assert!(cache.get_row("missing", 0).unwrap().is_none());
+        assert!(matches!(cache.get_row("batch", 3), Err(CacheError::RowOutOfRange { row: 3, rows: 3 })));
+    }
+
+    fn f32_data(values: &[f32]) -> Bytes {
+        Bytes::from(values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>())
+    }
+
+    #[test]
+    fn non_finite_policy_rejects_or_flags() {
+        let cache = TensorCache::new(1024).unwrap();
+        let data = f32_data(&[1.0, f32::NAN, f32::INFINITY, f32::NAN]);
+        let reject = PutOptions { non_finite: NonFinitePolicy::Reject, ..Default::default() };
+        let result = cache.put_with_options("bad".to_string(), make_valid_meta(), data.clone(), reject.clone());
+        assert_eq!(result, Err(CacheError::NonFinite { nan: 2, inf: 1 }));
+        assert!(cache.get("bad").is_none());
+
+        let flag = PutOptions { non_finite: NonFinitePolicy::Flag, ..Default::default() };
+        cache.put_with_options("flagged".to_string(), make_valid_meta(), data.clone(), flag).unwrap();
+        let tensor = cache.get("flagged").unwrap();
+        assert_eq!(tensor.get_attributes().get(NON_FINITE_ATTRIBUTE).map(String::as_str), Some("nan=2,inf=1"));
+
+        //finite and integer tensors pass the strictest policy untouched.
+        cache.put_with_options("ok".to_string(), make_valid_meta(), f32_data(&[1.0, 2.0, 3.0, 4.0]), reject.clone()).unwrap();
+        let ints = TensorMeta::new(DType::U8, vec![4], StorageLayout::RowMajor).unwrap();
+        cache.put_with_options("ints".to_string(), ints, Bytes::from(vec![255u8; 4]), reject).unwrap();
+        cache.put("allowed".to_string(), make_valid_meta(), data).unwrap();
+    }
+
+    #[test]
+    fn describe_is_cached_with_the_entry() {
+        let cache = TensorCache::new(1024).unwrap();
+        let options = PutOptions {
+            encoding: Encoding::new(Codec::Lz4, Shuffle::Byte),
+            ..Default::default()
+        };
+        let data = f32_data(&[2.0; 64]);
+        cache.put_with_options("t".to_string(), TensorMeta::new(DType::F32, vec![64], StorageLayout::RowMajor).unwrap(), data, options).unwrap();
+
+        let stats = cache.describe("t").unwrap().unwrap();
+        assert_eq!((stats.count, stats.min, stats.max, stats.std), (64, Some(2.0), Some(2.0), Some(0.0)));
+        assert_eq!(cache.get_stored("t").unwrap().stats().unwrap(), stats);
+        assert!(cache.describe("missing").unwrap().is_none());
+    }
+
     #[test]
     fn get_missing_returns_none() {
         let cache = TensorCache::new(128).unwrap();
@@ -0,0 +1,124 @@
+//! Element-wise conversion of dense tensors between dtypes, used to serve a tensor in the dtype a reader asks for.
+//! Floating point targets round to nearest, ties to even, and overflow to infinity.
+//! Integer targets round floats to nearest, ties to even, saturate at the bounds of the target and map NaN to
+//! zero. Integer to integer casts saturate as well, so `300i32` becomes `255u8` and `-1i64` becomes `0u8`.
+
+use bytes::Bytes;
+use half::{bf16, f16};
+use crate::error::tensor_error::TensorError;
+use crate::tensor::meta::{DType, TensorMeta};
+
+/// One element widened to a type that holds every value of its source dtype exactly.
+#[derive(Clone, Copy)]
+enum Element {
+    Float(f64),
+    Int(i64),
+}
+
+/// Casts the raw little endian payload of a dense tensor to `target`. The shape and layout are kept.
+pub fn cast(meta: &TensorMeta, data: &[u8], target: &DType) -> Result<(TensorMeta, Bytes), TensorError> {
+    if !meta.is_dense() {
+        return Err(TensorError::UnsupportedFormat("cannot cast sparse or ragged tensors".to_string()));
+    }
+    let cast_meta = TensorMeta::new(target.clone(), meta.shape().to_vec(), meta.layout().clone())?;
+    let source = meta.dtype();
+    let mut out = Vec::with_capacity(cast_meta.total_byte_size()?);
+    for bytes in data.chunks_exact(source.size_bytes()) {
+        write(read(source, bytes), target, &mut out);
+    }
+    Ok((cast_meta, Bytes::from(out)))
+}
+
+/// Reads one little endian element as an f64, integers beyond 2^53 are rounded.
+pub(crate) fn to_f64(dtype: &DType, b: &[u8]) -> f64 {
+    match read(dtype, b) {
+        Element::Float(v) => v,
+        Element::Int(v) => v as f64,
+    }
+}
+
+fn read(dtype: &DType, b: &[u8]) -> Element {
+    match dtype {
+        DType::F32 => Element::Float(f32::from_le_bytes(b.try_into().unwrap()) as f64),
+        DType::F64 => Element::Float(f64::from_le_bytes(b.try_into().unwrap())),
+        DType::F16 => Element::Float(f16::from_le_bytes(b.try_into().unwrap()).to_f64()),
+        DType::BF16 => Element::Float(bf16::from_le_bytes(b.try_into().unwrap()).to_f64()),
+        DType::I32 => Element::Int(i32::from_le_bytes(b.try_into().unwrap()) as i64),
+        DType::I64 => Element::Int(i64::from_le_bytes(b.try_into().unwrap())),
+        DType::U8 => Element::Int(b[0] as i64),
+    }
+}
+
+fn write(element: Element, dtype: &DType, out: &mut Vec<u8>) {
+    //`as` from a float to an integer saturates and maps NaN to zero.
+    match (dtype, element) {
+        (DType::F32, Element::Float(v)) => out.extend_from_slice(&(v as f32).to_le_bytes()),
+        (DType::F32, Element::Int(v)) => out.extend_from_slice(&(v as f32).to_le_bytes()),
+        (DType::F64, Element::Float(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (DType::F64, Element::Int(v)) => out.extend_from_slice(&(v as f64).to_le_bytes()),
+        (DType::F16, Element::Float(v)) => out.extend_from_slice(&f16::from_f64(v).to_le_bytes()),
+        (DType::F16, Element::Int(v)) => out.extend_from_slice(&f16::from_f64(v as f64).to_le_bytes()),
+        (DType::BF16, Element::Float(v)) => out.extend_from_slice(&bf16::from_f64(v).to_le_bytes()),
+        (DType::BF16, Element::Int(v)) => out.extend_from_slice(&bf16::from_f64(v as f64).to_le_bytes()),
+        (DType::I32, Element::Float(v)) => out.extend_from_slice(&(v.round_ties_even() as i32).to_le_bytes()),
+        (DType::I32, Element::Int(v)) => {
+            out.extend_from_slice(&(v.clamp(i32::MIN as i64, i32::MAX as i64) as i32).to_le_bytes())
+        }
+        (DType::I64, Element::Float(v)) => out.extend_from_slice(&(v.round_ties_even() as i64).to_le_bytes()),
+        (DType::I64, Element::Int(v)) => out.extend_from_slice(&v.to_le_bytes()),
+        (DType::U8, Element::Float(v)) => out.push(v.round_ties_even() as u8),
+        (DType::U8, Element::Int(v)) => out.push(v.clamp(0, u8::MAX as i64) as u8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::meta::StorageLayout;
+
+    fn f32_tensor(values: &[f32]) -> (TensorMeta, Vec<u8>) {
+        let meta = TensorMeta::new(DType::F32, vec![values.len()], StorageLayout::RowMajor).unwrap();
+        (meta, values.iter().flat_map(|v| v.to_le_bytes()).collect())
+    }
+
+    #[test]
+    fn float_targets_round_to_nearest_even() {
+        let (meta, data) = f32_tensor(&[1.0, -2.5, 1.0 + f32::EPSILON, 1e6, f32::NAN]);
+
+        let (half_meta, half) = cast(&meta, &data, &DType::F16).unwrap();
+        assert_eq!(half_meta.dtype(), &DType::F16);
+        assert_eq!(half_meta.total_byte_size().unwrap(), half.len());
+        let halves: Vec<f16> = half.chunks_exact(2).map(|b| f16::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(&halves[..3], &[f16::ONE, f16::from_f32(-2.5), f16::ONE]);
+        assert_eq!(halves[3], f16::INFINITY);
+        assert!(halves[4].is_nan());
+
+        let (_, brain) = cast(&meta, &data, &DType::BF16).unwrap();
+        let brains: Vec<bf16> = brain.chunks_exact(2).map(|b| bf16::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(brains[3], bf16::from_f32(1e6));
+    }
+
+    #[test]
+    fn integer_targets_round_and_saturate() {
+        let (meta, data) = f32_tensor(&[0.5, 1.5, -2.5, 300.0, -7.0, f32::NAN, f32::INFINITY]);
+
+        let (_, bytes) = cast(&meta, &data, &DType::U8).unwrap();
+        assert_eq!(bytes.as_ref(), &[0, 2, 0, 255, 0, 0, 255]);
+
+        let (_, ints) = cast(&meta, &data, &DType::I32).unwrap();
+        let ints: Vec<i32> = ints.chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(ints, vec![0, 2, -2, 300, -7, 0, i32::MAX]);
+
+        let wide = TensorMeta::new(DType::I64, vec![3], StorageLayout::RowMajor).unwrap();
+        let values: Vec<u8> = [i64::MAX, -1, 42].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let (_, narrow) = cast(&wide, &values, &DType::I32).unwrap();
+        let narrow: Vec<i32> = narrow.chunks_exact(4).map(|b| i32::from_le_bytes(b.try_into().unwrap())).collect();
+        assert_eq!(narrow, vec![i32::MAX, -1, 42]);
+    }
+
+    #[test]
+    fn sparse_tensors_are_rejected() {
+        let meta = TensorMeta::new_sparse(DType::F32, vec![4], crate::tensor::meta::SparseFormat::Coo, 0).unwrap();
+        assert!(matches!(cast(&meta, &[], &DType::F16), Err(TensorError::UnsupportedFormat(_))));
+    }
+}
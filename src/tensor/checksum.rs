@@ -0,0 +1,49 @@
+//! Payload checksums used to verify tensor bytes end to end.
+//! The checksum is computed once when a tensor is created, stored with it in the cache and sent with
+//! the first chunk of every Get, so the client can verify the reassembled bytes match what was put.
+
+use std::hash::Hasher;
+use twox_hash::XxHash3_64;
+
+/// Computes the xxh3 (64 bit) checksum of a tensor payload.
+pub fn compute(data: &[u8]) -> u64 {
+    XxHash3_64::oneshot(data)
+}
+
+/// Computes the same checksum as `compute` over a payload that arrives in chunks.
+#[derive(Default)]
+pub struct StreamingChecksum(XxHash3_64);
+
+impl StreamingChecksum {
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.0.write(chunk);
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_detects_single_bit_flip() {
+        let mut data = vec![0u8; 1024];
+        let original = compute(&data);
+        data[512] ^= 1;
+        assert_ne!(original, compute(&data));
+    }
+
+    #[test]
+    fn streaming_checksum_matches_oneshot() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7) as u8).collect();
+        let mut streaming = StreamingChecksum::default();
+        for chunk in data.chunks(333) {
+            streaming.update(chunk);
+        }
+        assert_eq!(streaming.finish(), compute(&data));
+        assert_eq!(StreamingChecksum::default().finish(), compute(&[]));
+    }
+}
@@ -0,0 +1,257 @@
+//! Per tensor compression codecs.
+//! A tensor can be stored LZ4 or zstd compressed, optionally after a shuffle prefilter keyed on the dtype
+//! width. Byte shuffle groups the i-th byte of every element together and bit shuffle groups the i-th bit,
+//! which turns the mostly equal high bytes of floats, and the zeros of sparse activations, into long runs
+//! the codecs compress well.
+//! Trailing elements that do not fill a full block of 8 are left unshuffled.
+//! Floating tensors can also be quantized before compression, see `quantize`.
+
+use bytes::Bytes;
+use crate::error::cache_error::CacheError;
+use crate::tensor::meta::TensorMeta;
+use crate::tensor::quantize::{self, Quantization};
+
+/// zstd level used for all puts, favouring speed since compression runs on the put path.
+const ZSTD_LEVEL: i32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    None,
+    Lz4,
+    Zstd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shuffle {
+    #[default]
+    None,
+    Byte,
+    Bit,
+}
+
+/// Describes how the stored bytes of a tensor are encoded. The default is raw, uncompressed bytes.
+/// Quantization is applied first, then the shuffle and the codec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Encoding {
+    pub codec: Codec,
+    pub shuffle: Shuffle,
+    pub quantization: Quantization,
+}
+
+impl Encoding {
+    pub fn new(codec: Codec, shuffle: Shuffle) -> Self {
+        Self { codec, shuffle, quantization: Quantization::None }
+    }
+
+    pub fn with_quantization(mut self, quantization: Quantization) -> Self {
+        self.quantization = quantization;
+        self
+    }
+
+    /// Returns true if the stored bytes are the raw tensor bytes.
+    pub fn is_raw(&self) -> bool {
+        self.codec == Codec::None && self.quantization == Quantization::None
+    }
+
+    /// Returns true if decoding does not give back the exact bytes that were encoded.
+    pub fn is_lossy(&self) -> bool {
+        self.quantization != Quantization::None
+    }
+}
+
+/// Encodes the raw bytes of a tensor described by `meta`.
+/// A shuffle without a codec has no effect, so it is skipped in that case.
+pub fn encode(data: &Bytes, encoding: Encoding, meta: &TensorMeta) -> Result<Bytes, CacheError> {
+    if encoding.is_raw() {
+        return Ok(data.clone());
+    }
+    let quantized = match encoding.quantization {
+        Quantization::None => data.clone(),
+        quantization => Bytes::from(quantize::quantize(data, meta, quantization)?),
+    };
+    if encoding.codec == Codec::None {
+        return Ok(quantized);
+    }
+    let type_width = stored_width(encoding, meta);
+    let shuffled = match encoding.shuffle {
+        Shuffle::None => quantized.to_vec(),
+        Shuffle::Byte => byte_shuffle(&quantized, type_width),
+        Shuffle::Bit => bit_shuffle(&quantized, type_width),
+    };
+    let compressed = match encoding.codec {
+        Codec::None => unreachable!("raw encoding returned early"),
+        Codec::Lz4 => lz4_flex::compress_prepend_size(&shuffled),
+        Codec::Zstd => zstd::bulk::compress(&shuffled, ZSTD_LEVEL)
+            .map_err(|e| CacheError::Codec(e.to_string()))?,
+    };
+    Ok(Bytes::from(compressed))
+}
+
+/// Decodes stored bytes back into the raw bytes of a tensor described by `meta`.
+pub fn decode(data: &Bytes, encoding: Encoding, meta: &TensorMeta) -> Result<Bytes, CacheError> {
+    if encoding.is_raw() {
+        return Ok(data.clone());
+    }
+    let quantized = if encoding.codec == Codec::None {
+        data.clone()
+    } else {
+        let quantized_len = quantize::quantized_len(meta, encoding.quantization)?;
+        let decompressed = match encoding.codec {
+            Codec::None => unreachable!("checked above"),
+            Codec::Lz4 => lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| CacheError::Codec(e.to_string()))?,
+            Codec::Zstd => zstd::bulk::decompress(data, quantized_len)
+                .map_err(|e| CacheError::Codec(e.to_string()))?,
+        };
+        if decompressed.len() != quantized_len {
+            return Err(CacheError::Codec(format!(
+                "decoded {} bytes, expected {}", decompressed.len(), quantized_len
+            )));
+        }
+        let type_width = stored_width(encoding, meta);
+        let unshuffled = match encoding.shuffle {
+            Shuffle::None => decompressed,
+            Shuffle::Byte => byte_unshuffle(&decompressed, type_width),
+            Shuffle::Bit => bit_unshuffle(&decompressed, type_width),
+        };
+        Bytes::from(unshuffled)
+    };
+    match encoding.quantization {
+        Quantization::None => Ok(quantized),
+        quantization => Ok(Bytes::from(quantize::dequantize(&quantized, meta, quantization)?)),
+    }
+}
+
+/// Width of one element as it is handed to the shuffle, after quantization.
+fn stored_width(encoding: Encoding, meta: &TensorMeta) -> usize {
+    match encoding.quantization {
+        Quantization::None => meta.dtype().size_bytes(),
+        Quantization::F16 | Quantization::BF16 => 2,
+        Quantization::Int8 => 1,
+    }
+}
+
+/// out[b * n + i] = in[i * width + b], for n complete elements.
+fn byte_shuffle(data: &[u8], width: usize) -> Vec<u8> {
+    let n = data.len() / width;
+    let mut out = vec![0u8; data.len()];
+    for i in 0..n {
+        for b in 0..width {
+            out[b * n + i] = data[i * width + b];
+        }
+    }
+    out[n * width..].copy_from_slice(&data[n * width..]);
+    out
+}
+
+fn byte_unshuffle(data: &[u8], width: usize) -> Vec<u8> {
+    let n = data.len() / width;
+    let mut out = vec![0u8; data.len()];
+    for i in 0..n {
+        for b in 0..width {
+            out[i * width + b] = data[b * n + i];
+        }
+    }
+    out[n * width..].copy_from_slice(&data[n * width..]);
+    out
+}
+
+/// Bit p of element i is written to bit plane p, which holds one bit per element.
+/// Only the leading multiple of 8 elements is shuffled so every bit plane is a whole number of bytes.
+fn bit_shuffle(data: &[u8], width: usize) -> Vec<u8> {
+    let blocked = (data.len() / width) / 8 * 8;
+    let plane_len = blocked / 8;
+    let mut out = vec![0u8; data.len()];
+    for i in 0..blocked {
+        let element = &data[i * width..(i + 1) * width];
+        for p in 0..width * 8 {
+            let bit = (element[p / 8] >> (p % 8)) & 1;
+            out[p * plane_len + i / 8] |= bit << (i % 8);
+        }
+    }
+    out[blocked * width..].copy_from_slice(&data[blocked * width..]);
+    out
+}
+
+fn bit_unshuffle(data: &[u8], width: usize) -> Vec<u8> {
+    let blocked = (data.len() / width) / 8 * 8;
+    let plane_len = blocked / 8;
+    let mut out = vec![0u8; data.len()];
+    for i in 0..blocked {
+        for p in 0..width * 8 {
+            let bit = (data[p * plane_len + i / 8] >> (i % 8)) & 1;
+            out[i * width + p / 8] |= bit << (p % 8);
+        }
+    }
+    out[blocked * width..].copy_from_slice(&data[blocked * width..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::meta::{DType, StorageLayout};
+
+    fn f32_meta(len: usize) -> TensorMeta {
+        TensorMeta::new(DType::F32, vec![len], StorageLayout::RowMajor).unwrap()
+    }
+
+    fn relu_activations(len: usize) -> Bytes {
+        //mostly zeros with a few positive values, like activations after a ReLU.
+        let values: Vec<f32> = (0..len).map(|i| if i % 7 == 0 { i as f32 * 0.5 } else { 0.0 }).collect();
+        Bytes::from(values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>())
+    }
+
+    #[test]
+    fn every_encoding_round_trips() {
+        //1003 elements so both shuffles have a tail that is not a multiple of 8.
+        let data = relu_activations(1003);
+        for codec in [Codec::Lz4, Codec::Zstd] {
+            for shuffle in [Shuffle::None, Shuffle::Byte, Shuffle::Bit] {
+                let encoding = Encoding::new(codec, shuffle);
+                let encoded = encode(&data, encoding, &f32_meta(1003)).unwrap();
+                assert!(encoded.len() < data.len(), "{:?} did not compress", encoding);
+                let decoded = decode(&encoded, encoding, &f32_meta(1003)).unwrap();
+                assert_eq!(decoded, data, "{:?} did not round trip", encoding);
+            }
+        }
+    }
+
+    #[test]
+    fn shuffles_are_inverse() {
+        let data: Vec<u8> = (0..=255).cycle().take(8 * 37 + 5).collect();
+        for width in [1, 2, 4, 8] {
+            assert_eq!(byte_unshuffle(&byte_shuffle(&data, width), width), data);
+            assert_eq!(bit_unshuffle(&bit_shuffle(&data, width), width), data);
+        }
+    }
+
+    #[test]
+    fn raw_encoding_is_passthrough() {
+        let data = relu_activations(16);
+        let encoding = Encoding::new(Codec::None, Shuffle::Bit);
+        assert_eq!(encode(&data, encoding, &f32_meta(16)).unwrap(), data);
+    }
+
+    #[test]
+    fn truncated_payload_fails_to_decode() {
+        let data = relu_activations(256);
+        let encoding = Encoding::new(Codec::Zstd, Shuffle::Byte);
+        let encoded = encode(&data, encoding, &f32_meta(256)).unwrap();
+        let truncated = encoded.slice(..encoded.len() / 2);
+        assert!(decode(&truncated, encoding, &f32_meta(256)).is_err());
+    }
+
+    #[test]
+    fn quantization_composes_with_compression() {
+        let data = relu_activations(1024);
+        let encoding = Encoding::new(Codec::Lz4, Shuffle::Bit).with_quantization(Quantization::BF16);
+        let encoded = encode(&data, encoding, &f32_meta(1024)).unwrap();
+        assert!(encoded.len() < data.len() / 2);
+        let decoded = decode(&encoded, encoding, &f32_meta(1024)).unwrap();
+        assert_eq!(decoded.len(), data.len());
+        //zeros are exact in bf16, so the sparse part of the activations survives unchanged.
+        assert_eq!(&decoded[4..28], &data[4..28]);
+    }
+}
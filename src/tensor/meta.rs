@@ -1,60 +1,142 @@
+use crate::error::tensor_error::TensorError;
+
 //declaration for tensor metadata
 #[derive(Clone)]
 pub struct TensorMeta {
     dtype: DType, //data type stored in the tensor
     shape: Vec<usize>, //shape of tensor (dimensions)
-    layout: StorageLayout //row major or column major in memory for faster access.
+    layout: StorageLayout, //row major or column major in memory for faster access.
+    format: TensorFormat, //dense unless the payload also carries indices or row offsets.
 }
 
 impl TensorMeta {
     /***
     Constructor for TensorMeta object
      */
-    pub fn new(dtype: DType, shape: Vec<usize>,layout: StorageLayout) -> Result<Self,&'static str> {
+    pub fn new(dtype: DType, shape: Vec<usize>,layout: StorageLayout) -> Result<Self,TensorError> {
         //creates a new tensor meta instance.
-        let tensor_meta = TensorMeta{dtype, shape, layout};
+        let tensor_meta = TensorMeta{dtype, shape, layout, format: TensorFormat::Dense};
         tensor_meta.validate()?;
         Ok(tensor_meta)
     }
 
     /***
-    Calculates the number of elements in a given tensor using its shape dimensions
+    Constructor for a sparse tensor with `nnz` stored values and the dense logical shape `shape`. The
+    payload layout is described in the `sparse` module.
      */
-    pub fn num_elements(&self) -> Result<usize,&'static str> {
+    pub fn new_sparse(dtype: DType, shape: Vec<usize>, format: SparseFormat, nnz: usize) -> Result<Self,TensorError> {
+        let tensor_meta = TensorMeta{
+            dtype,
+            shape,
+            layout: StorageLayout::RowMajor,
+            format: TensorFormat::Sparse(Sparsity { format, nnz }),
+        };
+        tensor_meta.validate()?;
+        Ok(tensor_meta)
+    }
+
+    /***
+    Constructor for a ragged tensor of `rows` rows of different lengths. `values_shape` is the shape of
+    the flat values, whose first dimension is the total length of all rows. The payload layout is
+    described in the `ragged` module.
+     */
+    pub fn new_ragged(dtype: DType, rows: usize, values_shape: Vec<usize>) -> Result<Self,TensorError> {
+        let tensor_meta = TensorMeta{
+            dtype,
+            shape: values_shape,
+            layout: StorageLayout::RowMajor,
+            format: TensorFormat::Ragged { rows },
+        };
+        tensor_meta.validate()?;
+        Ok(tensor_meta)
+    }
+
+    /***
+    Calculates the number of elements in a given tensor using its shape dimensions. A rank-0 tensor
+    (scalar) has one element and a tensor with any zero length dimension has none.
+     */
+    pub fn num_elements(&self) -> Result<usize,TensorError> {
         //find the number of elements in our tensor using this.
+        if self.shape.contains(&0) {
+            return Ok(0);
+        }
         let mut total_elements = 1usize;
         for dim in &self.shape {
             total_elements = total_elements.checked_mul(*dim).
-                ok_or("Shape overflow")?;
+                ok_or(TensorError::Overflow)?;
         }
         Ok(total_elements)
     }
 
     /***
     Returns the total size of the object. It is defined by number of elements * size in bytes of each element
+    for dense tensors, by the size of the index and value buffers for sparse tensors and by the size of the
+    offsets and values for ragged tensors.
+     */
+    pub fn total_byte_size(&self) -> Result<usize,TensorError> {
+        match self.format {
+            TensorFormat::Dense => self.dense_byte_size(),
+            TensorFormat::Sparse(sparsity) => self.sparse_byte_size(sparsity),
+            TensorFormat::Ragged { rows } => rows
+                .checked_add(1)
+                .and_then(|offsets| offsets.checked_mul(INDEX_WIDTH))
+                .and_then(|offset_bytes| offset_bytes.checked_add(self.dense_byte_size().ok()?))
+                .ok_or(TensorError::Overflow),
+        }
+    }
+
+    /***
+    Returns the size of a dense tensor of this shape. It is the total size for dense tensors, the size
+    once densified for sparse tensors and the size of the values for ragged tensors.
      */
-    pub fn total_byte_size(&self) -> Result<usize,&'static str> {
+    pub fn dense_byte_size(&self) -> Result<usize,TensorError> {
         let total_elements = self.num_elements()?;
         total_elements
             .checked_mul(self.dtype.size_bytes())
-            .ok_or("tensor byte size overflow")
+            .ok_or(TensorError::Overflow)
+    }
+
+    fn sparse_byte_size(&self, sparsity: Sparsity) -> Result<usize,TensorError> {
+        let index_count = match sparsity.format {
+            SparseFormat::Coo => self.shape.len().checked_mul(sparsity.nnz),
+            //row pointers followed by one column index per value.
+            SparseFormat::Csr => self.shape
+                .first()
+                .and_then(|rows| rows.checked_add(1))
+                .and_then(|pointers| pointers.checked_add(sparsity.nnz)),
+        }.ok_or(TensorError::Overflow)?;
+        let index_bytes = index_count
+            .checked_mul(INDEX_WIDTH)
+            .ok_or(TensorError::Overflow)?;
+        sparsity.nnz
+            .checked_mul(self.dtype.size_bytes())
+            .and_then(|value_bytes| value_bytes.checked_add(index_bytes))
+            .ok_or(TensorError::Overflow)
     }
     /***
-    Validates whether a given tensor is structurally correct by:
-    1. verifies it exists and has proper dimensionality
-    2. verifies that none of the dimensions are 0
-    3. verifies that there is no overflow in regard to usize and its total size in bytes.
+    Validates whether a given tensor is structurally correct by verifying that there is no overflow in
+    regard to usize and its total size in bytes.
+    An empty shape describes a scalar and zero length dimensions describe an empty tensor, both are valid.
      */
-    pub fn validate(&self) -> Result<(),&'static str> {
-        //all dimensions are non zero, non zero shape length, number of elements are equal to
-        // product(shape) * size_in_bytes(dtype)
-
-        if self.shape.is_empty() {
-           return Err("Shape cannot be empty")
-        }
-        for dimension in &self.shape {
-            if *dimension == 0 {
-                return Err("Shape cannot be zero")
+    pub fn validate(&self) -> Result<(),TensorError> {
+        //number of elements are equal to product(shape) * size_in_bytes(dtype)
+        self.dense_byte_size()?;
+        match self.format {
+            TensorFormat::Dense => {}
+            TensorFormat::Sparse(sparsity) => {
+                //sparse tensors are also checked against their dense shape, so that they can be densified.
+                if sparsity.format == SparseFormat::Csr && self.shape.len() != 2 {
+                    return Err(TensorError::RankMismatch { expected: 2, actual: self.shape.len() })
+                }
+                let elements = self.num_elements()?;
+                if sparsity.nnz > elements {
+                    return Err(TensorError::TooManyValues { nnz: sparsity.nnz, elements })
+                }
+            }
+            TensorFormat::Ragged { .. } => {
+                if self.shape.is_empty() {
+                    return Err(TensorError::EmptyShape)
+                }
             }
         }
         self.total_byte_size()?;
@@ -65,6 +147,20 @@ impl TensorMeta {
     pub fn dtype(&self) -> &DType { &self.dtype }
     pub fn shape(&self) -> &[usize] { &self.shape }
     pub fn layout(&self) -> &StorageLayout { &self.layout }
+    pub fn format(&self) -> &TensorFormat { &self.format }
+    pub fn is_dense(&self) -> bool { self.format == TensorFormat::Dense }
+    pub fn sparsity(&self) -> Option<&Sparsity> {
+        match &self.format {
+            TensorFormat::Sparse(sparsity) => Some(sparsity),
+            _ => None,
+        }
+    }
+    pub fn ragged_rows(&self) -> Option<usize> {
+        match self.format {
+            TensorFormat::Ragged { rows } => Some(rows),
+            _ => None,
+        }
+    }
 
 }
 
@@ -75,6 +171,8 @@ pub enum DType{
     I32,
     I64,
     U8,
+    F16,
+    BF16,
 }
 
 impl DType {
@@ -85,8 +183,15 @@ impl DType {
             DType::I32 => 4,
             DType::I64 => 8,
             DType::U8 => 1,
+            DType::F16 => 2,
+            DType::BF16 => 2,
         }
     }
+
+    /// Returns true for the floating point dtypes, the only ones that can hold NaN or infinities.
+    pub fn is_float(&self) -> bool {
+        matches!(self, DType::F32 | DType::F64 | DType::F16 | DType::BF16)
+    }
 }
 
 #[derive(Debug, Clone,PartialEq)]
@@ -95,6 +200,35 @@ pub enum StorageLayout{
     ColumnMajor,
 }
 
+/// Width in bytes of one sparse index or ragged offset, both are stored as little endian i64.
+pub const INDEX_WIDTH: usize = 8;
+
+/// How the payload of a tensor is organised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TensorFormat {
+    /// one value per element of the shape.
+    Dense,
+    /// indices and values of the non zero elements, see the `sparse` module.
+    Sparse(Sparsity),
+    /// row offsets followed by the flat values of all rows, see the `ragged` module.
+    Ragged { rows: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseFormat {
+    /// coordinate list, one index per dimension for every value.
+    Coo,
+    /// compressed sparse rows, only for two dimensional tensors.
+    Csr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sparsity {
+    pub format: SparseFormat,
+    /// number of stored values.
+    pub nnz: usize,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -111,14 +245,72 @@ mod tests {
     }
 
     #[test]
-    fn test_meta_zero_dim_fails() {
+    fn test_meta_scalar_has_one_element() {
+        let meta = TensorMeta::new(
+            DType::F64,
+            vec![],
+            StorageLayout::RowMajor
+        ).unwrap();
+
+        assert_eq!(meta.num_elements().unwrap(), 1);
+        assert_eq!(meta.total_byte_size().unwrap(), 8);
+    }
+
+    #[test]
+    fn test_meta_zero_dim_is_empty() {
         let meta = TensorMeta::new(
             DType::F32,
             vec![4, 0],
             StorageLayout::RowMajor
+        ).unwrap();
+
+        assert_eq!(meta.total_byte_size().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_meta_zero_dim_does_not_overflow() {
+        let meta = TensorMeta::new(
+            DType::F64,
+            vec![usize::MAX, 2, 0],
+            StorageLayout::RowMajor
+        ).unwrap();
+
+        assert_eq!(meta.num_elements().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sparse_meta_sizes() {
+        let coo = TensorMeta::new_sparse(DType::F32, vec![1000, 1000], SparseFormat::Coo, 10).unwrap();
+        assert_eq!(coo.total_byte_size().unwrap(), 2 * 10 * 8 + 10 * 4);
+        assert_eq!(coo.dense_byte_size().unwrap(), 4_000_000);
+
+        let csr = TensorMeta::new_sparse(DType::F64, vec![3, 5], SparseFormat::Csr, 4).unwrap();
+        assert_eq!(csr.total_byte_size().unwrap(), (4 + 4) * 8 + 4 * 8);
+    }
+
+    #[test]
+    fn test_sparse_meta_invalid() {
+        assert_eq!(
+            TensorMeta::new_sparse(DType::F32, vec![2, 2, 2], SparseFormat::Csr, 1).err(),
+            Some(TensorError::RankMismatch { expected: 2, actual: 3 })
         );
+        assert_eq!(
+            TensorMeta::new_sparse(DType::F32, vec![2, 2], SparseFormat::Coo, 5).err(),
+            Some(TensorError::TooManyValues { nnz: 5, elements: 4 })
+        );
+        assert_eq!(
+            TensorMeta::new_sparse(DType::F32, vec![usize::MAX, 2], SparseFormat::Coo, 1).err(),
+            Some(TensorError::Overflow)
+        );
+    }
 
-        assert!(meta.is_err());
+    #[test]
+    fn test_ragged_meta_sizes() {
+        let meta = TensorMeta::new_ragged(DType::I32, 3, vec![10]).unwrap();
+        assert_eq!(meta.ragged_rows(), Some(3));
+        assert_eq!(meta.total_byte_size().unwrap(), 4 * 8 + 10 * 4);
+        assert!(TensorMeta::new_ragged(DType::I32, 3, vec![]).is_err());
+        assert!(TensorMeta::new_ragged(DType::I32, usize::MAX, vec![1]).is_err());
     }
 
     #[test]
@@ -129,6 +321,6 @@ mod tests {
             StorageLayout::RowMajor
         );
 
-        assert!(meta.is_err());
+        assert_eq!(meta.err(), Some(TensorError::Overflow));
     }
 }
\ No newline at end of file
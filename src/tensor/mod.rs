@@ -1,2 +1,9 @@
+pub mod cast;
+pub mod checksum;
+pub mod codec;
 pub mod meta;
+pub mod quantize;
+pub mod ragged;
+pub mod sparse;
+pub mod stats;
 pub mod tensor;
\ No newline at end of file
@@ -0,0 +1,224 @@
+//! Lossy quantize-on-store for F32 and F64 tensors.
+//! F16 and BF16 round every element to nearest even. Int8 is asymmetric with one scale and zero point per
+//! channel, where a channel is a slice along the outermost storage dimension (the first dimension for
+//! row-major tensors, the last one for column-major tensors). Tensors with fewer than two dimensions
+//! use a single channel.
+//! The int8 payload is laid out as `[scales: f32 x C][zero points: i8 x C][values: i8 x N]`, so the
+//! parameters are accounted with the tensor. Int8 does not preserve NaN or infinities, NaN is stored as
+//! zero and infinities saturate to the channel range.
+
+use half::{bf16, f16};
+use crate::error::cache_error::CacheError;
+use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Quantization {
+    #[default]
+    None,
+    F16,
+    BF16,
+    Int8,
+}
+
+/// Size in bytes of the quantized payload of a tensor described by `meta`.
+pub fn quantized_len(meta: &TensorMeta, quantization: Quantization) -> Result<usize, CacheError> {
+    if quantization == Quantization::None {
+        return Ok(meta.total_byte_size()?);
+    }
+    if !meta.is_dense() {
+        return Err(CacheError::UnsupportedEncoding("cannot quantize sparse or ragged tensors".to_string()));
+    }
+    let elements = meta.num_elements()?;
+    Ok(match quantization {
+        Quantization::None => unreachable!("returned above"),
+        Quantization::F16 | Quantization::BF16 => elements * 2,
+        Quantization::Int8 => channels(meta, elements).0 * 5 + elements,
+    })
+}
+
+/// Quantizes raw little endian F32/F64 bytes.
+pub fn quantize(data: &[u8], meta: &TensorMeta, quantization: Quantization) -> Result<Vec<u8>, CacheError> {
+    if !meta.is_dense() && quantization != Quantization::None {
+        return Err(CacheError::UnsupportedEncoding("cannot quantize sparse or ragged tensors".to_string()));
+    }
+    let values = read_floats(data, meta.dtype())?;
+    match quantization {
+        Quantization::None => Ok(data.to_vec()),
+        Quantization::F16 => Ok(values.iter().flat_map(|v| f16::from_f64(*v).to_le_bytes()).collect()),
+        Quantization::BF16 => Ok(values.iter().flat_map(|v| bf16::from_f64(*v).to_le_bytes()).collect()),
+        Quantization::Int8 => Ok(quantize_int8(&values, meta)),
+    }
+}
+
+/// Dequantizes a payload produced by `quantize` back into raw bytes of `meta.dtype()`.
+pub fn dequantize(data: &[u8], meta: &TensorMeta, quantization: Quantization) -> Result<Vec<u8>, CacheError> {
+    if data.len() != quantized_len(meta, quantization)? {
+        return Err(CacheError::Codec(format!(
+            "quantized payload is {} bytes, expected {}", data.len(), quantized_len(meta, quantization)?
+        )));
+    }
+    let values: Vec<f64> = match quantization {
+        Quantization::None => return Ok(data.to_vec()),
+        Quantization::F16 => data.chunks_exact(2).map(|b| f16::from_le_bytes([b[0], b[1]]).to_f64()).collect(),
+        Quantization::BF16 => data.chunks_exact(2).map(|b| bf16::from_le_bytes([b[0], b[1]]).to_f64()).collect(),
+        Quantization::Int8 => dequantize_int8(data, meta),
+    };
+    Ok(write_floats(&values, meta.dtype()))
+}
+
+/// Returns (channel count, elements per channel).
+fn channels(meta: &TensorMeta, elements: usize) -> (usize, usize) {
+    let shape = meta.shape();
+    if shape.len() < 2 {
+        return (1, elements);
+    }
+    let outer = match meta.layout() {
+        StorageLayout::RowMajor => shape[0],
+        StorageLayout::ColumnMajor => shape[shape.len() - 1],
+    };
+    (outer, elements.checked_div(outer).unwrap_or(0))
+}
+
+fn quantize_int8(values: &[f64], meta: &TensorMeta) -> Vec<u8> {
+    let (count, per_channel) = channels(meta, values.len());
+    let mut scales = Vec::with_capacity(count * 4);
+    let mut zero_points = Vec::with_capacity(count);
+    let mut quantized = Vec::with_capacity(values.len());
+
+    for c in 0..count {
+        let channel = &values[c * per_channel..(c + 1) * per_channel];
+        //the range always contains zero so that zero is represented exactly.
+        let (min, max) = channel
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((0.0f64, 0.0f64), |(lo, hi), v| (lo.min(*v), hi.max(*v)));
+        let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+        let zero_point = (-128.0 - (min / scale).round()).clamp(-128.0, 127.0);
+
+        scales.extend_from_slice(&(scale as f32).to_le_bytes());
+        zero_points.push(zero_point as i8 as u8);
+        for v in channel {
+            let v = if v.is_nan() { 0.0 } else { *v };
+            let q = ((v / scale).round() + zero_point).clamp(-128.0, 127.0);
+            quantized.push(q as i8 as u8);
+        }
+    }
+
+    let mut out = scales;
+    out.extend_from_slice(&zero_points);
+    out.extend_from_slice(&quantized);
+    out
+}
+
+fn dequantize_int8(data: &[u8], meta: &TensorMeta) -> Vec<f64> {
+    let elements = meta.num_elements().unwrap_or(0);
+    let (count, per_channel) = channels(meta, elements);
+    let (scales, rest) = data.split_at(count * 4);
+    let (zero_points, quantized) = rest.split_at(count);
+
+    let mut values = Vec::with_capacity(elements);
+    for c in 0..count {
+        let scale = f32::from_le_bytes(scales[c * 4..c * 4 + 4].try_into().unwrap()) as f64;
+        let zero_point = zero_points[c] as i8 as f64;
+        for q in &quantized[c * per_channel..(c + 1) * per_channel] {
+            values.push((*q as i8 as f64 - zero_point) * scale);
+        }
+    }
+    values
+}
+
+fn read_floats(data: &[u8], dtype: &DType) -> Result<Vec<f64>, CacheError> {
+    match dtype {
+        DType::F32 => Ok(data.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64).collect()),
+        DType::F64 => Ok(data.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect()),
+        other => Err(CacheError::UnsupportedEncoding(format!("cannot quantize {:?} tensors, only F32 and F64", other))),
+    }
+}
+
+fn write_floats(values: &[f64], dtype: &DType) -> Vec<u8> {
+    match dtype {
+        DType::F64 => values.iter().flat_map(|v| v.to_le_bytes()).collect(),
+        _ => values.iter().flat_map(|v| (*v as f32).to_le_bytes()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(dtype: DType, shape: Vec<usize>) -> TensorMeta {
+        TensorMeta::new(dtype, shape, StorageLayout::RowMajor).unwrap()
+    }
+
+    fn sample(len: usize) -> Vec<f32> {
+        (0..len).map(|i| ((i as f32) * 0.37).sin() * (1.0 + (i % 13) as f32)).collect()
+    }
+
+    fn round_trip(values: &[f32], meta: &TensorMeta, quantization: Quantization) -> Vec<f32> {
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let quantized = quantize(&raw, meta, quantization).unwrap();
+        assert_eq!(quantized.len(), quantized_len(meta, quantization).unwrap());
+        let restored = dequantize(&quantized, meta, quantization).unwrap();
+        restored.chunks_exact(4).map(|b| f32::from_le_bytes(b.try_into().unwrap())).collect()
+    }
+
+    #[test]
+    fn half_precision_error_is_bounded() {
+        let values = sample(512);
+        let meta = meta(DType::F32, vec![8, 64]);
+        //relative error of round to nearest is at most half an ulp: 2^-11 for f16 and 2^-8 for bf16.
+        for (quantization, bound) in [(Quantization::F16, 1.0 / 2048.0), (Quantization::BF16, 1.0 / 256.0)] {
+            let restored = round_trip(&values, &meta, quantization);
+            for (original, restored) in values.iter().zip(restored) {
+                assert!((original - restored).abs() <= original.abs() * bound + 1e-7, "{:?}", quantization);
+            }
+        }
+    }
+
+    #[test]
+    fn int8_error_is_bounded_per_channel() {
+        let values = sample(512);
+        let meta = meta(DType::F32, vec![8, 64]);
+        let restored = round_trip(&values, &meta, Quantization::Int8);
+        for channel in 0..8 {
+            let range = &values[channel * 64..(channel + 1) * 64];
+            let min = range.iter().cloned().fold(0.0f32, f32::min);
+            let max = range.iter().cloned().fold(0.0f32, f32::max);
+            let scale = (max - min) / 255.0;
+            for i in channel * 64..(channel + 1) * 64 {
+                //half a quantization step, plus slack for the f32 scale.
+                assert!((values[i] - restored[i]).abs() <= scale * 0.5 + 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn int8_keeps_zero_exact_and_handles_constant_channels() {
+        let values = vec![0.0f32, 0.0, 0.0, 0.0, 3.0, 3.0, 3.0, 3.0];
+        let meta = meta(DType::F32, vec![2, 4]);
+        let restored = round_trip(&values, &meta, Quantization::Int8);
+        assert_eq!(&restored[..4], &[0.0; 4]);
+        for v in &restored[4..] {
+            assert!((v - 3.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn f64_round_trips_through_int8() {
+        let values: Vec<f64> = (0..16).map(|i| i as f64 - 4.0).collect();
+        let raw: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let meta = meta(DType::F64, vec![16]);
+        let restored = dequantize(&quantize(&raw, &meta, Quantization::Int8).unwrap(), &meta, Quantization::Int8).unwrap();
+        for (original, restored) in values.iter().zip(restored.chunks_exact(8)) {
+            let restored = f64::from_le_bytes(restored.try_into().unwrap());
+            assert!((original - restored).abs() <= 15.0 / 255.0 / 2.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn integer_tensors_cannot_be_quantized() {
+        let meta = meta(DType::I32, vec![4]);
+        let result = quantize(&[0u8; 16], &meta, Quantization::F16);
+        assert!(matches!(result, Err(CacheError::UnsupportedEncoding(_))));
+    }
+}
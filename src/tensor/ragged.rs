@@ -0,0 +1,148 @@
+//! Payload layout and validation of ragged tensors, whose rows have different lengths.
+//! Ragged payloads are `[offsets: rows + 1][values]`, with offsets as little endian i64. Row `i` holds the
+//! values `offsets[i]..offsets[i + 1]` along the first dimension of the flat values, so the offsets must
+//! start at 0, never decrease and end at the total length of all rows.
+
+use bytes::Bytes;
+use crate::error::tensor_error::TensorError;
+use crate::tensor::meta::{DType, StorageLayout, TensorMeta, INDEX_WIDTH};
+
+/// Packs rows of raw bytes into a ragged payload. Each row must hold whole elements of `inner_shape`,
+/// which is the shape of one position in a row (empty for rows of scalars).
+pub fn pack_rows(dtype: DType, inner_shape: &[usize], rows: &[&[u8]]) -> Result<(TensorMeta, Bytes), TensorError> {
+    if let Some(index) = inner_shape.iter().position(|&dim| dim == 0) {
+        //the length of a row cannot be recovered from its bytes when a position holds no bytes.
+        return Err(TensorError::ZeroDim { index: index + 1 });
+    }
+    let position_size = TensorMeta::new(dtype.clone(), inner_shape.to_vec(), StorageLayout::RowMajor)?
+        .total_byte_size()?;
+    let mut offsets = Vec::with_capacity(rows.len() + 1);
+    offsets.push(0i64);
+    let mut length = 0usize;
+    for row in rows {
+        if row.len() % position_size != 0 {
+            return Err(TensorError::LengthMismatch {
+                expected: row.len().next_multiple_of(position_size),
+                actual: row.len(),
+            });
+        }
+        length += row.len() / position_size;
+        offsets.push(length as i64);
+    }
+
+    let mut values_shape = vec![length];
+    values_shape.extend_from_slice(inner_shape);
+    let meta = TensorMeta::new_ragged(dtype, rows.len(), values_shape)?;
+    let mut payload = Vec::with_capacity(meta.total_byte_size()?);
+    for offset in offsets {
+        payload.extend_from_slice(&offset.to_le_bytes());
+    }
+    for row in rows {
+        payload.extend_from_slice(row);
+    }
+    Ok((meta, Bytes::from(payload)))
+}
+
+/// Checks that the offsets of a ragged payload split the values into rows. The payload length must
+/// already match `meta.total_byte_size()`.
+pub fn validate(meta: &TensorMeta, data: &[u8]) -> Result<(), TensorError> {
+    let offsets = read_offsets(meta, data)?;
+    if offsets[0] != 0 {
+        return Err(TensorError::InvalidOffsets { position: 0 });
+    }
+    if let Some(position) = offsets.windows(2).position(|w| w[0] > w[1]) {
+        return Err(TensorError::InvalidOffsets { position: position + 1 });
+    }
+    if offsets[offsets.len() - 1] != meta.shape()[0] {
+        return Err(TensorError::InvalidOffsets { position: offsets.len() - 1 });
+    }
+    Ok(())
+}
+
+/// Returns the metadata and values of one row as a dense row-major tensor. The values are sliced out
+/// of `data` without copying.
+pub fn row(meta: &TensorMeta, data: &Bytes, row: usize) -> Result<(TensorMeta, Bytes), TensorError> {
+    let offsets = read_offsets(meta, data)?;
+    if row + 1 >= offsets.len() {
+        return Err(TensorError::IndexOutOfBounds { dim: 0, index: row, extent: offsets.len() - 1 });
+    }
+    let (start, end) = (offsets[row], offsets[row + 1]);
+    let position_size = meta.dtype().size_bytes() * meta.shape()[1..].iter().product::<usize>();
+    let values_start = offsets.len() * INDEX_WIDTH;
+
+    let mut shape = vec![end - start];
+    shape.extend_from_slice(&meta.shape()[1..]);
+    let row_meta = TensorMeta::new(meta.dtype().clone(), shape, StorageLayout::RowMajor)?;
+    let row_data = data.slice(values_start + start * position_size..values_start + end * position_size);
+    Ok((row_meta, row_data))
+}
+
+fn read_offsets(meta: &TensorMeta, data: &[u8]) -> Result<Vec<usize>, TensorError> {
+    let rows = meta.ragged_rows().ok_or_else(|| TensorError::UnsupportedFormat("tensor is not ragged".to_string()))?;
+    data[..(rows + 1) * INDEX_WIDTH]
+        .chunks_exact(INDEX_WIDTH)
+        .enumerate()
+        .map(|(position, b)| {
+            let offset = i64::from_le_bytes(b.try_into().unwrap());
+            usize::try_from(offset).map_err(|_| TensorError::NegativeIndex { position })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn i32_bytes(values: &[i32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn packed_rows_can_be_read_back() {
+        let rows = [i32_bytes(&[1, 2, 3]), i32_bytes(&[]), i32_bytes(&[4])];
+        let rows: Vec<&[u8]> = rows.iter().map(Vec::as_slice).collect();
+        let (meta, payload) = pack_rows(DType::I32, &[], &rows).unwrap();
+        assert_eq!(meta.shape(), &[4]);
+        assert_eq!(payload.len(), meta.total_byte_size().unwrap());
+        validate(&meta, &payload).unwrap();
+
+        let (first_meta, first) = row(&meta, &payload, 0).unwrap();
+        assert_eq!(first_meta.shape(), &[3]);
+        assert_eq!(first.as_ref(), i32_bytes(&[1, 2, 3]).as_slice());
+        assert!(row(&meta, &payload, 1).unwrap().1.is_empty());
+        assert_eq!(row(&meta, &payload, 2).unwrap().1.as_ref(), i32_bytes(&[4]).as_slice());
+    }
+
+    #[test]
+    fn rows_keep_their_inner_shape() {
+        //two kv blocks with a head dimension of 2.
+        let rows = [vec![1u8, 2, 3, 4], vec![5u8, 6]];
+        let rows: Vec<&[u8]> = rows.iter().map(Vec::as_slice).collect();
+        let (meta, payload) = pack_rows(DType::U8, &[2], &rows).unwrap();
+        assert_eq!(meta.shape(), &[3, 2]);
+
+        let (row_meta, data) = row(&meta, &payload, 0).unwrap();
+        assert_eq!(row_meta.shape(), &[2, 2]);
+        assert_eq!(data.as_ref(), &[1, 2, 3, 4]);
+        assert_eq!(
+            pack_rows(DType::U8, &[2], &[&[1u8, 2, 3]]).err(),
+            Some(TensorError::LengthMismatch { expected: 4, actual: 3 })
+        );
+        assert_eq!(pack_rows(DType::U8, &[0], &[]).err(), Some(TensorError::ZeroDim { index: 1 }));
+    }
+
+    #[test]
+    fn invalid_offsets_are_rejected() {
+        let meta = TensorMeta::new_ragged(DType::U8, 2, vec![3]).unwrap();
+        let payload = |offsets: [i64; 3]| -> Vec<u8> {
+            let mut data: Vec<u8> = offsets.iter().flat_map(|o| o.to_le_bytes()).collect();
+            data.extend_from_slice(&[1, 2, 3]);
+            data
+        };
+        assert!(validate(&meta, &payload([0, 1, 3])).is_ok());
+        assert_eq!(validate(&meta, &payload([1, 1, 3])), Err(TensorError::InvalidOffsets { position: 0 }));
+        assert_eq!(validate(&meta, &payload([0, 2, 1])), Err(TensorError::InvalidOffsets { position: 2 }));
+        assert_eq!(validate(&meta, &payload([0, 1, 2])), Err(TensorError::InvalidOffsets { position: 2 }));
+        assert_eq!(validate(&meta, &payload([0, -1, 3])), Err(TensorError::NegativeIndex { position: 1 }));
+    }
+}
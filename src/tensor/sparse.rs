@@ -0,0 +1,187 @@
+//! Payload layout, validation and densification of sparse tensors. Indices are little endian i64.
+//! COO payloads are `[indices: rank x nnz][values: nnz]`, where the indices of dimension `d` are stored
+//! contiguously, as in `torch.sparse_coo_tensor`. CSR payloads are `[row pointers: rows + 1][column
+//! indices: nnz][values: nnz]`, as in `scipy.sparse.csr_matrix`.
+//! Valid tensors have every index in bounds and no duplicate coordinates, CSR column indices must also
+//! be sorted within each row.
+
+use bytes::Bytes;
+use crate::error::tensor_error::TensorError;
+use crate::tensor::meta::{SparseFormat, StorageLayout, TensorMeta, INDEX_WIDTH};
+
+/// Builds a COO payload from indices laid out as `[rank x nnz]` and the raw value bytes.
+pub fn coo_payload(indices: &[i64], values: &[u8]) -> Bytes {
+    concat(&[indices], values)
+}
+
+/// Builds a CSR payload from row pointers, column indices and the raw value bytes.
+pub fn csr_payload(row_pointers: &[i64], column_indices: &[i64], values: &[u8]) -> Bytes {
+    concat(&[row_pointers, column_indices], values)
+}
+
+/// Checks that the indices of a sparse payload describe valid, distinct coordinates of the dense shape.
+/// The payload length must already match `meta.total_byte_size()`.
+pub fn validate(meta: &TensorMeta, data: &[u8]) -> Result<(), TensorError> {
+    linear_indices(meta, data).map(|_| ())
+}
+
+/// Scatters a sparse payload into a zero filled row-major buffer of the dense shape. Fails with
+/// `TensorError::TooLarge` instead of allocating when the dense shape holds more than `max_bytes`, a few
+/// values can describe a shape far larger than memory.
+pub fn densify(meta: &TensorMeta, data: &[u8], max_bytes: usize) -> Result<(TensorMeta, Bytes), TensorError> {
+    let dense_meta = TensorMeta::new(meta.dtype().clone(), meta.shape().to_vec(), StorageLayout::RowMajor)?;
+    let size = dense_meta.total_byte_size()?;
+    if size > max_bytes {
+        return Err(TensorError::TooLarge { size, limit: max_bytes });
+    }
+    let width = meta.dtype().size_bytes();
+    let positions = linear_indices(meta, data)?;
+    let values = &data[data.len() - positions.len() * width..];
+
+    let mut dense = vec![0u8; size];
+    for (i, position) in positions.into_iter().enumerate() {
+        dense[position * width..(position + 1) * width].copy_from_slice(&values[i * width..(i + 1) * width]);
+    }
+    Ok((dense_meta, Bytes::from(dense)))
+}
+
+/// Returns the row-major position of every stored value, validating the indices on the way.
+fn linear_indices(meta: &TensorMeta, data: &[u8]) -> Result<Vec<usize>, TensorError> {
+    let sparsity = meta.sparsity().ok_or_else(|| TensorError::UnsupportedFormat("tensor is not sparse".to_string()))?;
+    let shape = meta.shape();
+    let nnz = sparsity.nnz;
+    match sparsity.format {
+        SparseFormat::Coo => {
+            let indices = read_indices(data, shape.len() * nnz)?;
+            let mut positions = vec![0usize; nnz];
+            for (dim, &extent) in shape.iter().enumerate() {
+                for (i, position) in positions.iter_mut().enumerate() {
+                    let index = indices[dim * nnz + i];
+                    if index >= extent {
+                        return Err(TensorError::IndexOutOfBounds { dim, index, extent });
+                    }
+                    //cannot overflow, the dense size was validated with the metadata.
+                    *position = *position * extent + index;
+                }
+            }
+            let mut sorted = positions.clone();
+            sorted.sort_unstable();
+            if sorted.windows(2).any(|w| w[0] == w[1]) {
+                return Err(TensorError::DuplicateIndex);
+            }
+            Ok(positions)
+        }
+        SparseFormat::Csr => {
+            let (rows, columns) = (shape[0], shape[1]);
+            let indices = read_indices(data, rows + 1 + nnz)?;
+            let (row_pointers, column_indices) = indices.split_at(rows + 1);
+            if row_pointers[0] != 0 {
+                return Err(TensorError::InvalidOffsets { position: 0 });
+            }
+            if row_pointers[rows] != nnz {
+                return Err(TensorError::InvalidOffsets { position: rows });
+            }
+            let mut positions = Vec::with_capacity(nnz);
+            for row in 0..rows {
+                let (start, end) = (row_pointers[row], row_pointers[row + 1]);
+                if start > end || end > nnz {
+                    return Err(TensorError::InvalidOffsets { position: row + 1 });
+                }
+                let row_columns = &column_indices[start..end];
+                if row_columns.windows(2).any(|w| w[0] >= w[1]) {
+                    return Err(TensorError::UnsortedIndices { row });
+                }
+                for &column in row_columns {
+                    if column >= columns {
+                        return Err(TensorError::IndexOutOfBounds { dim: 1, index: column, extent: columns });
+                    }
+                    positions.push(row * columns + column);
+                }
+            }
+            Ok(positions)
+        }
+    }
+}
+
+fn read_indices(data: &[u8], count: usize) -> Result<Vec<usize>, TensorError> {
+    data[..count * INDEX_WIDTH]
+        .chunks_exact(INDEX_WIDTH)
+        .enumerate()
+        .map(|(position, b)| {
+            let index = i64::from_le_bytes(b.try_into().unwrap());
+            usize::try_from(index).map_err(|_| TensorError::NegativeIndex { position })
+        })
+        .collect()
+}
+
+fn concat(index_buffers: &[&[i64]], values: &[u8]) -> Bytes {
+    let index_count: usize = index_buffers.iter().map(|b| b.len()).sum();
+    let mut payload = Vec::with_capacity(index_count * INDEX_WIDTH + values.len());
+    for buffer in index_buffers {
+        for index in buffer.iter() {
+            payload.extend_from_slice(&index.to_le_bytes());
+        }
+    }
+    payload.extend_from_slice(values);
+    Bytes::from(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::meta::DType;
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn coo_densifies_to_row_major() {
+        let meta = TensorMeta::new_sparse(DType::F32, vec![2, 3], SparseFormat::Coo, 2).unwrap();
+        //values at (0, 2) and (1, 0).
+        let payload = coo_payload(&[0, 1, 2, 0], &f32_bytes(&[1.5, -2.0]));
+        assert_eq!(payload.len(), meta.total_byte_size().unwrap());
+
+        let (dense_meta, dense) = densify(&meta, &payload, usize::MAX).unwrap();
+        assert!(dense_meta.sparsity().is_none());
+        assert_eq!(dense.as_ref(), f32_bytes(&[0.0, 0.0, 1.5, -2.0, 0.0, 0.0]).as_slice());
+    }
+
+    #[test]
+    fn csr_densifies_to_row_major() {
+        let meta = TensorMeta::new_sparse(DType::F32, vec![3, 2], SparseFormat::Csr, 3).unwrap();
+        let payload = csr_payload(&[0, 2, 2, 3], &[0, 1, 1], &f32_bytes(&[1.0, 2.0, 3.0]));
+
+        let (_, dense) = densify(&meta, &payload, usize::MAX).unwrap();
+        assert_eq!(dense.as_ref(), f32_bytes(&[1.0, 2.0, 0.0, 0.0, 0.0, 3.0]).as_slice());
+    }
+
+    #[test]
+    fn densify_refuses_shapes_above_the_limit() {
+        let meta = TensorMeta::new_sparse(DType::U8, vec![1 << 20, 1 << 20, 1 << 20], SparseFormat::Coo, 1).unwrap();
+        let payload = coo_payload(&[0, 0, 0], &[1]);
+        assert!(validate(&meta, &payload).is_ok());
+        assert_eq!(densify(&meta, &payload, 1 << 30).err(), Some(TensorError::TooLarge { size: 1 << 60, limit: 1 << 30 }));
+    }
+
+    #[test]
+    fn invalid_indices_are_rejected() {
+        let values = f32_bytes(&[1.0, 2.0]);
+        let coo = TensorMeta::new_sparse(DType::F32, vec![2, 3], SparseFormat::Coo, 2).unwrap();
+        assert_eq!(
+            validate(&coo, &coo_payload(&[0, 2, 0, 0], &values)),
+            Err(TensorError::IndexOutOfBounds { dim: 0, index: 2, extent: 2 })
+        );
+        assert_eq!(validate(&coo, &coo_payload(&[1, 1, 2, 2], &values)), Err(TensorError::DuplicateIndex));
+        assert_eq!(validate(&coo, &coo_payload(&[0, -1, 0, 0], &values)), Err(TensorError::NegativeIndex { position: 1 }));
+
+        let csr = TensorMeta::new_sparse(DType::F32, vec![2, 3], SparseFormat::Csr, 2).unwrap();
+        assert!(validate(&csr, &csr_payload(&[0, 2, 2], &[0, 2], &values)).is_ok());
+        assert_eq!(validate(&csr, &csr_payload(&[0, 2, 2], &[2, 0], &values)), Err(TensorError::UnsortedIndices { row: 0 }));
+        assert_eq!(validate(&csr, &csr_payload(&[0, 2, 1], &[0, 2], &values)), Err(TensorError::InvalidOffsets { position: 2 }));
+        assert_eq!(
+            validate(&csr, &csr_payload(&[0, 1, 2], &[0, 3], &values)),
+            Err(TensorError::IndexOutOfBounds { dim: 1, index: 3, extent: 3 })
+        );
+    }
+}
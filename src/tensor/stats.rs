@@ -0,0 +1,157 @@
+//! Summary statistics of a tensor's values, used to describe cached tensors and to detect NaN and infinities.
+//! Sparse tensors are described by their dense elements, the implicit zeros are counted without densifying.
+//! Ragged tensors are described by the flat values of all rows.
+
+use crate::error::tensor_error::TensorError;
+use crate::tensor::cast;
+use crate::tensor::meta::{TensorFormat, TensorMeta, INDEX_WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct TensorStats {
+    /// number of elements, including NaN and infinities.
+    pub count: u64,
+    pub nan_count: u64,
+    pub inf_count: u64,
+    /// min, max, mean and population standard deviation of the finite elements, None when there are none.
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    pub std: Option<f64>,
+}
+
+impl TensorStats {
+    /// Number of NaN and infinite elements.
+    pub fn non_finite_count(&self) -> u64 {
+        self.nan_count + self.inf_count
+    }
+}
+
+/// Computes the statistics of a raw (decoded) payload.
+pub fn compute(meta: &TensorMeta, data: &[u8]) -> Result<TensorStats, TensorError> {
+    let width = meta.dtype().size_bytes();
+    let mut accumulator = Accumulator::default();
+    let values = match meta.format() {
+        TensorFormat::Dense => data,
+        TensorFormat::Sparse(sparsity) => {
+            accumulator.push_zeros((meta.num_elements()? - sparsity.nnz) as u64);
+            &data[data.len() - sparsity.nnz * width..]
+        }
+        TensorFormat::Ragged { rows } => &data[(rows + 1) * INDEX_WIDTH..],
+    };
+    for element in values.chunks_exact(width) {
+        accumulator.push(cast::to_f64(meta.dtype(), element));
+    }
+    Ok(accumulator.finish())
+}
+
+/// Welford's online mean and variance over the finite values.
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    nan_count: u64,
+    inf_count: u64,
+    finite: u64,
+    min: f64,
+    max: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Accumulator {
+    fn push(&mut self, value: f64) {
+        self.count += 1;
+        if value.is_nan() {
+            self.nan_count += 1;
+            return;
+        }
+        if value.is_infinite() {
+            self.inf_count += 1;
+            return;
+        }
+        if self.finite == 0 {
+            (self.min, self.max) = (value, value);
+        } else {
+            (self.min, self.max) = (self.min.min(value), self.max.max(value));
+        }
+        self.finite += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.finite as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Adds `n` zeros at once, merging them as a group of mean 0 and variance 0.
+    fn push_zeros(&mut self, n: u64) {
+        if n == 0 {
+            return;
+        }
+        if self.finite == 0 {
+            (self.min, self.max) = (0.0, 0.0);
+        } else {
+            (self.min, self.max) = (self.min.min(0.0), self.max.max(0.0));
+        }
+        let total = self.finite + n;
+        let delta = -self.mean;
+        self.mean += delta * n as f64 / total as f64;
+        self.m2 += delta * delta * self.finite as f64 * n as f64 / total as f64;
+        self.count += n;
+        self.finite = total;
+    }
+
+    fn finish(self) -> TensorStats {
+        let finite = |value: f64| (self.finite > 0).then_some(value);
+        TensorStats {
+            count: self.count,
+            nan_count: self.nan_count,
+            inf_count: self.inf_count,
+            min: finite(self.min),
+            max: finite(self.max),
+            mean: finite(self.mean),
+            std: finite((self.m2 / self.finite.max(1) as f64).sqrt()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor::meta::{DType, SparseFormat, StorageLayout};
+    use crate::tensor::sparse;
+
+    fn f32_bytes(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn dense_stats_skip_non_finite_values() {
+        let values = [1.0f32, 2.0, f32::NAN, 3.0, f32::INFINITY, 4.0, f32::NEG_INFINITY];
+        let meta = TensorMeta::new(DType::F32, vec![7], StorageLayout::RowMajor).unwrap();
+        let stats = compute(&meta, &f32_bytes(&values)).unwrap();
+
+        assert_eq!((stats.count, stats.nan_count, stats.inf_count), (7, 1, 2));
+        assert_eq!((stats.min, stats.max, stats.mean), (Some(1.0), Some(4.0), Some(2.5)));
+        assert!((stats.std.unwrap() - 1.25f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn sparse_stats_count_implicit_zeros() {
+        let meta = TensorMeta::new_sparse(DType::F32, vec![2, 2], SparseFormat::Coo, 2).unwrap();
+        let payload = sparse::coo_payload(&[0, 1, 0, 1], &f32_bytes(&[2.0, 6.0]));
+        let stats = compute(&meta, &payload).unwrap();
+
+        //the dense tensor is [2, 0, 0, 6].
+        assert_eq!(stats.count, 4);
+        assert_eq!((stats.min, stats.max, stats.mean), (Some(0.0), Some(6.0), Some(2.0)));
+        assert!((stats.std.unwrap() - 6.0f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn empty_and_integer_tensors() {
+        let empty = TensorMeta::new(DType::F32, vec![0], StorageLayout::RowMajor).unwrap();
+        let stats = compute(&empty, &[]).unwrap();
+        assert_eq!(stats, TensorStats::default());
+
+        let ints = TensorMeta::new(DType::U8, vec![3], StorageLayout::RowMajor).unwrap();
+        let stats = compute(&ints, &[1, 2, 255]).unwrap();
+        assert_eq!((stats.min, stats.max, stats.non_finite_count()), (Some(1.0), Some(255.0), 0));
+    }
+}
@@ -1,43 +1,253 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
 use bytes::Bytes;
-use crate::tensor::meta::TensorMeta;
+use crate::error::cache_error::CacheError;
+use crate::error::tensor_error::TensorError;
+use crate::tensor::cast;
+use crate::tensor::checksum;
+use crate::tensor::codec::{self, Encoding};
+use crate::tensor::meta::{DType, TensorFormat, TensorMeta};
+use crate::tensor::quantize::Quantization;
+use crate::tensor::ragged;
+use crate::tensor::sparse;
+use crate::tensor::stats::{self, TensorStats};
 
 //define the full tensor object to be stored
 pub struct Tensor {
     metadata: TensorMeta,
     //modified to Bytes for zero copy.
-    data: Bytes
+    data: Bytes,
+    //checksum of the raw (decoded) data, computed once on creation.
+    checksum: u64,
+    //how data is encoded, raw unless the tensor was compressed for storage.
+    encoding: Encoding,
+    //free form key/value tags set by the writer, such as the model version that produced the tensor.
+    attributes: HashMap<String, String>,
+    //statistics of the values, computed on the first describe and kept for the life of the entry.
+    stats: OnceLock<TensorStats>,
+    //version assigned by the cache when the tensor was stored, 0 until then.
+    version: u64,
 }
 
 impl Tensor {
-    pub fn new(metadata: TensorMeta,data: Bytes) -> Result<Self, &'static str> {
+    pub fn new(metadata: TensorMeta,data: Bytes) -> Result<Self, TensorError> {
         let data_len = data.len();
         let expected = metadata.total_byte_size()?;
         if data_len != expected {
-            return Err("Data length does not match expected length (calculated from tensor metadata)");
+            return Err(TensorError::LengthMismatch { expected, actual: data_len });
         }
-        Ok(Self { metadata, data })
+        match metadata.format() {
+            TensorFormat::Dense => {}
+            TensorFormat::Sparse(_) => sparse::validate(&metadata, &data)?,
+            TensorFormat::Ragged { .. } => ragged::validate(&metadata, &data)?,
+        }
+        let checksum = checksum::compute(&data);
+        Ok(Self { metadata, data, checksum, encoding: Encoding::default(), attributes: HashMap::new(), stats: OnceLock::new(), version: 0 })
+    }
+
+    /// Builds a tensor with an arbitrary stored checksum, used to simulate corrupted payloads.
+    #[cfg(test)]
+    pub(crate) fn with_checksum(metadata: TensorMeta, data: Bytes, checksum: u64) -> Self {
+        Self { metadata, data, checksum, encoding: Encoding::default(), attributes: HashMap::new(), stats: OnceLock::new(), version: 0 }
+    }
+
+    /// Returns a copy of this tensor with its data encoded. The metadata still describes the raw data.
+    /// The checksum describes the bytes a decode gives back, which for lossy encodings is the
+    /// reconstruction rather than the original. If the encoded form is not smaller the tensor is kept raw.
+    pub fn encode(&self, encoding: Encoding) -> Result<Self, CacheError> {
+        let raw = self.decode()?;
+        let encoded = codec::encode(&raw.data, encoding, &self.metadata)?;
+        if encoded.len() >= raw.data.len() {
+            return Ok(raw);
+        }
+        let (checksum, stats) = if encoding.is_lossy() {
+            (checksum::compute(&codec::decode(&encoded, encoding, &self.metadata)?), OnceLock::new())
+        } else {
+            (self.checksum, self.stats.clone())
+        };
+        Ok(Self { metadata: self.metadata.clone(), data: encoded, checksum, encoding, attributes: self.attributes.clone(), stats, version: self.version })
+    }
+
+    /// Returns this tensor with its data decoded back into raw bytes. Raw tensors are cloned without
+    /// copying their data.
+    pub fn decode(&self) -> Result<Self, CacheError> {
+        let data = codec::decode(&self.data, self.encoding, &self.metadata)?;
+        Ok(Self {
+            metadata: self.metadata.clone(),
+            data,
+            checksum: self.checksum,
+            encoding: Encoding::default(),
+            attributes: self.attributes.clone(),
+            stats: self.stats.clone(),
+            version: self.version,
+        })
+    }
+
+    /// Returns a tensor of the same dtype and size with a different shape. The payload is shared, not copied.
+    /// Sparse and ragged tensors, whose shape describes their indices, and int8 quantized tensors, whose
+    /// scales are per channel, cannot be reshaped.
+    pub fn view(&self, shape: Vec<usize>) -> Result<Self, CacheError> {
+        if !self.metadata.is_dense() {
+            return Err(TensorError::UnsupportedFormat("cannot reshape sparse or ragged tensors".to_string()).into());
+        }
+        if self.encoding.quantization == Quantization::Int8 {
+            return Err(CacheError::UnsupportedEncoding("cannot reshape int8 quantized tensors".to_string()));
+        }
+        let metadata = TensorMeta::new(self.metadata.dtype().clone(), shape, self.metadata.layout().clone())?;
+        let (expected, actual) = (metadata.total_byte_size()?, self.metadata.total_byte_size()?);
+        if expected != actual {
+            return Err(TensorError::LengthMismatch { expected, actual }.into());
+        }
+        Ok(Self {
+            metadata,
+            data: self.data.clone(),
+            checksum: self.checksum,
+            encoding: self.encoding,
+            attributes: self.attributes.clone(),
+            //a reshape keeps every value, so the statistics still hold.
+            stats: self.stats.clone(),
+            version: self.version,
+        })
     }
-    
+
+    /// Returns this tensor with the given attributes attached, replacing any existing ones.
+    pub fn with_attributes(mut self, attributes: HashMap<String, String>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// Returns this tensor with one attribute added, replacing an existing value for the same key.
+    pub fn with_attribute(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.attributes.insert(key.into(), value.into());
+        self
+    }
+
+    /// Returns this tensor holding `data`, a payload with the same bytes that is already cached, so both
+    /// entries share one allocation.
+    pub(crate) fn with_shared_payload(mut self, data: Bytes) -> Self {
+        debug_assert_eq!(data, self.data);
+        self.data = data;
+        self
+    }
+
+    /// Returns this tensor holding `data` as its payload unchanged otherwise, to simulate corruption in tests.
+    #[cfg(test)]
+    pub(crate) fn with_corrupted_payload(mut self, data: Bytes) -> Self {
+        self.data = data;
+        self
+    }
+
+    /// Returns this tensor stamped with the version the cache stored it under.
+    pub(crate) fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Returns the version the cache assigned when this tensor was stored, see `PutMode`. Every write to
+    /// a key gets a higher version than the last. Tensors that were never stored have version 0.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the statistics of the decoded values. They are computed on the first call and cached with
+    /// the tensor, so later calls are free.
+    pub fn stats(&self) -> Result<TensorStats, CacheError> {
+        if let Some(stats) = self.stats.get() {
+            return Ok(*stats);
+        }
+        let raw = self.decode()?;
+        let computed = stats::compute(&self.metadata, &raw.data)?;
+        Ok(*self.stats.get_or_init(|| computed))
+    }
+
+    /// Returns this tensor as a raw dense row-major tensor. Dense tensors are only decoded, sparse ones fail
+    /// with `TensorError::TooLarge` if their dense form exceeds `max_bytes`.
+    pub fn densify(&self, max_bytes: usize) -> Result<Self, CacheError> {
+        let raw = self.decode()?;
+        if self.metadata.sparsity().is_none() {
+            return Ok(raw);
+        }
+        let (metadata, data) = sparse::densify(&raw.metadata, &raw.data, max_bytes)?;
+        let dense = Tensor::new(metadata, data)?;
+        Ok(dense.with_attributes(raw.attributes).with_version(raw.version))
+    }
+
+    /// Returns one row of a ragged tensor as a raw dense tensor. The values of a raw tensor are not copied.
+    pub fn ragged_row(&self, row: usize) -> Result<Self, CacheError> {
+        let rows = self.metadata.ragged_rows().ok_or(CacheError::NotRagged)?;
+        if row >= rows {
+            return Err(CacheError::RowOutOfRange { row, rows });
+        }
+        let raw = self.decode()?;
+        let (metadata, data) = ragged::row(&raw.metadata, &raw.data, row)?;
+        Ok(Tensor::new(metadata, data)?.with_version(raw.version))
+    }
+
+    /// Returns this tensor as a raw dense tensor of `dtype`, see the `cast` module for the rounding rules.
+    pub fn cast(&self, dtype: &DType) -> Result<Self, CacheError> {
+        let raw = self.decode()?;
+        let (metadata, data) = cast::cast(&raw.metadata, &raw.data, dtype)?;
+        Ok(Tensor::new(metadata, data)?.with_attributes(raw.attributes).with_version(raw.version))
+    }
+
     /***
-    Returns size in bytes of the tensor object
+    Returns size in bytes of the stored data, which is smaller than the tensor size when compressed
     */
     pub fn byte_size(&self) -> usize {
-        self.metadata.total_byte_size().unwrap()
+        self.data.len()
+    }
+
+    /// Returns size in bytes of the tensor once decoded, as described by its metadata.
+    pub fn logical_byte_size(&self) -> usize {
+        self.metadata.total_byte_size().unwrap_or(self.data.len())
+    }
+
+    /// Returns how the data returned by `get_data` is encoded.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
     }
 
     pub fn get_metadata(&self) -> &TensorMeta {
         &self.metadata
     }
 
+    /// Returns the stored data, which is encoded unless `encoding().is_raw()`.
     pub fn get_data(&self) -> &Bytes {
         &self.data
     }
+
+    pub fn get_attributes(&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+
+    /// Returns true if the attribute `key` is set to `value`.
+    pub fn has_tag(&self, key: &str, value: &str) -> bool {
+        self.attributes.get(key).is_some_and(|v| v == value)
+    }
+
+    /// Returns the checksum computed over the data when the tensor was created.
+    pub fn checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Recomputes the checksum of the data and compares it against the stored one. Encoded data is
+    /// decoded first, a payload that fails to decode is reported as corrupted.
+    pub fn verify_checksum(&self) -> bool {
+        if self.encoding.is_raw() {
+            return checksum::compute(&self.data) == self.checksum;
+        }
+        self.decode()
+            .map(|raw| checksum::compute(&raw.data) == self.checksum)
+            .unwrap_or(false)
+    }
 }
 
 #[cfg(test)]
 mod tests{
     use super::*;
+    use crate::tensor::codec::{Codec, Shuffle};
     use crate::tensor::meta::{DType, StorageLayout};
+    use crate::tensor::quantize::Quantization;
     #[test]
     fn test_tensor_new_valid() {
         let meta = TensorMeta::new(
@@ -65,4 +275,131 @@ mod tests{
         let tensor = Tensor::new(meta, data_bytes);
         assert!(tensor.is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_tensor_checksum_matches_data() {
+        let meta = TensorMeta::new(
+            DType::U8,
+            vec![8],
+            StorageLayout::RowMajor
+        ).unwrap();
+        let data = Bytes::from(vec![3u8; 8]);
+        let tensor = Tensor::new(meta, data.clone()).unwrap();
+        assert_eq!(tensor.checksum(), checksum::compute(&data));
+        assert!(tensor.verify_checksum());
+    }
+
+    #[test]
+    fn test_tensor_encode_decode() {
+        let meta = TensorMeta::new(
+            DType::F32,
+            vec![64, 4],
+            StorageLayout::RowMajor
+        ).unwrap();
+        let tensor = Tensor::new(meta, Bytes::from(vec![0u8; 1024])).unwrap();
+
+        let encoded = tensor.encode(Encoding::new(Codec::Lz4, Shuffle::Byte)).unwrap();
+        assert!(encoded.byte_size() < tensor.byte_size());
+        assert_eq!(encoded.checksum(), tensor.checksum());
+        assert!(encoded.verify_checksum());
+
+        let decoded = encoded.decode().unwrap();
+        assert!(decoded.encoding().is_raw());
+        assert_eq!(decoded.get_data(), tensor.get_data());
+    }
+
+    #[test]
+    fn test_incompressible_tensor_stays_raw() {
+        let meta = TensorMeta::new(
+            DType::U8,
+            vec![4],
+            StorageLayout::RowMajor
+        ).unwrap();
+        let tensor = Tensor::new(meta, Bytes::from(vec![1u8, 2, 3, 4])).unwrap();
+        let encoded = tensor.encode(Encoding::new(Codec::Zstd, Shuffle::None)).unwrap();
+        assert!(encoded.encoding().is_raw());
+        assert_eq!(encoded.byte_size(), 4);
+    }
+
+    #[test]
+    fn test_quantized_checksum_covers_reconstruction() {
+        let meta = TensorMeta::new(
+            DType::F32,
+            vec![64],
+            StorageLayout::RowMajor
+        ).unwrap();
+        let data: Vec<u8> = (0..64).flat_map(|i| (i as f32 * 0.1).to_le_bytes()).collect();
+        let tensor = Tensor::new(meta, Bytes::from(data)).unwrap();
+
+        let encoded = tensor.encode(Encoding::default().with_quantization(Quantization::F16)).unwrap();
+        assert_eq!(encoded.byte_size(), 128);
+        assert_eq!(encoded.logical_byte_size(), 256);
+        assert!(encoded.verify_checksum());
+        assert_ne!(encoded.checksum(), tensor.checksum());
+    }
+
+    #[test]
+    fn test_attributes_survive_encoding() {
+        let meta = TensorMeta::new(
+            DType::F32,
+            vec![64],
+            StorageLayout::RowMajor
+        ).unwrap();
+        let attributes = HashMap::from([("model_version".to_string(), "v3".to_string())]);
+        let tensor = Tensor::new(meta, Bytes::from(vec![0u8; 256])).unwrap().with_attributes(attributes);
+
+        let encoded = tensor.encode(Encoding::new(Codec::Lz4, Shuffle::None)).unwrap();
+        assert!(encoded.has_tag("model_version", "v3"));
+        assert!(encoded.decode().unwrap().has_tag("model_version", "v3"));
+        assert!(!encoded.has_tag("model_version", "v2"));
+    }
+
+    #[test]
+    fn test_sparse_tensor_is_validated_and_densified() {
+        use crate::tensor::meta::SparseFormat;
+        let meta = TensorMeta::new_sparse(DType::U8, vec![4, 4], SparseFormat::Coo, 1).unwrap();
+        assert!(Tensor::new(meta.clone(), sparse::coo_payload(&[4, 0], &[7])).is_err());
+
+        let tensor = Tensor::new(meta, sparse::coo_payload(&[3, 1], &[7])).unwrap();
+        assert_eq!(tensor.byte_size(), 17);
+        let dense = tensor.densify(usize::MAX).unwrap();
+        assert!(dense.get_metadata().sparsity().is_none());
+        assert_eq!(dense.byte_size(), 16);
+        assert_eq!(dense.get_data()[13], 7);
+        assert!(dense.verify_checksum());
+    }
+
+    #[test]
+    fn test_ragged_rows_are_fetched_individually() {
+        let rows: [&[u8]; 3] = [&[1, 2], &[], &[3, 4, 5]];
+        let (meta, payload) = ragged::pack_rows(DType::U8, &[], &rows).unwrap();
+        let tensor = Tensor::new(meta, payload).unwrap();
+        let encoded = tensor.encode(Encoding::new(Codec::Zstd, Shuffle::None)).unwrap();
+
+        for t in [&tensor, &encoded] {
+            assert_eq!(t.ragged_row(2).unwrap().get_data().as_ref(), &[3, 4, 5]);
+            assert!(t.ragged_row(1).unwrap().get_data().is_empty());
+            assert!(matches!(t.ragged_row(3), Err(CacheError::RowOutOfRange { row: 3, rows: 3 })));
+        }
+        let dense = Tensor::new(TensorMeta::new(DType::U8, vec![2], StorageLayout::RowMajor).unwrap(), Bytes::from(vec![0u8; 2])).unwrap();
+        assert!(matches!(dense.ragged_row(0), Err(CacheError::NotRagged)));
+    }
+
+    #[test]
+    fn test_view_shares_payload_with_new_shape() {
+        let meta = TensorMeta::new(DType::F32, vec![4, 6], StorageLayout::RowMajor).unwrap();
+        let tensor = Tensor::new(meta, Bytes::from(vec![1u8; 96])).unwrap();
+
+        let view = tensor.view(vec![2, 2, 6]).unwrap();
+        assert_eq!(view.get_metadata().shape(), &[2, 2, 6]);
+        assert_eq!(view.get_data().as_ptr(), tensor.get_data().as_ptr());
+        assert_eq!(view.checksum(), tensor.checksum());
+        assert!(matches!(
+            tensor.view(vec![5, 5]),
+            Err(CacheError::InvalidTensor(TensorError::LengthMismatch { expected: 100, actual: 96 }))
+        ));
+
+        let quantized = tensor.encode(Encoding::default().with_quantization(Quantization::Int8)).unwrap();
+        assert!(matches!(quantized.view(vec![24]), Err(CacheError::UnsupportedEncoding(_))));
+    }
+}
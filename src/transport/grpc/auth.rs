@@ -0,0 +1,249 @@
+//! Bearer token authentication and per-prefix authorization. The server reads its tokens from a TOML file:
+//!
+//! ```toml
+//! [[tokens]]
+//! name = "trainer"
+//! token = "3f1c..."
+//! permissions = ["read", "write"]
+//! prefixes = ["features/", "embeddings/"]
+//! ```
+//!
+//! `AuthInterceptor` rejects calls without a known token as `Unauthenticated` and attaches the token's `Grant`
+//! to the request, handlers then check the grant against the keys the call touches. A token without
+//! `prefixes` may touch every key. `admin` covers the calls that are not scoped to keys (stats, delete by tag)
+//! and does not imply the other permissions.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+
+/// Metadata key the token is sent under, as `Bearer <token>`.
+pub const AUTHORIZATION: &str = "authorization";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// get, multi get, head and describe.
+    Read,
+    /// put, streaming put, multi put and create view.
+    Write,
+    /// delete and multi delete.
+    Delete,
+    /// stats and delete by tag, which are not scoped to keys.
+    Admin,
+}
+
+/// What one token may do.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Grant {
+    /// shown in errors instead of the token.
+    pub name: String,
+    pub permissions: Vec<Permission>,
+    /// keys the token may touch start with one of these, every key when empty.
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+}
+
+impl Grant {
+    /// Whether the grant allows `permission` on `key`. Admin calls pass no key.
+    pub fn allows(&self, permission: Permission, key: Option<&str>) -> bool {
+        self.permissions.contains(&permission)
+            && match key {
+                Some(key) => self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| key.starts_with(prefix.as_str())),
+                None => true,
+            }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenFile {
+    tokens: Vec<TokenEntry>,
+}
+
+#[derive(Deserialize)]
+struct TokenEntry {
+    token: String,
+    #[serde(flatten)]
+    grant: Grant,
+}
+
+/// The tokens a server accepts, see `ServerOptions::auth`.
+#[derive(Clone)]
+pub struct TokenAuth {
+    grants: HashMap<String, Arc<Grant>>,
+}
+
+impl TokenAuth {
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file: TokenFile = toml::from_str(content)?;
+        let mut grants = HashMap::with_capacity(file.tokens.len());
+        for entry in file.tokens {
+            if entry.token.is_empty() {
+                return Err(format!("token '{}' is empty", entry.grant.name).into());
+            }
+            let name = entry.grant.name.clone();
+            if grants.insert(entry.token, Arc::new(entry.grant)).is_some() {
+                return Err(format!("token '{}' is listed twice", name).into());
+            }
+        }
+        Ok(Self { grants })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    fn grant(&self, token: &str) -> Option<Arc<Grant>> {
+        self.grants.get(token).cloned()
+    }
+}
+
+//keeps tokens out of logs.
+impl fmt::Debug for TokenAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.grants.values().map(|grant| &grant.name)).finish()
+    }
+}
+
+/// Server interceptor resolving the bearer token of every call, lets everything through when `auth` is None.
+#[derive(Clone)]
+pub struct AuthInterceptor {
+    auth: Option<Arc<TokenAuth>>,
+}
+
+impl AuthInterceptor {
+    pub fn new(auth: Option<TokenAuth>) -> Self {
+        Self { auth: auth.map(Arc::new) }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(auth) = &self.auth else { return Ok(request) };
+        let token = request.metadata()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("Missing bearer token"))?;
+        let grant = auth.grant(token).ok_or_else(|| Status::unauthenticated("Unknown bearer token"))?;
+        request.extensions_mut().insert(grant);
+        Ok(request)
+    }
+}
+
+/// The grant `AuthInterceptor` attached to `request`. None when the server runs without authentication.
+pub(crate) fn grant_of<T>(request: &Request<T>) -> Option<Arc<Grant>> {
+    request.extensions().get::<Arc<Grant>>().cloned()
+}
+
+/// Fails with `PermissionDenied` unless `grant` allows `permission` on `key`, a missing grant allows everything.
+pub(crate) fn authorize(grant: Option<&Grant>, permission: Permission, key: Option<&str>) -> Result<(), Status> {
+    match grant {
+        Some(grant) if !grant.allows(permission, key) => Err(Status::permission_denied(match key {
+            Some(key) => format!("Token '{}' may not {:?} key: {}", grant.name, permission, key),
+            None => format!("Token '{}' lacks {:?} permission", grant.name, permission),
+        })),
+        _ => Ok(()),
+    }
+}
+
+/// Client interceptor sending a bearer token with every call, or nothing when None.
+#[derive(Clone)]
+pub struct BearerToken(Option<MetadataValue<Ascii>>);
+
+impl BearerToken {
+    pub fn new(token: Option<&str>) -> Result<Self, Status> {
+        token
+            .map(|token| format!("Bearer {}", token).parse())
+            .transpose()
+            .map(Self)
+            .map_err(|_| Status::unauthenticated("Bearer token must be printable ASCII"))
+    }
+}
+
+impl Interceptor for BearerToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(value) = &self.0 {
+            request.metadata_mut().insert(AUTHORIZATION, value.clone());
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    const TOKENS: &str = r#"
+[[tokens]]
+name = "trainer"
+token = "train-token"
+permissions = ["read", "write"]
+prefixes = ["features/"]
+
+[[tokens]]
+name = "operator"
+token = "ops-token"
+permissions = ["admin", "delete"]
+"#;
+
+    fn with_token(token: &str) -> Request<()> {
+        let mut request = Request::new(());
+        request.metadata_mut().insert(AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn test_grants_check_permissions_and_prefixes() {
+        let auth = TokenAuth::from_toml(TOKENS).unwrap();
+        let trainer = auth.grant("train-token").unwrap();
+        assert!(trainer.allows(Permission::Read, Some("features/a")));
+        assert!(trainer.allows(Permission::Write, Some("features/a")));
+        assert!(!trainer.allows(Permission::Read, Some("labels/a")));
+        assert!(!trainer.allows(Permission::Delete, Some("features/a")));
+        assert!(!trainer.allows(Permission::Admin, None));
+
+        let operator = auth.grant("ops-token").unwrap();
+        assert!(operator.allows(Permission::Delete, Some("anything")));
+        assert!(operator.allows(Permission::Admin, None));
+        assert!(!operator.allows(Permission::Read, Some("anything")));
+
+        let denied = authorize(Some(&trainer), Permission::Read, Some("labels/a")).unwrap_err();
+        assert_eq!(denied.code(), Code::PermissionDenied);
+        assert!(!denied.message().contains("train-token"));
+        assert!(authorize(None, Permission::Admin, None).is_ok());
+    }
+
+    #[test]
+    fn test_interceptor_resolves_tokens() {
+        let mut interceptor = AuthInterceptor::new(Some(TokenAuth::from_toml(TOKENS).unwrap()));
+        let request = interceptor.call(with_token("train-token")).unwrap();
+        assert_eq!(grant_of(&request).unwrap().name, "trainer");
+
+        assert_eq!(interceptor.call(with_token("guess")).unwrap_err().code(), Code::Unauthenticated);
+        assert_eq!(interceptor.call(Request::new(())).unwrap_err().code(), Code::Unauthenticated);
+
+        let mut open = AuthInterceptor::new(None);
+        assert!(grant_of(&open.call(Request::new(())).unwrap()).is_none());
+
+        let mut bearer = BearerToken::new(Some("ops-token")).unwrap();
+        let request = interceptor.call(bearer.call(Request::new(())).unwrap()).unwrap();
+        assert_eq!(grant_of(&request).unwrap().name, "operator");
+        assert!(BearerToken::new(Some("bad\ntoken")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_token_files_are_rejected() {
+        let duplicate = format!("{}\n[[tokens]]\nname = \"again\"\ntoken = \"ops-token\"\npermissions = []\n", TOKENS);
+        assert!(TokenAuth::from_toml(&duplicate).is_err());
+        assert!(TokenAuth::from_toml("[[tokens]]\nname = \"x\"\ntoken = \"\"\npermissions = [\"read\"]\n").is_err());
+        assert!(TokenAuth::from_toml("[[tokens]]\nname = \"x\"\ntoken = \"t\"\npermissions = [\"root\"]\n").is_err());
+    }
+}
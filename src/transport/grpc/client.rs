@@ -1,126 +1,355 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use tonic::Code;
-use tonic::transport::Channel;
+use tonic::service::interceptor::InterceptedService;
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tonic::transport::{Channel, Endpoint};
+use crate::cache::get_options::GetOptions;
+use crate::cache::put_options::{PutMode, PutOptions};
+use crate::error::cache_error::CacheError;
 use crate::error::client_error::ClientError;
 use crate::proto;
-use crate::proto::{GetRequest,PutRequest,DeleteRequest,StatsRequest};
+use crate::proto::{GetRequest,GetResponseChunk,PutRequest,DeleteRequest,DeleteByTagRequest,CreateViewRequest,DescribeRequest,HeadRequest,MultiDeleteRequest,MultiGetRequest,StatsRequest};
 use crate::proto::red_stone_client::RedStoneClient;
-use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+use crate::proto::red_stone_server::SERVICE_NAME;
+use crate::tensor::codec::{self, Codec, Encoding, Shuffle};
+use crate::tensor::quantize::Quantization;
+use crate::tensor::meta::{DType, SparseFormat, Sparsity, StorageLayout, TensorMeta};
+use crate::tensor::stats::TensorStats;
 use crate::tensor::tensor::Tensor;
+use crate::transport::grpc::auth::BearerToken;
+use crate::transport::grpc::error_details::key_status;
+use crate::transport::grpc::tensor_stream::TensorStream;
+use crate::transport::grpc::tls::ClientTlsConfig;
 use moka::future::Cache;
 
 #[derive(Clone)]
 pub struct RemoteCacheClient {
     // each client-server connection has a separate channel.
-    clients: Arc<Vec<RedStoneClient<Channel>>>,
+    clients: Arc<Vec<RedStoneClient<InterceptedService<Channel, BearerToken>>>>,
     next: Arc<AtomicUsize>,
+    health: HealthClient<Channel>,
     l1_cache: Cache<String,Arc<Tensor>>,
+    put_stream_threshold: usize,
 }
 
 const POOL_SIZE: usize = 10;
+/// payloads larger than this are sent with the streaming put unless `ConnectOptions::put_stream_threshold`
+/// says otherwise, well below tonic's 4 MB message limit.
+pub const DEFAULT_PUT_STREAM_THRESHOLD: usize = 2 * 1024 * 1024;
+/// size of the data chunks of a streaming put, smaller if the put stream threshold is.
+const PUT_CHUNK_SIZE: usize = 1024 * 1024;
 //256 KB l1 cache
 const L1_MAX_BYTES: u64 = 1024 * 1024;
 impl RemoteCacheClient {
     pub async fn connect(addr: String) -> Result<Self, ClientError> {
-        let url = if addr.starts_with("http://") || addr.starts_with("https://") {
-            addr
-        } else {
-            format!("http://{}", addr)
-        };
+        Self::connect_with_options(addr, ConnectOptions::default()).await
+    }
+
+    /// Connects over TLS, verifying the server against `tls.ca` and presenting `tls.identity` to servers
+    /// that require mutual TLS. A bare `host:port` address is reached over `https://`.
+    pub async fn connect_with_tls(addr: String, tls: &ClientTlsConfig) -> Result<Self, ClientError> {
+        Self::connect_with_options(addr, ConnectOptions { tls: Some(tls.clone()), ..Default::default() }).await
+    }
 
+    /// Connects with TLS and a bearer token as set in `options`.
+    pub async fn connect_with_options(addr: String, options: ConnectOptions) -> Result<Self, ClientError> {
+        let endpoint = match &options.tls {
+            Some(tls) => {
+                let url = if addr.starts_with("https://") {
+                    addr
+                } else {
+                    format!("https://{}", addr.trim_start_matches("http://"))
+                };
+                Endpoint::from_shared(url)?.tls_config(tls.to_tonic())?
+            }
+            None => {
+                let url = if addr.starts_with("http://") || addr.starts_with("https://") {
+                    addr
+                } else {
+                    format!("http://{}", addr)
+                };
+                Endpoint::from_shared(url)?
+            }
+        };
+        let token = BearerToken::new(options.token.as_deref()).map_err(ClientError::from)?;
         let mut clients = Vec::with_capacity(POOL_SIZE);
+        let mut health = None;
 
         for _ in 0..POOL_SIZE {
-            let client = RedStoneClient::connect(url.clone()).await?;
-            clients.push(client);
+            let channel = endpoint.connect().await?;
+            health.get_or_insert_with(|| HealthClient::new(channel.clone()));
+            clients.push(RedStoneClient::with_interceptor(channel, token.clone()));
         }
 
         Ok(Self {
             clients: Arc::new(clients),
             next: Arc::new(AtomicUsize::new(0)),
+            health: health.expect("the pool is never empty"),
             l1_cache: Cache::builder()
                 .weigher(|_k: &String, v: &Arc<Tensor>| -> u32 {
                     v.byte_size().min(u32::MAX as usize) as u32
                 })
                 .max_capacity(L1_MAX_BYTES)
-                .build()
+                .build(),
+            put_stream_threshold: options.put_stream_threshold,
         })
     }
 
     pub async fn get(&self, key: String) -> Result<Option<Arc<Tensor>>, ClientError> {
+        self.get_with_options(key, GetOptions::default()).await
+    }
 
-        //first check if key exists in client cache, if not, send the request to server.
-        if let Some(tensor) = self.l1_cache.get(&key).await {
-            return Ok(Some(tensor.clone()));
-        }
-        let request = tonic::Request::new(GetRequest { key });
+    pub async fn get_with_options(&self, key: String, options: GetOptions) -> Result<Option<Arc<Tensor>>, ClientError> {
+        //an l1 copy is only served once the server confirms it is still current, which costs no payload.
+        let cached = self.l1_get(&key, &options).await;
+        let request = tonic::Request::new(GetRequest {
+            key,
+            decode_on_client: options.decode_on_client,
+            densify: options.densify,
+            row: options.row.map(|row| row as u64),
+            desired_dtype: options.desired_dtype.as_ref().map(dtype_to_proto),
+            cached_version: cached.as_ref().map(|tensor| tensor.version()),
+        });
         let key = request.get_ref().key.clone();
         let mut client = self.client();
         match client.get(request).await {
             Ok(response) => {
                 let mut stream = response.into_inner();
-                let mut meta = None;
-                let mut buffer = bytes::BytesMut::new();
+                let mut pending = PendingTensor::default();
                 while let Some(chunk) = stream
                     .message()
                     .await
-                    .map_err(ClientError::GrpcStatus)? {
-                    if meta.is_none() {
-                        meta = chunk.meta;
-                    }
-                    buffer.extend_from_slice(&chunk.data);
-                    if chunk.done {
+                    .map_err(ClientError::from)? {
+                    if pending.push(chunk) {
                         break;
                     }
                 }
-
-                let proto_meta = meta.ok_or_else(|| ClientError::ServerError("Missing metadata".into()))?;
-
-                let meta = proto_to_meta(&proto_meta)?;
-                let tensor = Tensor::new(meta, buffer.freeze()).map_err(|_| ClientError::ServerError("Invalid tensor data".into()))?;
-                let tensor = Arc::new(tensor);
-                self.l1_cache.insert(key.clone(), tensor.clone()).await;
+                if pending.unchanged
+                    && let Some(tensor) = cached {
+                    return from_l1(tensor, &options).map(Some);
+                }
+                let tensor = Arc::new(pending.finish(&key)?);
+                if is_stored_form(&options) {
+                    self.l1_cache.insert(key.clone(), tensor.clone()).await;
+                } else if cached.is_some() {
+                    self.l1_cache.remove(&key).await;
+                }
                 Ok(Some(tensor))
             }
 
             Err(status) => match status.code() {
-                Code::NotFound => Ok(None),
-                Code::Internal => Err(ClientError::ServerError(
-                    "Internal server error".to_string()
-                )),
-                Code::Aborted => Err(ClientError::GrpcStatus(status)),
-                _ => Err(ClientError::ServerError(
-                    "Unknown error".to_string()
-                )),
+                Code::NotFound => {
+                    self.l1_cache.remove(&key).await;
+                    Ok(None)
+                }
+                _ => Err(ClientError::from(status)),
             }
         }
     }
 
-    pub async fn put(&self, key: String, meta: TensorMeta, data: Vec<u8>) -> Result<(), ClientError> {
-        let proto_meta = proto::TensorMeta {
-            dtype: dtype_to_proto(meta.dtype()),
-            shape: meta.shape().iter().map(|&s| s as u64).collect(),
-            layout: layout_to_proto(meta.layout()),
+    /// Streams a tensor from the server chunk by chunk instead of buffering it, see `TensorStream`. The
+    /// metadata is returned before any data is read and the payload is verified as the last chunk arrives.
+    /// Returns None if the key is not cached. Streams always read from the server, not the l1 cache.
+    pub async fn get_stream(&self, key: String) -> Result<Option<TensorStream>, ClientError> {
+        self.get_stream_with_options(key, GetOptions::default()).await
+    }
+
+    /// Streams a tensor with the given options, the server always decodes it so
+    /// `options.decode_on_client` is ignored.
+    pub async fn get_stream_with_options(&self, key: String, options: GetOptions) -> Result<Option<TensorStream>, ClientError> {
+        let request = tonic::Request::new(GetRequest {
+            key: key.clone(),
+            decode_on_client: false,
+            densify: options.densify,
+            row: options.row.map(|row| row as u64),
+            desired_dtype: options.desired_dtype.as_ref().map(dtype_to_proto),
+            cached_version: None,
+        });
+        let mut client = self.client();
+        let mut chunks = match client.get(request).await {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(status.into()),
         };
+        let first = chunks
+            .message()
+            .await?
+            .ok_or_else(|| ClientError::ServerError("Empty get stream".into()))?;
+        let proto_meta = first.meta.as_ref().ok_or_else(|| ClientError::ServerError("Missing metadata".into()))?;
+        let meta = proto_to_meta(proto_meta)?;
+        if !proto_to_encoding(first.encoding.as_ref())?.is_raw() {
+            return Err(ClientError::ServerError("Server streamed an encoded tensor".into()));
+        }
+        Ok(Some(TensorStream::new(key, meta, first, chunks)?))
+    }
 
-        let bytes = Bytes::from(data);
+    /// Fetches one row of a ragged tensor as a dense tensor, only that row is sent over the wire.
+    pub async fn get_row(&self, key: String, row: usize) -> Result<Option<Arc<Tensor>>, ClientError> {
+        self.get_with_options(key, GetOptions { row: Some(row), ..Default::default() }).await
+    }
 
-        let tensor = Arc::new(Tensor::new(meta.clone(), bytes.clone())
-                .map_err(|_| ClientError::ServerError("Invalid tensor data".into()))?
-        );
+    /// Fetches a tensor cast to `dtype` on the server, only the cast bytes are sent over the wire.
+    pub async fn get_as(&self, key: String, dtype: DType) -> Result<Option<Arc<Tensor>>, ClientError> {
+        self.get_with_options(key, GetOptions { desired_dtype: Some(dtype), ..Default::default() }).await
+    }
 
-        let request = tonic::Request::new(PutRequest {
-            key: key.clone(),
-            meta: Some(proto_meta),
-            data: bytes,
+    /// Puts a tensor and returns the version the server stored it under.
+    pub async fn put(&self, key: String, meta: TensorMeta, data: Vec<u8>) -> Result<u64, ClientError> {
+        self.put_with_options(key, meta, data, PutOptions::default()).await
+    }
+
+    /// Puts a tensor with the given options and returns the version the server stored it under. The
+    /// checksum is always computed here, so `options.expected_checksum` is ignored. Payloads above
+    /// `ConnectOptions::put_stream_threshold` are sent in chunks with `PutStream`, which the server inserts only
+    /// once every chunk has arrived.
+    pub async fn put_with_options(&self, key: String, meta: TensorMeta, data: Vec<u8>, options: PutOptions) -> Result<u64, ClientError> {
+        let request = put_request(key.clone(), meta, data, &options)?;
+        //the server may store the tensor with more attributes or as a lossy reconstruction, so the l1 cache is
+        //only filled from what a get returns.
+        self.l1_cache.remove(&key).await;
+
+        let mut client = self.client();
+        let response = if request.data.len() > self.put_stream_threshold {
+            let chunk_size = self.put_stream_threshold.clamp(1, PUT_CHUNK_SIZE);
+            client.put_stream(tokio_stream::iter(put_chunks(request, chunk_size))).await
+        } else {
+            client.put(tonic::Request::new(request)).await
+        };
+        Ok(response?.into_inner().version)
+    }
+
+    /// Fetches many keys in one round trip with `MultiGet`. Keys whose l1 copy is still current are not sent
+    /// again.
+    /// Returns one result per key in the order of `keys`, None for a key that is not cached, and fails as a
+    /// whole only if the request itself fails.
+    pub async fn multi_get(&self, keys: Vec<String>) -> Result<Vec<Result<Option<Arc<Tensor>>, ClientError>>, ClientError> {
+        self.multi_get_with_options(keys, GetOptions::default()).await
+    }
+
+    /// Fetches many keys in one round trip, applying `options` to every key as `get_with_options` does.
+    pub async fn multi_get_with_options(&self, keys: Vec<String>, options: GetOptions) -> Result<Vec<Result<Option<Arc<Tensor>>, ClientError>>, ClientError> {
+        //replaced once the server answers for each key.
+        let mut results: Vec<Result<Option<Arc<Tensor>>, ClientError>> = keys.iter()
+            .map(|key| Err(ClientError::ServerError(format!("No result for key: {}", key))))
+            .collect();
+        let mut cached = Vec::with_capacity(keys.len());
+        for key in &keys {
+            cached.push(self.l1_get(key, &options).await);
+        }
+
+        let request = tonic::Request::new(MultiGetRequest {
+            keys: keys.clone(),
+            decode_on_client: options.decode_on_client,
+            densify: options.densify,
+            row: options.row.map(|row| row as u64),
+            desired_dtype: options.desired_dtype.as_ref().map(dtype_to_proto),
+            cached_versions: cached.iter().map(|tensor| tensor.as_ref().map_or(0, |tensor| tensor.version())).collect(),
         });
+        let mut client = self.client();
+        let mut stream = client.multi_get(request).await?.into_inner();
+        let mut pending: HashMap<u32, PendingTensor> = HashMap::new();
+        while let Some(message) = stream.message().await? {
+            let index = message.index as usize;
+            if index >= keys.len() {
+                return Err(ClientError::ServerError(format!("Unexpected batch index {}", message.index)));
+            }
+            if let Some(status) = message.result.as_ref().and_then(key_status) {
+                results[index] = match status.code() {
+                    Code::NotFound => {
+                        self.l1_cache.remove(&keys[index]).await;
+                        Ok(None)
+                    }
+                    _ => Err(status.into()),
+                };
+                continue;
+            }
+            let Some(chunk) = message.chunk else { continue };
+            if !pending.entry(message.index).or_default().push(chunk) {
+                continue;
+            }
+            let Some(tensor) = pending.remove(&message.index) else { continue };
+            if tensor.unchanged
+                && let Some(cached) = cached[index].take() {
+                results[index] = from_l1(cached, &options).map(Some);
+                continue;
+            }
+            let tensor = tensor.finish(&keys[index]).map(Arc::new);
+            match &tensor {
+                Ok(tensor) if is_stored_form(&options) => {
+                    self.l1_cache.insert(keys[index].clone(), Arc::clone(tensor)).await;
+                }
+                _ if cached[index].is_some() => {
+                    self.l1_cache.remove(&keys[index]).await;
+                }
+                _ => {}
+            }
+            results[index] = tensor.map(Some);
+        }
+        Ok(results)
+    }
+
+    /// Puts many tensors in one round trip with `MultiPut`, tensors above the put stream threshold are put on
+    /// their own with `PutStream`. Returns the stored version of each entry in order, a key that already
+    /// exists fails alone. Fails as a whole only if the request itself fails.
+    pub async fn multi_put(&self, entries: Vec<(String, TensorMeta, Vec<u8>)>) -> Result<Vec<Result<u64, ClientError>>, ClientError> {
+        let mut results: Vec<Result<u64, ClientError>> = Vec::with_capacity(entries.len());
+        let mut batched = Vec::new();
+        let mut requests = Vec::new();
+        for (index, (key, meta, data)) in entries.into_iter().enumerate() {
+            results.push(Ok(0));
+            if data.len() > self.put_stream_threshold {
+                results[index] = self.put(key, meta, data).await;
+                continue;
+            }
+            self.l1_cache.remove(&key).await;
+            match put_request(key, meta, data, &PutOptions::default()) {
+                Ok(request) => {
+                    batched.push(index);
+                    requests.push(request);
+                }
+                Err(e) => results[index] = Err(e),
+            }
+        }
+        if requests.is_empty() {
+            return Ok(results);
+        }
 
         let mut client = self.client();
-        client.put(request).await?;
-        self.l1_cache.insert(key, tensor).await;
-        Ok(())
+        let response = client.multi_put(tokio_stream::iter(requests)).await?.into_inner();
+        if response.results.len() != batched.len() || response.versions.len() != batched.len() {
+            return Err(ClientError::ServerError(format!(
+                "Expected {} put results, got {}", batched.len(), response.results.len()
+            )));
+        }
+        let outcomes = response.results.iter().zip(response.versions);
+        for (index, (result, version)) in batched.into_iter().zip(outcomes) {
+            results[index] = match key_status(result) {
+                Some(status) => Err(status.into()),
+                None => Ok(version),
+            };
+        }
+        Ok(results)
+    }
+
+    /// Deletes many keys in one round trip with `MultiDelete`. Returns, in the order of `keys`, whether
+    /// each key existed.
+    pub async fn multi_delete(&self, keys: Vec<String>) -> Result<Vec<bool>, ClientError> {
+        for key in &keys {
+            self.l1_cache.remove(key).await;
+        }
+        let count = keys.len();
+        let request = tonic::Request::new(MultiDeleteRequest { keys });
+        let mut client = self.client();
+        let deleted = client.multi_delete(request).await?.into_inner().deleted;
+        if deleted.len() != count {
+            return Err(ClientError::ServerError(format!("Expected {} delete results, got {}", count, deleted.len())));
+        }
+        Ok(deleted)
     }
 
     pub async fn delete(&self, key: String) -> Result<(), ClientError> {
@@ -134,6 +363,81 @@ impl RemoteCacheClient {
         Ok(())
     }
 
+    /// Deletes every tensor on the server whose attribute `key` is set to `value`, returning the
+    /// deleted keys.
+    pub async fn delete_by_tag(&self, key: String, value: String) -> Result<Vec<String>, ClientError> {
+        let request = tonic::Request::new(DeleteByTagRequest { key, value });
+        let mut client = self.client();
+        let keys = client.delete_by_tag(request).await?.into_inner().keys;
+        for key in &keys {
+            self.l1_cache.remove(key).await;
+        }
+        Ok(keys)
+    }
+
+    /// Stores `key` on the server as a view of `source` with a different shape, no payload is sent.
+    pub async fn create_view(&self, source: String, key: String, shape: Vec<usize>) -> Result<(), ClientError> {
+        let request = tonic::Request::new(CreateViewRequest {
+            source_key: source,
+            key,
+            shape: shape.iter().map(|&s| s as u64).collect(),
+        });
+        let mut client = self.client();
+        client.create_view(request).await?;
+        Ok(())
+    }
+
+    /// Returns the statistics of a tensor's values, computed by the server and cached with the entry.
+    /// Returns None if the key is not cached.
+    pub async fn describe(&self, key: String) -> Result<Option<TensorStats>, ClientError> {
+        let request = tonic::Request::new(DescribeRequest { key });
+        let mut client = self.client();
+        match client.describe(request).await {
+            Ok(response) => {
+                let response = response.into_inner();
+                Ok(Some(TensorStats {
+                    count: response.count,
+                    nan_count: response.nan_count,
+                    inf_count: response.inf_count,
+                    min: response.min,
+                    max: response.max,
+                    mean: response.mean,
+                    std: response.std,
+                }))
+            }
+            Err(status) if status.code() == Code::NotFound => Ok(None),
+            Err(status) => Err(status.into()),
+        }
+    }
+
+    /// Returns the metadata, sizes and attributes of a tensor without fetching its payload, None if the
+    /// key is not cached. The server only counts it as a use of the entry for eviction if `touch` is set.
+    pub async fn head(&self, key: String, touch: bool) -> Result<Option<TensorHead>, ClientError> {
+        let request = tonic::Request::new(HeadRequest { key, touch });
+        let mut client = self.client();
+        let response = client.head(request).await?.into_inner();
+        if !response.present {
+            return Ok(None);
+        }
+        let version = response.version;
+        let meta = response.meta.as_ref()
+            .ok_or_else(|| ClientError::ServerError("Head response is missing meta".to_string()))?;
+        Ok(Some(TensorHead {
+            meta: proto_to_meta(meta)?,
+            byte_size: response.byte_size,
+            stored_byte_size: response.stored_byte_size,
+            attributes: response.attributes,
+            version,
+        }))
+    }
+
+    /// Asks the standard `grpc.health.v1` service whether the server is serving the cache.
+    pub async fn health(&self) -> Result<bool, ClientError> {
+        let request = HealthCheckRequest { service: SERVICE_NAME.to_string() };
+        let response = self.health.clone().check(request).await?.into_inner();
+        Ok(response.status() == ServingStatus::Serving)
+    }
+
     pub async fn get_stats(&self) -> Result<CacheStats, ClientError> {
         let request = tonic::Request::new(StatsRequest {});
         let mut client = self.client();
@@ -148,16 +452,61 @@ impl RemoteCacheClient {
             evictions: response.evictions,
             hit_rate: response.hit_rate,
             memory_utilization: response.memory_utilization,
+            corruptions: response.corruptions,
+            bytes_saved: response.bytes_saved,
+            logical_bytes: response.logical_bytes,
+            deduplicated_bytes: response.deduplicated_bytes,
         })
     }
 
-    fn client(&self) -> RedStoneClient<Channel> {
+    //another client may have replaced or deleted the key since it was cached, so the version of a hit is sent
+    //along with the get and the copy is only served if the server answers that it is unchanged. Densifying
+    //into more than the l1 cache holds is left to the server, which refuses shapes larger than its memory limit.
+    async fn l1_get(&self, key: &str, options: &GetOptions) -> Option<Arc<Tensor>> {
+        self.l1_cache.get(key).await.filter(|tensor| {
+            !options.densify || tensor.get_metadata().dense_byte_size().is_ok_and(|size| size as u64 <= L1_MAX_BYTES)
+        })
+    }
+
+    fn client(&self) -> RedStoneClient<InterceptedService<Channel, BearerToken>> {
         let idx = self.next.fetch_add(1, Ordering::Relaxed);
         self.clients[idx % self.clients.len()].clone()
     }
 
 }
 
+/// How `RemoteCacheClient::connect_with_options` reaches a server.
+#[derive(Clone)]
+pub struct ConnectOptions {
+    /// connect over TLS, plaintext when None.
+    pub tls: Option<ClientTlsConfig>,
+    /// sent as a bearer token with every call to servers that require one.
+    pub token: Option<String>,
+    /// payloads larger than this are sent with the streaming put, in chunks no larger than it. Must stay below
+    /// the server's `max_decoding_message_size` less the message overhead, or the server refuses unary puts
+    /// just below the threshold. `DEFAULT_PUT_STREAM_THRESHOLD` by default.
+    pub put_stream_threshold: usize,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self { tls: None, token: None, put_stream_threshold: DEFAULT_PUT_STREAM_THRESHOLD }
+    }
+}
+
+/// What a `head` returns about a cached tensor, everything but the payload.
+#[derive(Clone)]
+pub struct TensorHead {
+    pub meta: TensorMeta,
+    /// size of the tensor once decoded.
+    pub byte_size: u64,
+    /// size of the payload as stored, smaller than `byte_size` for compressed or quantized tensors.
+    pub stored_byte_size: u64,
+    pub attributes: HashMap<String, String>,
+    /// version the tensor was stored under, see `PutMode`.
+    pub version: u64,
+}
+
 pub struct CacheStats {
     pub entries: u64,
     pub memory_used: u64,
@@ -167,6 +516,129 @@ pub struct CacheStats {
     pub evictions: u64,
     pub hit_rate: f64,
     pub memory_utilization: f64,
+    pub corruptions: u64,
+    pub bytes_saved: u64,
+    pub logical_bytes: u64,
+    pub deduplicated_bytes: u64,
+}
+
+//applies the options of a get to a tensor found in the l1 cache, as the server would.
+fn from_l1(tensor: Arc<Tensor>, options: &GetOptions) -> Result<Arc<Tensor>, ClientError> {
+    let tensor = if let Some(row) = options.row {
+        let row = tensor.ragged_row(row)
+            .map_err(|e| ClientError::ServerError(format!("Failed to fetch row: {}", e)))?;
+        Arc::new(row)
+    } else if options.densify && tensor.get_metadata().sparsity().is_some() {
+        let dense = tensor.densify(L1_MAX_BYTES as usize)
+            .map_err(|e| ClientError::ServerError(format!("Failed to densify tensor: {}", e)))?;
+        Arc::new(dense)
+    } else {
+        tensor
+    };
+    if let Some(dtype) = &options.desired_dtype
+        && tensor.get_metadata().dtype() != dtype {
+        let cast = tensor.cast(dtype).map_err(|e| match e {
+            CacheError::InvalidTensor(e) => ClientError::InvalidTensor(e),
+            e => ClientError::ServerError(format!("Failed to cast tensor: {}", e)),
+        })?;
+        return Ok(Arc::new(cast));
+    }
+    Ok(tensor)
+}
+
+//a densified, cast or single row tensor is not the stored form, so it is not cached.
+fn is_stored_form(options: &GetOptions) -> bool {
+    !options.densify && options.row.is_none() && options.desired_dtype.is_none()
+}
+
+//validates a tensor locally and builds its put request, the checksum is computed here.
+fn put_request(key: String, meta: TensorMeta, data: Vec<u8>, options: &PutOptions) -> Result<PutRequest, ClientError> {
+    let proto_meta = meta_to_proto(&meta);
+
+    let bytes = Bytes::from(data);
+
+    let tensor = Tensor::new(meta, bytes.clone())?;
+    let (mode, expected_version) = match options.mode {
+        PutMode::IfAbsent => (proto::PutMode::IfAbsent, 0),
+        PutMode::Overwrite => (proto::PutMode::Overwrite, 0),
+        PutMode::IfVersion(version) => (proto::PutMode::IfVersion, version),
+    };
+
+    let request = PutRequest {
+        key,
+        meta: Some(proto_meta),
+        data: bytes,
+        checksum: Some(tensor.checksum()),
+        encoding: encoding_to_proto(options.encoding),
+        attributes: options.attributes.clone(),
+        mode: mode as i32,
+        expected_version,
+    };
+    Ok(request)
+}
+
+//a tensor being reassembled from its response chunks.
+#[derive(Default)]
+struct PendingTensor {
+    meta: Option<proto::TensorMeta>,
+    checksum: u64,
+    encoding: Option<proto::Encoding>,
+    attributes: HashMap<String, String>,
+    version: u64,
+    //the server answered that the client's copy is current, there is no payload.
+    unchanged: bool,
+    buffer: BytesMut,
+}
+
+impl PendingTensor {
+    //adds one chunk and returns true if it was the last.
+    fn push(&mut self, chunk: GetResponseChunk) -> bool {
+        if self.meta.is_none() {
+            self.meta = chunk.meta;
+            self.checksum = chunk.checksum;
+            self.encoding = chunk.encoding;
+            self.attributes = chunk.attributes;
+            self.version = chunk.version;
+            self.unchanged = chunk.unchanged;
+        }
+        self.buffer.extend_from_slice(&chunk.data);
+        chunk.done
+    }
+
+    //decodes the payload and verifies it against the checksum sent with the first chunk.
+    fn finish(self, key: &str) -> Result<Tensor, ClientError> {
+        let proto_meta = self.meta.ok_or_else(|| ClientError::ServerError("Missing metadata".into()))?;
+
+        let meta = proto_to_meta(&proto_meta)?;
+        let encoding = proto_to_encoding(self.encoding.as_ref())?;
+        let data = codec::decode(&self.buffer.freeze(), encoding, &meta)
+            .map_err(|e| ClientError::ServerError(format!("Failed to decode tensor: {}", e)))?;
+        let tensor = Tensor::new(meta, data)?
+            .with_attributes(self.attributes)
+            .with_version(self.version);
+        if tensor.checksum() != self.checksum {
+            return Err(ClientError::ChecksumMismatch {
+                key: key.to_string(),
+                expected: self.checksum,
+                actual: tensor.checksum(),
+            });
+        }
+        Ok(tensor)
+    }
+}
+
+//splits a put into a first message with every field and the first data chunk, followed by data only chunks.
+fn put_chunks(request: PutRequest, chunk_size: usize) -> Vec<PutRequest> {
+    let data = request.data.clone();
+    let mut chunks = Vec::with_capacity(data.len().div_ceil(chunk_size));
+    chunks.push(PutRequest { data: data.slice(..chunk_size.min(data.len())), ..request });
+    let mut offset = chunk_size;
+    while offset < data.len() {
+        let end = (offset + chunk_size).min(data.len());
+        chunks.push(PutRequest { data: data.slice(offset..end), ..Default::default() });
+        offset = end;
+    }
+    chunks
 }
 
 /// utility functions for conversion
@@ -177,6 +649,8 @@ fn dtype_to_proto(dtype: &DType) -> i32 {
         DType::I32 => proto::DType::I32 as i32,
         DType::I64 => proto::DType::I64 as i32,
         DType::U8 => proto::DType::U8 as i32,
+        DType::F16 => proto::DType::F16 as i32,
+        DType::BF16 => proto::DType::Bf16 as i32,
     }
 }
 
@@ -196,6 +670,8 @@ fn proto_to_dtype(dtype: i32) -> Result<DType, ClientError> {
     proto::DType::I32 => Ok(DType::I32),
     proto::DType::I64 => Ok(DType::I64),
     proto::DType::U8 => Ok(DType::U8),
+    proto::DType::F16 => Ok(DType::F16),
+    proto::DType::Bf16 => Ok(DType::BF16),
     _ => Err(ClientError::ServerError("Invalid dtype".into())),
     })
 }
@@ -210,11 +686,87 @@ fn proto_to_layout(layout: i32) -> Result<StorageLayout, ClientError> {
         })
 }
 
+fn encoding_to_proto(encoding: Encoding) -> Option<proto::Encoding> {
+    if encoding.is_raw() {
+        return None;
+    }
+    let codec = match encoding.codec {
+        Codec::None => proto::Codec::None,
+        Codec::Lz4 => proto::Codec::Lz4,
+        Codec::Zstd => proto::Codec::Zstd,
+    };
+    let shuffle = match encoding.shuffle {
+        Shuffle::None => proto::Shuffle::None,
+        Shuffle::Byte => proto::Shuffle::ByteShuffle,
+        Shuffle::Bit => proto::Shuffle::BitShuffle,
+    };
+    let quantization = match encoding.quantization {
+        Quantization::None => proto::Quantization::None,
+        Quantization::F16 => proto::Quantization::F16,
+        Quantization::BF16 => proto::Quantization::Bf16,
+        Quantization::Int8 => proto::Quantization::Int8,
+    };
+    Some(proto::Encoding { codec: codec as i32, shuffle: shuffle as i32, quantization: quantization as i32 })
+}
+
+fn proto_to_encoding(encoding: Option<&proto::Encoding>) -> Result<Encoding, ClientError> {
+    let Some(encoding) = encoding else {
+        return Ok(Encoding::default());
+    };
+    let codec = match proto::Codec::try_from(encoding.codec) {
+        Ok(proto::Codec::None) => Codec::None,
+        Ok(proto::Codec::Lz4) => Codec::Lz4,
+        Ok(proto::Codec::Zstd) => Codec::Zstd,
+        Err(_) => return Err(ClientError::ServerError("Invalid codec".into())),
+    };
+    let shuffle = match proto::Shuffle::try_from(encoding.shuffle) {
+        Ok(proto::Shuffle::None) => Shuffle::None,
+        Ok(proto::Shuffle::ByteShuffle) => Shuffle::Byte,
+        Ok(proto::Shuffle::BitShuffle) => Shuffle::Bit,
+        Err(_) => return Err(ClientError::ServerError("Invalid shuffle".into())),
+    };
+    let quantization = match proto::Quantization::try_from(encoding.quantization) {
+        Ok(proto::Quantization::None) => Quantization::None,
+        Ok(proto::Quantization::F16) => Quantization::F16,
+        Ok(proto::Quantization::Bf16) => Quantization::BF16,
+        Ok(proto::Quantization::Int8) => Quantization::Int8,
+        Err(_) => return Err(ClientError::ServerError("Invalid quantization".into())),
+    };
+    Ok(Encoding::new(codec, shuffle).with_quantization(quantization))
+}
+
+fn meta_to_proto(meta: &TensorMeta) -> proto::TensorMeta {
+    proto::TensorMeta {
+        dtype: dtype_to_proto(meta.dtype()),
+        shape: meta.shape().iter().map(|&s| s as u64).collect(),
+        layout: layout_to_proto(meta.layout()),
+        sparse_format: sparse_format_to_proto(meta.sparsity()),
+        nnz: meta.sparsity().map_or(0, |s| s.nnz as u64),
+        ragged_rows: meta.ragged_rows().map(|rows| rows as u64),
+    }
+}
+
+fn sparse_format_to_proto(sparsity: Option<&Sparsity>) -> i32 {
+    match sparsity.map(|s| s.format) {
+        None => proto::SparseFormat::Dense as i32,
+        Some(SparseFormat::Coo) => proto::SparseFormat::Coo as i32,
+        Some(SparseFormat::Csr) => proto::SparseFormat::Csr as i32,
+    }
+}
+
 fn proto_to_meta(proto_meta: &proto::TensorMeta) -> Result<TensorMeta, ClientError> {
     let dtype = proto_to_dtype(proto_meta.dtype)?;
     let layout = proto_to_layout(proto_meta.layout)?;
     let shape: Vec<usize> = proto_meta.shape.iter().map(|&s| s as usize).collect();
+    let nnz = proto_meta.nnz as usize;
 
-    TensorMeta::new(dtype, shape, layout)
-        .map_err(|_| ClientError::ServerError("Invalid metadata".into()))
+    let sparse_format = proto::SparseFormat::try_from(proto_meta.sparse_format);
+    let meta = match (sparse_format, proto_meta.ragged_rows) {
+        (Ok(proto::SparseFormat::Dense), None) => TensorMeta::new(dtype, shape, layout),
+        (Ok(proto::SparseFormat::Dense), Some(rows)) => TensorMeta::new_ragged(dtype, rows as usize, shape),
+        (Ok(proto::SparseFormat::Coo), None) => TensorMeta::new_sparse(dtype, shape, SparseFormat::Coo, nnz),
+        (Ok(proto::SparseFormat::Csr), None) => TensorMeta::new_sparse(dtype, shape, SparseFormat::Csr, nnz),
+        _ => return Err(ClientError::ServerError("Invalid tensor format".into())),
+    };
+    Ok(meta?)
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+//! Structured errors carried in the details of a grpc status, so that clients get back the same
+//! `TensorError` the server rejected a tensor with instead of only a message, and can tell an existing key,
+//! a full cache or a lost compare-and-swap apart without parsing messages.
+
+use prost::Message;
+use tonic::{Code, Status};
+use crate::error::tensor_error::TensorError;
+use crate::proto::{
+    error_detail, ErrorDetail, KeyExistsDetail, KeyResult, OutOfMemoryDetail, RejectedTensorDetail,
+    TensorErrorDetail, TensorErrorKind, VersionMismatchDetail,
+};
+
+fn with_detail(code: Code, message: String, kind: error_detail::Kind) -> Status {
+    Status::with_details(code, message, ErrorDetail { kind: Some(kind) }.encode_to_vec().into())
+}
+
+/// Builds an `InvalidArgument` status for a rejected tensor, with the error encoded in its details.
+pub fn invalid_tensor(error: &TensorError) -> Status {
+    with_detail(Code::InvalidArgument, error.to_string(), error_detail::Kind::Tensor(tensor_error_to_proto(error)))
+}
+
+/// Builds an `AlreadyExists` status for a write-once put or view of a cached key.
+pub fn key_exists(key: &str) -> Status {
+    let detail = KeyExistsDetail { key: key.to_string() };
+    with_detail(Code::AlreadyExists, format!("Key already exists: {}", key), error_detail::Kind::KeyExists(detail))
+}
+
+/// Builds a `ResourceExhausted` status for a tensor of `requested_bytes` that does not fit the cache.
+pub fn out_of_memory(requested_bytes: u64, memory_limit: u64) -> Status {
+    let message = format!("Cache is full, {} bytes do not fit a cache of {} bytes", requested_bytes, memory_limit);
+    let detail = OutOfMemoryDetail { requested_bytes, memory_limit };
+    with_detail(Code::ResourceExhausted, message, error_detail::Kind::OutOfMemory(detail))
+}
+
+/// Builds a `FailedPrecondition` status for a compare-and-swap put that found `key` at another version.
+pub fn version_mismatch(key: &str, expected: u64, actual: u64) -> Status {
+    let message = format!("Key is at version {}, expected version {} for key: {}", actual, expected, key);
+    let detail = VersionMismatchDetail { key: key.to_string(), expected, actual };
+    with_detail(Code::FailedPrecondition, message, error_detail::Kind::VersionMismatch(detail))
+}
+
+/// Builds an `InvalidArgument` status for a valid tensor the server refuses to store, such as one with NaN
+/// values under `NonFinitePolicy::Reject`.
+pub fn rejected_tensor(key: &str, reason: String) -> Status {
+    let message = format!("{} for key: {}", reason, key);
+    let detail = RejectedTensorDetail { key: key.to_string(), reason };
+    with_detail(Code::InvalidArgument, message, error_detail::Kind::RejectedTensor(detail))
+}
+
+/// Encodes the outcome of one key of a batch, a success is `KeyResult::default()`.
+pub fn key_result(status: &Status) -> KeyResult {
+    KeyResult {
+        code: status.code() as i32,
+        message: status.message().to_string(),
+        details: status.details().to_vec(),
+    }
+}
+
+/// Returns the status of a key of a batch, None if it succeeded.
+pub fn key_status(result: &KeyResult) -> Option<Status> {
+    let code = Code::from(result.code);
+    (code != Code::Ok).then(|| Status::with_details(code, result.message.clone(), result.details.clone().into()))
+}
+
+/// Returns the structured error carried in the details of `status`, if any.
+pub fn error_detail(status: &Status) -> Option<error_detail::Kind> {
+    if status.details().is_empty() {
+        return None;
+    }
+    ErrorDetail::decode(status.details()).ok()?.kind
+}
+
+/// Returns the tensor error carried in the details of `status`, if any.
+pub fn tensor_error(status: &Status) -> Option<TensorError> {
+    match error_detail(status)? {
+        error_detail::Kind::Tensor(detail) => proto_to_tensor_error(&detail),
+        _ => None,
+    }
+}
+
+fn tensor_error_to_proto(error: &TensorError) -> TensorErrorDetail {
+    let detail = |kind: TensorErrorKind| TensorErrorDetail { kind: kind as i32, ..Default::default() };
+    match error {
+        TensorError::Overflow => detail(TensorErrorKind::Overflow),
+        TensorError::LengthMismatch { expected, actual } => TensorErrorDetail {
+            expected: *expected as u64,
+            actual: *actual as u64,
+            ..detail(TensorErrorKind::LengthMismatch)
+        },
+        TensorError::EmptyShape => detail(TensorErrorKind::EmptyShape),
+        TensorError::ZeroDim { index } => TensorErrorDetail { index: *index as u64, ..detail(TensorErrorKind::ZeroDim) },
+        TensorError::RankMismatch { expected, actual } => TensorErrorDetail {
+            expected: *expected as u64,
+            actual: *actual as u64,
+            ..detail(TensorErrorKind::RankMismatch)
+        },
+        TensorError::UnsupportedDType(name) => TensorErrorDetail {
+            detail: name.clone(),
+            ..detail(TensorErrorKind::UnsupportedDtype)
+        },
+        TensorError::UnsupportedLayout(name) => TensorErrorDetail {
+            detail: name.clone(),
+            ..detail(TensorErrorKind::UnsupportedLayout)
+        },
+        TensorError::UnsupportedFormat(name) => TensorErrorDetail {
+            detail: name.clone(),
+            ..detail(TensorErrorKind::UnsupportedFormat)
+        },
+        TensorError::TooManyValues { nnz, elements } => TensorErrorDetail {
+            expected: *elements as u64,
+            actual: *nnz as u64,
+            ..detail(TensorErrorKind::TooManyValues)
+        },
+        TensorError::IndexOutOfBounds { dim, index, extent } => TensorErrorDetail {
+            expected: *extent as u64,
+            index: *index as u64,
+            dim: *dim as u64,
+            ..detail(TensorErrorKind::IndexOutOfBounds)
+        },
+        TensorError::NegativeIndex { position } => TensorErrorDetail {
+            index: *position as u64,
+            ..detail(TensorErrorKind::NegativeIndex)
+        },
+        TensorError::DuplicateIndex => detail(TensorErrorKind::DuplicateIndex),
+        TensorError::UnsortedIndices { row } => TensorErrorDetail {
+            index: *row as u64,
+            ..detail(TensorErrorKind::UnsortedIndices)
+        },
+        TensorError::InvalidOffsets { position } => TensorErrorDetail {
+            index: *position as u64,
+            ..detail(TensorErrorKind::InvalidOffsets)
+        },
+        TensorError::TooLarge { size, limit } => TensorErrorDetail {
+            expected: *limit as u64,
+            actual: *size as u64,
+            ..detail(TensorErrorKind::TooLarge)
+        },
+    }
+}
+
+fn proto_to_tensor_error(detail: &TensorErrorDetail) -> Option<TensorError> {
+    let (expected, actual, index, dim) =
+        (detail.expected as usize, detail.actual as usize, detail.index as usize, detail.dim as usize);
+    Some(match TensorErrorKind::try_from(detail.kind).ok()? {
+        TensorErrorKind::Unspecified => return None,
+        TensorErrorKind::Overflow => TensorError::Overflow,
+        TensorErrorKind::LengthMismatch => TensorError::LengthMismatch { expected, actual },
+        TensorErrorKind::EmptyShape => TensorError::EmptyShape,
+        TensorErrorKind::ZeroDim => TensorError::ZeroDim { index },
+        TensorErrorKind::RankMismatch => TensorError::RankMismatch { expected, actual },
+        TensorErrorKind::UnsupportedDtype => TensorError::UnsupportedDType(detail.detail.clone()),
+        TensorErrorKind::UnsupportedLayout => TensorError::UnsupportedLayout(detail.detail.clone()),
+        TensorErrorKind::UnsupportedFormat => TensorError::UnsupportedFormat(detail.detail.clone()),
+        TensorErrorKind::TooManyValues => TensorError::TooManyValues { nnz: actual, elements: expected },
+        TensorErrorKind::IndexOutOfBounds => TensorError::IndexOutOfBounds { dim, index, extent: expected },
+        TensorErrorKind::NegativeIndex => TensorError::NegativeIndex { position: index },
+        TensorErrorKind::DuplicateIndex => TensorError::DuplicateIndex,
+        TensorErrorKind::UnsortedIndices => TensorError::UnsortedIndices { row: index },
+        TensorErrorKind::InvalidOffsets => TensorError::InvalidOffsets { position: index },
+        TensorErrorKind::TooLarge => TensorError::TooLarge { size: actual, limit: expected },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::client_error::ClientError;
+
+    #[test]
+    fn tensor_errors_round_trip_through_status_details() {
+        let errors = [
+            TensorError::Overflow,
+            TensorError::LengthMismatch { expected: 16, actual: 15 },
+            TensorError::RankMismatch { expected: 2, actual: 3 },
+            TensorError::UnsupportedDType("7".to_string()),
+            TensorError::TooManyValues { nnz: 5, elements: 4 },
+            TensorError::IndexOutOfBounds { dim: 1, index: 3, extent: 3 },
+            TensorError::InvalidOffsets { position: 2 },
+            TensorError::TooLarge { size: 1 << 40, limit: 1 << 30 },
+        ];
+        for error in errors {
+            let status = invalid_tensor(&error);
+            assert_eq!(status.code(), Code::InvalidArgument);
+            assert_eq!(status.message(), error.to_string());
+            assert_eq!(tensor_error(&status), Some(error));
+        }
+    }
+
+    #[test]
+    fn status_without_details_has_no_tensor_error() {
+        assert_eq!(tensor_error(&Status::invalid_argument("Missing tensor metadata")), None);
+        let garbage = Status::with_details(Code::InvalidArgument, "bad", vec![0xff, 0xff].into());
+        assert_eq!(tensor_error(&garbage), None);
+    }
+
+    #[test]
+    fn key_results_carry_code_and_details() {
+        assert!(key_status(&KeyResult::default()).is_none());
+        let error = TensorError::LengthMismatch { expected: 16, actual: 15 };
+        let status = key_status(&key_result(&invalid_tensor(&error))).unwrap();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(tensor_error(&status), Some(error));
+        assert_eq!(key_status(&key_result(&Status::already_exists("a"))).unwrap().code(), Code::AlreadyExists);
+    }
+
+    #[test]
+    fn cache_errors_carry_their_detail() {
+        let exists = key_status(&key_result(&key_exists("a"))).unwrap();
+        assert_eq!(exists.code(), Code::AlreadyExists);
+        assert_eq!(error_detail(&exists), Some(error_detail::Kind::KeyExists(KeyExistsDetail { key: "a".to_string() })));
+        assert_eq!(tensor_error(&exists), None);
+
+        let full = out_of_memory(2048, 1024);
+        assert_eq!(full.code(), Code::ResourceExhausted);
+        assert_eq!(
+            error_detail(&full),
+            Some(error_detail::Kind::OutOfMemory(OutOfMemoryDetail { requested_bytes: 2048, memory_limit: 1024 })),
+        );
+
+        let stale = version_mismatch("a", 3, 4);
+        assert_eq!(stale.code(), Code::FailedPrecondition);
+        assert!(matches!(
+            error_detail(&stale),
+            Some(error_detail::Kind::VersionMismatch(VersionMismatchDetail { expected: 3, actual: 4, .. }))
+        ));
+
+        let rejected = rejected_tensor("a", "Tensor contains 1 NaN and 0 infinite values".to_string());
+        assert_eq!(rejected.code(), Code::InvalidArgument);
+        assert!(matches!(error_detail(&rejected), Some(error_detail::Kind::RejectedTensor(detail)) if detail.key == "a"));
+    }
+
+    #[test]
+    fn statuses_map_to_typed_errors() {
+        let error = ClientError::from(key_exists("a"));
+        assert!(matches!(&error, ClientError::KeyAlreadyExists(key) if key == "a"));
+        assert!(!error.is_retryable());
+        assert!(matches!(
+            ClientError::from(out_of_memory(2048, 1024)),
+            ClientError::OutOfMemory { requested_bytes: 2048, memory_limit: 1024 }
+        ));
+        assert!(matches!(
+            ClientError::from(version_mismatch("a", 3, 4)),
+            ClientError::VersionMismatch { expected: 3, actual: 4, .. }
+        ));
+        assert!(matches!(
+            ClientError::from(rejected_tensor("a", "Invalid tensor size".to_string())),
+            ClientError::TensorRejected { key, reason } if key == "a" && reason == "Invalid tensor size"
+        ));
+        assert!(matches!(
+            ClientError::from(invalid_tensor(&TensorError::EmptyShape)),
+            ClientError::InvalidTensor(TensorError::EmptyShape)
+        ));
+
+        let unavailable = ClientError::from(Status::unavailable("connection reset"));
+        assert!(matches!(unavailable, ClientError::Unavailable(_)));
+        assert!(unavailable.is_retryable());
+        //a bare status without details maps by its code.
+        assert!(matches!(ClientError::from(Status::already_exists("a")), ClientError::KeyAlreadyExists(key) if key == "a"));
+        assert!(matches!(
+            ClientError::from(Status::resource_exhausted("cache is full")),
+            ClientError::OutOfMemory { requested_bytes: 0, memory_limit: 0 }
+        ));
+        assert!(matches!(ClientError::from(Status::internal("a")), ClientError::GrpcStatus(_)));
+    }
+}
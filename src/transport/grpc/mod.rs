@@ -1,2 +1,7 @@
+pub mod auth;
 pub mod client;
-pub mod server;
\ No newline at end of file
+pub mod error_details;
+pub mod server;
+pub mod server_config;
+pub mod tensor_stream;
+pub mod tls;
\ No newline at end of file
@@ -1,26 +1,199 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
 use std::sync::Arc;
-use bytes::{Buf, Bytes};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use bytes::{Bytes, BytesMut};
+use tokio_stream::StreamExt;
 use tokio_stream::wrappers::ReceiverStream;
-use tokio::sync::mpsc;
-use tonic::{transport::Server, Request, Response, Status, Code};
-use crate::proto::{DeleteRequest, DeleteResponse, GetRequest, GetResponseChunk, PutRequest, PutResponse, StatsRequest, StatsResponse};
-use crate::proto::red_stone_server::{RedStone, RedStoneServer};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::server::{Connected, TcpConnectInfo, TcpIncoming};
+use tonic_health::ServingStatus;
+use tonic_health::pb::health_server::HealthServer;
+use tonic_health::server::{HealthReporter, HealthService};
+use crate::proto::{CreateViewRequest, CreateViewResponse, DeleteByTagRequest, DeleteByTagResponse, DeleteRequest, DeleteResponse, DescribeRequest, DescribeResponse, GetRequest, HeadRequest, HeadResponse, GetResponseChunk, KeyResult, MultiDeleteRequest, MultiDeleteResponse, MultiGetRequest, MultiGetResponseChunk, MultiPutResponse, PutRequest, PutResponse, StatsRequest, StatsResponse};
+use crate::proto::red_stone_server::{RedStone, RedStoneServer, SERVICE_NAME};
 use crate::proto;
 
 use crate::TensorCache;
-use crate::tensor::meta::{DType, StorageLayout, TensorMeta};
+use crate::cache::get_options::GetOptions;
+use crate::cache::put_options::{NonFinitePolicy, PutMode, PutOptions};
+use crate::cache::scrubber::spawn_scrubber;
+use crate::tensor::codec::{Codec, Encoding, Shuffle};
+use crate::tensor::quantize::Quantization;
+use crate::tensor::meta::{DType, SparseFormat, Sparsity, StorageLayout, TensorMeta};
 use crate::error::cache_error::CacheError;
+use crate::error::tensor_error::TensorError;
+use crate::tensor::tensor::Tensor;
+use crate::transport::grpc::error_details::{invalid_tensor, key_exists, key_result, out_of_memory, rejected_tensor, version_mismatch};
+use crate::transport::grpc::auth::{authorize, grant_of, AuthInterceptor, Permission, TokenAuth};
+use crate::transport::grpc::tls::ServerTlsConfig;
 
-/// size of chunk that is sent at once for streaming grpcs.
-const CHUNK_SIZE: usize = 256 * 1024;
+/// size of chunk that is sent at once for streaming grpcs, unless `ServerOptions::chunk_size` says otherwise.
+pub const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+/// HTTP/2 flow control window, per stream and per connection.
+pub const DEFAULT_WINDOW_SIZE: u32 = 64 * 1024 * 1024;
+/// largest message the server accepts or sends, tonic's default.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 4 * 1024 * 1024;
+/// how long a shutdown waits for open calls unless `ServerOptions::drain_timeout` says otherwise.
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
 
 pub struct CacheServer {
     cache: Arc<TensorCache>,
+    //applied to every put, see `ServerOptions::non_finite`.
+    non_finite: NonFinitePolicy,
+    chunk_size: usize,
+}
+
+/// Optional server behaviour, `ServerOptions::default()` is what `start_server` runs with. `ServerConfig`
+/// builds these from a TOML file.
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// how often the background scrubber re-verifies every cached tensor, disabled when None.
+    pub scrub_interval: Option<std::time::Duration>,
+    /// what a put does with floating point tensors containing NaN or infinities, allowed by default.
+    pub non_finite: NonFinitePolicy,
+    /// store byte-identical payloads once across keys, see `TensorCache::with_dedup`.
+    pub dedup: bool,
+    /// serve over TLS, and require client certificates when `ServerTlsConfig::client_ca` is set.
+    pub tls: Option<ServerTlsConfig>,
+    /// require a bearer token from this set on every call and restrict it to its grant, see `auth`.
+    pub auth: Option<TokenAuth>,
+    /// how long a shutdown waits for open calls to finish, `DEFAULT_DRAIN_TIMEOUT` when None.
+    pub drain_timeout: Option<Duration>,
+    /// payload bytes per message of a streamed get.
+    pub chunk_size: usize,
+    /// HTTP/2 flow control window of each stream.
+    pub stream_window: u32,
+    /// HTTP/2 flow control window of each connection, shared by its streams.
+    pub connection_window: u32,
+    /// largest request message accepted, larger puts must use the streaming put.
+    pub max_decoding_message_size: usize,
+    /// largest response message sent.
+    pub max_encoding_message_size: usize,
+    /// streams a client may open on one connection, hyper's default when None.
+    pub max_concurrent_streams: Option<u32>,
+    /// requests served at once per connection, unlimited when None.
+    pub concurrency_limit_per_connection: Option<usize>,
+    /// serve gRPC reflection next to the cache and health services.
+    pub reflection: bool,
+    /// report `SERVING` to health checks as soon as the server starts. When false the server reports
+    /// `NOT_SERVING` until `ServerHandle::set_serving(true)`, e.g. to fill the cache before taking traffic.
+    pub serving_on_start: bool,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            scrub_interval: None,
+            non_finite: NonFinitePolicy::default(),
+            dedup: false,
+            tls: None,
+            auth: None,
+            drain_timeout: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            stream_window: DEFAULT_WINDOW_SIZE,
+            connection_window: DEFAULT_WINDOW_SIZE,
+            max_decoding_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            max_encoding_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            max_concurrent_streams: None,
+            concurrency_limit_per_connection: None,
+            reflection: true,
+            serving_on_start: true,
+        }
+    }
 }
 
 impl CacheServer {
     pub fn new(cache: Arc<TensorCache>) -> Self {
-        Self { cache }
+        Self { cache, non_finite: NonFinitePolicy::default(), chunk_size: DEFAULT_CHUNK_SIZE }
+    }
+
+    pub fn with_non_finite_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    //largest tensor a get may build from a sparse one.
+    fn max_dense_bytes(&self) -> usize {
+        usize::try_from(self.cache.get_stats().memory_limit).unwrap_or(usize::MAX)
+    }
+
+    //validates and inserts the tensor of a put, shared by the unary and the streaming put.
+    fn insert(&self, put_request: PutRequest) -> Result<Response<PutResponse>, Status> {
+        let proto_meta = put_request.meta.ok_or_else(|| {
+            Status::invalid_argument("Missing tensor metadata")
+        })?;
+        let meta = proto_to_meta(&proto_meta)?;
+        let put_request_data_bytes = Bytes::from(put_request.data);
+        let requested_bytes = put_request_data_bytes.len() as u64;
+        let options = PutOptions {
+            expected_checksum: put_request.checksum,
+            encoding: proto_to_encoding(put_request.encoding.as_ref())?,
+            attributes: put_request.attributes,
+            non_finite: self.non_finite,
+            mode: proto_to_put_mode(put_request.mode, put_request.expected_version)?,
+        };
+        match self.cache.put_with_options(put_request.key.clone(),meta,put_request_data_bytes,options) {
+            Ok(version) => Ok(Response::new(PutResponse { version })),
+            Err(e) => {
+                match e {
+                    CacheError::KeyAlreadyExists => {
+                        Err(key_exists(&put_request.key))
+                    }
+                    CacheError::InvalidTensor(e) => {
+                        Err(invalid_tensor(&e))
+                    }
+                    CacheError::InvalidSize => {
+                        Err(rejected_tensor(&put_request.key, e.to_string()))
+                    }
+                    CacheError::OutOfMemory => {
+                        Err(out_of_memory(requested_bytes, self.cache.get_stats().memory_limit))
+                    }
+                    CacheError::ChecksumMismatch { .. } => {
+                        Err(Status::data_loss(format!("{} for key: {}", e, put_request.key)))
+                    }
+                    CacheError::Codec(_) => {
+                        Err(Status::internal(e.to_string()))
+                    }
+                    CacheError::UnsupportedEncoding(_) => {
+                        Err(rejected_tensor(&put_request.key, e.to_string()))
+                    }
+                    CacheError::NotRagged | CacheError::RowOutOfRange { .. } => {
+                        Err(Status::invalid_argument(e.to_string()))
+                    }
+                    CacheError::KeyNotFound(_) => {
+                        Err(Status::not_found(e.to_string()))
+                    }
+                    CacheError::VersionMismatch { expected, actual } => {
+                        Err(version_mismatch(&put_request.key, expected, actual))
+                    }
+                    CacheError::NonFinite { .. } => {
+                        Err(rejected_tensor(&put_request.key, e.to_string()))
+                    }
+                }
+            }
+        }
+
+    }
+}
+
+fn proto_to_put_mode(mode: i32, expected_version: u64) -> Result<PutMode, Status> {
+    match proto::PutMode::try_from(mode) {
+        Ok(proto::PutMode::IfAbsent) => Ok(PutMode::IfAbsent),
+        Ok(proto::PutMode::Overwrite) => Ok(PutMode::Overwrite),
+        Ok(proto::PutMode::IfVersion) => Ok(PutMode::IfVersion(expected_version)),
+        Err(_) => Err(Status::invalid_argument("Invalid put mode")),
     }
 }
 
@@ -32,7 +205,9 @@ fn proto_to_dtype(dtype: i32) -> Result<DType, Status> {
         Ok(proto::DType::I32) => Ok(DType::I32),
         Ok(proto::DType::I64) => Ok(DType::I64),
         Ok(proto::DType::U8) => Ok(DType::U8),
-        _ => Err(Status::invalid_argument("Invalid dtype")),
+        Ok(proto::DType::F16) => Ok(DType::F16),
+        Ok(proto::DType::Bf16) => Ok(DType::BF16),
+        _ => Err(invalid_tensor(&TensorError::UnsupportedDType(dtype.to_string()))),
     }
 }
 
@@ -40,7 +215,7 @@ fn proto_to_layout(layout: i32) -> Result<StorageLayout, Status> {
     match proto::StorageLayout::try_from(layout) {
         Ok(proto::StorageLayout::RowMajor) => Ok(StorageLayout::RowMajor),
         Ok(proto::StorageLayout::ColumnMajor) => Ok(StorageLayout::ColumnMajor),
-        _ => Err(Status::invalid_argument("Invalid storage layout")),
+        _ => Err(invalid_tensor(&TensorError::UnsupportedLayout(layout.to_string()))),
     }
 }
 
@@ -48,9 +223,20 @@ fn proto_to_meta(proto_meta: &proto::TensorMeta) -> Result<TensorMeta, Status> {
     let dtype = proto_to_dtype(proto_meta.dtype)?;
     let layout = proto_to_layout(proto_meta.layout)?;
     let shape: Vec<usize> = proto_meta.shape.iter().map(|&s| s as usize).collect();
+    let nnz = proto_meta.nnz as usize;
 
-    TensorMeta::new(dtype, shape, layout)
-        .map_err(|e| Status::invalid_argument(format!("Invalid tensor metadata: {:?}", e)))
+    let sparse_format = proto::SparseFormat::try_from(proto_meta.sparse_format);
+    let meta = match (sparse_format, proto_meta.ragged_rows) {
+        (Ok(proto::SparseFormat::Dense), None) => TensorMeta::new(dtype, shape, layout),
+        (Ok(proto::SparseFormat::Dense), Some(rows)) => TensorMeta::new_ragged(dtype, rows as usize, shape),
+        (Ok(proto::SparseFormat::Coo), None) => TensorMeta::new_sparse(dtype, shape, SparseFormat::Coo, nnz),
+        (Ok(proto::SparseFormat::Csr), None) => TensorMeta::new_sparse(dtype, shape, SparseFormat::Csr, nnz),
+        _ => {
+            let format = format!("sparse format {} with ragged rows {:?}", proto_meta.sparse_format, proto_meta.ragged_rows);
+            return Err(invalid_tensor(&TensorError::UnsupportedFormat(format)));
+        }
+    };
+    meta.map_err(|e| invalid_tensor(&e))
 }
 
 //convert from rust defined data types to proto defined data types.
@@ -61,6 +247,8 @@ fn dtype_to_proto(dtype: &DType) -> i32 {
         DType::I32 => proto::DType::I32 as i32,
         DType::I64 => proto::DType::I64 as i32,
         DType::U8 => proto::DType::U8 as i32,
+        DType::F16 => proto::DType::F16 as i32,
+        DType::BF16 => proto::DType::Bf16 as i32,
     }
 }
 
@@ -76,6 +264,151 @@ fn meta_to_proto(meta: &TensorMeta) -> proto::TensorMeta {
         dtype: dtype_to_proto(meta.dtype()),
         shape: meta.shape().iter().map(|&s| s as u64).collect(),
         layout: layout_to_proto(meta.layout()),
+        sparse_format: sparse_format_to_proto(meta.sparsity()),
+        nnz: meta.sparsity().map_or(0, |s| s.nnz as u64),
+        ragged_rows: meta.ragged_rows().map(|rows| rows as u64),
+    }
+}
+
+fn sparse_format_to_proto(sparsity: Option<&Sparsity>) -> i32 {
+    match sparsity.map(|s| s.format) {
+        None => proto::SparseFormat::Dense as i32,
+        Some(SparseFormat::Coo) => proto::SparseFormat::Coo as i32,
+        Some(SparseFormat::Csr) => proto::SparseFormat::Csr as i32,
+    }
+}
+
+fn proto_to_encoding(encoding: Option<&proto::Encoding>) -> Result<Encoding, Status> {
+    let Some(encoding) = encoding else {
+        return Ok(Encoding::default());
+    };
+    let codec = match proto::Codec::try_from(encoding.codec) {
+        Ok(proto::Codec::None) => Codec::None,
+        Ok(proto::Codec::Lz4) => Codec::Lz4,
+        Ok(proto::Codec::Zstd) => Codec::Zstd,
+        Err(_) => return Err(Status::invalid_argument("Invalid codec")),
+    };
+    let shuffle = match proto::Shuffle::try_from(encoding.shuffle) {
+        Ok(proto::Shuffle::None) => Shuffle::None,
+        Ok(proto::Shuffle::ByteShuffle) => Shuffle::Byte,
+        Ok(proto::Shuffle::BitShuffle) => Shuffle::Bit,
+        Err(_) => return Err(Status::invalid_argument("Invalid shuffle")),
+    };
+    let quantization = match proto::Quantization::try_from(encoding.quantization) {
+        Ok(proto::Quantization::None) => Quantization::None,
+        Ok(proto::Quantization::F16) => Quantization::F16,
+        Ok(proto::Quantization::Bf16) => Quantization::BF16,
+        Ok(proto::Quantization::Int8) => Quantization::Int8,
+        Err(_) => return Err(Status::invalid_argument("Invalid quantization")),
+    };
+    Ok(Encoding::new(codec, shuffle).with_quantization(quantization))
+}
+
+fn encoding_to_proto(encoding: Encoding) -> Option<proto::Encoding> {
+    if encoding.is_raw() {
+        return None;
+    }
+    let codec = match encoding.codec {
+        Codec::None => proto::Codec::None,
+        Codec::Lz4 => proto::Codec::Lz4,
+        Codec::Zstd => proto::Codec::Zstd,
+    };
+    let shuffle = match encoding.shuffle {
+        Shuffle::None => proto::Shuffle::None,
+        Shuffle::Byte => proto::Shuffle::ByteShuffle,
+        Shuffle::Bit => proto::Shuffle::BitShuffle,
+    };
+    let quantization = match encoding.quantization {
+        Quantization::None => proto::Quantization::None,
+        Quantization::F16 => proto::Quantization::F16,
+        Quantization::BF16 => proto::Quantization::Bf16,
+        Quantization::Int8 => proto::Quantization::Int8,
+    };
+    Some(proto::Encoding { codec: codec as i32, shuffle: shuffle as i32, quantization: quantization as i32 })
+}
+
+//splits a tensor into response chunks, at least one so that empty tensors still carry their metadata.
+fn response_chunks(tensor: &Tensor, chunk_size: usize) -> impl Iterator<Item = GetResponseChunk> + Send + 'static {
+    let meta = meta_to_proto(tensor.get_metadata());
+    let checksum = tensor.checksum();
+    let version = tensor.version();
+    let encoding = encoding_to_proto(tensor.encoding());
+    let mut attributes = tensor.get_attributes().clone();
+    let data_bytes = tensor.get_data().clone();
+    let len = data_bytes.len();
+    (0..len.div_ceil(chunk_size).max(1)).map(move |i| {
+        let (offset, end) = (i * chunk_size, ((i + 1) * chunk_size).min(len));
+        GetResponseChunk {
+            meta: if offset == 0 {
+                Some(meta.clone())
+            } else {
+                None
+            },
+            data: data_bytes.slice(offset..end),
+            done: end == len,
+            checksum: if offset == 0 { checksum } else { 0 },
+            encoding: if offset == 0 { encoding } else { None },
+            attributes: std::mem::take(&mut attributes),
+            version: if offset == 0 { version } else { 0 },
+            unchanged: false,
+        }
+    })
+}
+
+//the messages of one get, either the chunks of the tensor or a single unchanged chunk.
+type ResponseChunks = Box<dyn Iterator<Item = GetResponseChunk> + Send>;
+
+//answers a get whose client already holds the current version.
+fn unchanged_chunk(version: u64) -> GetResponseChunk {
+    GetResponseChunk { done: true, version, unchanged: true, ..Default::default() }
+}
+
+//applies the options of a get to a stored tensor. Compressed tensors are decoded here unless the client
+//asked to decode them itself.
+async fn prepare_for_get(stored: Arc<Tensor>, key: &str, options: &GetOptions, max_dense_bytes: usize) -> Result<Arc<Tensor>, Status> {
+    let cast_to = options.desired_dtype.clone().filter(|dtype| dtype != stored.get_metadata().dtype());
+    let tensor = if let Some(row) = options.row {
+        let row = tokio::task::spawn_blocking(move || stored.ragged_row(row))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| match e {
+                CacheError::NotRagged => Status::invalid_argument(format!("{} for key: {}", e, key)),
+                CacheError::RowOutOfRange { .. } => Status::out_of_range(format!("{} for key: {}", e, key)),
+                _ => Status::data_loss(format!("{} for key: {}", e, key)),
+            })?;
+        Arc::new(row)
+    } else if options.densify && stored.get_metadata().sparsity().is_some() {
+        //a few sparse values can describe a dense shape of any size, never build one larger than the cache.
+        let dense = tokio::task::spawn_blocking(move || stored.densify(max_dense_bytes))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| match e {
+                CacheError::InvalidTensor(e) => invalid_tensor(&e),
+                _ => Status::data_loss(format!("{} for key: {}", e, key)),
+            })?;
+        Arc::new(dense)
+    } else if stored.encoding().is_raw() || (options.decode_on_client && cast_to.is_none()) {
+        stored
+    } else {
+        let decoded = tokio::task::spawn_blocking(move || stored.decode())
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?
+            .map_err(|e| Status::data_loss(format!("{} for key: {}", e, key)))?;
+        Arc::new(decoded)
+    };
+    //cast after decoding, so quantized tensors are cast from their reconstruction.
+    match cast_to {
+        Some(dtype) => {
+            let cast = tokio::task::spawn_blocking(move || tensor.cast(&dtype))
+                .await
+                .map_err(|e| Status::internal(e.to_string()))?
+                .map_err(|e| match e {
+                    CacheError::InvalidTensor(e) => invalid_tensor(&e),
+                    _ => Status::data_loss(format!("{} for key: {}", e, key)),
+                })?;
+            Ok(Arc::new(cast))
+        }
+        None => Ok(tensor),
     }
 }
 
@@ -85,32 +418,28 @@ impl RedStone for CacheServer {
     /// Implementation for the GET method for the gRPC server.
     type GetStream = ReceiverStream<Result<GetResponseChunk, Status>>;
     async fn get(&self, request: Request<GetRequest>) -> Result<Response<Self::GetStream>, Status> {
+        let grant = grant_of(&request);
         let get_request = request.into_inner();
-        if let Some(tensor) = self.cache.get(&get_request.key) {
-            let meta = meta_to_proto(tensor.get_metadata());
-            let data_bytes = tensor.get_data().clone();
+        authorize(grant.as_deref(), Permission::Read, Some(&get_request.key))?;
+        let options = GetOptions {
+            decode_on_client: get_request.decode_on_client,
+            densify: get_request.densify,
+            row: get_request.row.map(|row| row as usize),
+            desired_dtype: get_request.desired_dtype.map(proto_to_dtype).transpose()?,
+        };
+        if let Some(stored) = self.cache.get_stored(&get_request.key) {
+            let chunks: ResponseChunks = if get_request.cached_version == Some(stored.version()) {
+                Box::new(std::iter::once(unchanged_chunk(stored.version())))
+            } else {
+                let tensor = prepare_for_get(stored, &get_request.key, &options, self.max_dense_bytes()).await?;
+                Box::new(response_chunks(&tensor, self.chunk_size))
+            };
             let (tx, rx) = mpsc::channel(8);
             tokio::spawn(async move {
-                let mut offset = 0;
-                let len = data_bytes.len();
-
-                while offset < len {
-                    let end = (offset + CHUNK_SIZE).min(len);
-                    let chunk = data_bytes.slice(offset..end);
-
-                    let msg = GetResponseChunk {
-                        meta: if offset == 0 {
-                            Some(meta.clone())
-                        } else {
-                            None
-                        },
-                        data: chunk,
-                        done: end == len,
-                    };
+                for msg in chunks {
                     if tx.send(Ok(msg)).await.is_err() {
                         break;
                     }
-                    offset = end;
                 }
             });
             Ok(Response::new(ReceiverStream::new(rx)))
@@ -120,46 +449,202 @@ impl RedStone for CacheServer {
     }
 
     async fn put(&self, request: Request<PutRequest>) -> Result<Response<PutResponse>, Status> {
+        let grant = grant_of(&request);
         let put_request = request.into_inner();
-        let proto_meta = put_request.meta.ok_or_else(|| {
+        authorize(grant.as_deref(), Permission::Write, Some(&put_request.key))?;
+        self.insert(put_request)
+    }
+
+    /// Assembles a tensor sent as a stream of `PutRequest`s, see `PutStream` in the proto. It is validated
+    /// and inserted only once the stream completes, so a broken stream leaves nothing behind. A raw tensor
+    /// larger than the cache is refused on the header. An encoded one is only stored compressed, so as with
+    /// `Put` the cache decides once it is encoded, and its payload is buffered up to its declared size.
+    async fn put_stream(&self, request: Request<Streaming<PutRequest>>) -> Result<Response<PutResponse>, Status> {
+        let grant = grant_of(&request);
+        let mut stream = request.into_inner();
+        let header = stream.message().await?.ok_or_else(|| {
+            Status::invalid_argument("Empty put stream")
+        })?;
+        //checked on the header, before any payload is buffered.
+        authorize(grant.as_deref(), Permission::Write, Some(&header.key))?;
+        let proto_meta = header.meta.as_ref().ok_or_else(|| {
             Status::invalid_argument("Missing tensor metadata")
         })?;
-        let meta = proto_to_meta(&proto_meta)?;
-        let put_request_data_bytes = Bytes::from(put_request.data);
-        match self.cache.put(put_request.key.clone(),meta,put_request_data_bytes) {
-            Ok(()) => Ok(Response::new(PutResponse{})),
-            Err(e) => {
-                match e {
-                    CacheError::KeyAlreadyExists => {
-                        Err(Status::new(
-                            Code::AlreadyExists,
-                            format!("Key already exists: {}", put_request.key)
-                        ))
-                    }
-                    CacheError::InvalidTensor => {
-                        Err(Status::invalid_argument("Invalid tensor data"))
-                    }
-                    CacheError::InvalidSize => {
-                        Err(Status::invalid_argument("Invalid tensor size"))
-                    }
-                    CacheError::OutOfMemory => {
-                        Err(Status::resource_exhausted("Cache is full"))
+        let expected = proto_to_meta(proto_meta)?.total_byte_size().map_err(|e| invalid_tensor(&e))?;
+        //a raw tensor is stored as sent, one larger than the cache could never be stored.
+        let memory_limit = self.cache.get_stats().memory_limit;
+        if proto_to_encoding(header.encoding.as_ref())?.is_raw() && expected as u64 > memory_limit {
+            return Err(out_of_memory(expected as u64, memory_limit));
+        }
+        //grows with the chunks that actually arrive rather than with the size the header claims.
+        let mut buffer = BytesMut::from(header.data.as_ref());
+        while let Some(chunk) = stream.message().await? {
+            buffer.extend_from_slice(&chunk.data);
+            if buffer.len() > expected {
+                return Err(invalid_tensor(&TensorError::LengthMismatch { expected, actual: buffer.len() }));
+            }
+        }
+        self.insert(PutRequest { data: buffer.freeze(), ..header })
+    }
+
+    type MultiGetStream = ReceiverStream<Result<MultiGetResponseChunk, Status>>;
+    async fn multi_get(&self, request: Request<MultiGetRequest>) -> Result<Response<Self::MultiGetStream>, Status> {
+        let grant = grant_of(&request);
+        let get_request = request.into_inner();
+        let options = GetOptions {
+            decode_on_client: get_request.decode_on_client,
+            densify: get_request.densify,
+            row: get_request.row.map(|row| row as usize),
+            desired_dtype: get_request.desired_dtype.map(proto_to_dtype).transpose()?,
+        };
+        let cache = Arc::clone(&self.cache);
+        let chunk_size = self.chunk_size;
+        let max_dense_bytes = self.max_dense_bytes();
+        let (tx, rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            for (index, key) in get_request.keys.into_iter().enumerate() {
+                let cached_version = get_request.cached_versions.get(index).copied().filter(|&version| version != 0);
+                let index = index as u32;
+                let allowed = authorize(grant.as_deref(), Permission::Read, Some(&key));
+                let chunks = match allowed.map(|()| cache.get_stored(&key)) {
+                    Err(status) => Err(status),
+                    Ok(None) => Err(Status::not_found(format!("Key not found in cache: {}", key))),
+                    Ok(Some(stored)) if cached_version == Some(stored.version()) => {
+                        Ok(Box::new(std::iter::once(unchanged_chunk(stored.version()))) as ResponseChunks)
                     }
-                    CacheError::InvalidTensorMetadata => {
-                        Err(Status::invalid_argument("Invalid tensor metadata"))
+                    Ok(Some(stored)) => prepare_for_get(stored, &key, &options, max_dense_bytes)
+                        .await
+                        .map(|tensor| Box::new(response_chunks(&tensor, chunk_size)) as ResponseChunks),
+                };
+                let messages: Box<dyn Iterator<Item = MultiGetResponseChunk> + Send> = match chunks {
+                    Ok(chunks) => Box::new(chunks.map(move |chunk| MultiGetResponseChunk {
+                        index,
+                        result: None,
+                        chunk: Some(chunk),
+                    })),
+                    Err(status) => Box::new(std::iter::once(MultiGetResponseChunk {
+                        index,
+                        result: Some(key_result(&status)),
+                        chunk: None,
+                    })),
+                };
+                for msg in messages {
+                    if tx.send(Ok(msg)).await.is_err() {
+                        return;
                     }
                 }
             }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn multi_put(&self, request: Request<Streaming<PutRequest>>) -> Result<Response<MultiPutResponse>, Status> {
+        let grant = grant_of(&request);
+        let mut stream = request.into_inner();
+        let mut results = Vec::new();
+        let mut versions = Vec::new();
+        while let Some(put_request) = stream.message().await? {
+            let inserted = authorize(grant.as_deref(), Permission::Write, Some(&put_request.key))
+                .and_then(|()| self.insert(put_request));
+            let (result, version) = match inserted {
+                Ok(response) => (KeyResult::default(), response.into_inner().version),
+                Err(status) => (key_result(&status), 0),
+            };
+            results.push(result);
+            versions.push(version);
         }
+        Ok(Response::new(MultiPutResponse { results, versions }))
+    }
 
+    async fn multi_delete(&self, request: Request<MultiDeleteRequest>) -> Result<Response<MultiDeleteResponse>, Status> {
+        let grant = grant_of(&request);
+        let keys = request.into_inner().keys;
+        //there is no per-key error to report, so one forbidden key fails the whole batch.
+        for key in &keys {
+            authorize(grant.as_deref(), Permission::Delete, Some(key))?;
+        }
+        let deleted = keys.iter().map(|key| self.cache.delete(key).is_some()).collect();
+        Ok(Response::new(MultiDeleteResponse { deleted }))
     }
+
     async fn delete(&self, request: Request<DeleteRequest>) -> Result<Response<DeleteResponse>, Status> {
+        let grant = grant_of(&request);
         let delete_request = request.into_inner();
+        authorize(grant.as_deref(), Permission::Delete, Some(&delete_request.key))?;
         let deleted = self.cache.delete(&delete_request.key).is_some();
          Ok(Response::new(DeleteResponse { deleted }))
         }
 
+    async fn delete_by_tag(&self, request: Request<DeleteByTagRequest>) -> Result<Response<DeleteByTagResponse>, Status> {
+        authorize(grant_of(&request).as_deref(), Permission::Admin, None)?;
+        let delete_request = request.into_inner();
+        if delete_request.key.is_empty() {
+            return Err(Status::invalid_argument("Missing tag key"));
+        }
+        let keys = self.cache.delete_by_tag(&delete_request.key, &delete_request.value);
+        Ok(Response::new(DeleteByTagResponse { keys }))
+    }
+
+    async fn create_view(&self, request: Request<CreateViewRequest>) -> Result<Response<CreateViewResponse>, Status> {
+        let grant = grant_of(&request);
+        let view_request = request.into_inner();
+        authorize(grant.as_deref(), Permission::Read, Some(&view_request.source_key))?;
+        authorize(grant.as_deref(), Permission::Write, Some(&view_request.key))?;
+        let shape = view_request.shape.iter().map(|&s| s as usize).collect();
+        match self.cache.create_view(&view_request.source_key, view_request.key.clone(), shape) {
+            Ok(()) => Ok(Response::new(CreateViewResponse {})),
+            Err(CacheError::KeyAlreadyExists) => Err(key_exists(&view_request.key)),
+            Err(CacheError::InvalidTensor(e)) => Err(invalid_tensor(&e)),
+            Err(e @ CacheError::KeyNotFound(_)) => Err(Status::not_found(e.to_string())),
+            Err(e) => Err(Status::invalid_argument(e.to_string())),
+        }
+    }
+
+    async fn describe(&self, request: Request<DescribeRequest>) -> Result<Response<DescribeResponse>, Status> {
+        let grant = grant_of(&request);
+        let key = request.into_inner().key;
+        authorize(grant.as_deref(), Permission::Read, Some(&key))?;
+        let cache = Arc::clone(&self.cache);
+        let describe_key = key.clone();
+        //the first describe of a tensor scans every value, keep it off the async workers.
+        let described = tokio::task::spawn_blocking(move || cache.describe(&describe_key))
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        match described {
+            Ok(Some(stats)) => Ok(Response::new(DescribeResponse {
+                count: stats.count,
+                nan_count: stats.nan_count,
+                inf_count: stats.inf_count,
+                min: stats.min,
+                max: stats.max,
+                mean: stats.mean,
+                std: stats.std,
+            })),
+            Ok(None) => Err(Status::not_found(format!("Key not found in cache: {}", key))),
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn head(&self, request: Request<HeadRequest>) -> Result<Response<HeadResponse>, Status> {
+        let grant = grant_of(&request);
+        let head_request = request.into_inner();
+        authorize(grant.as_deref(), Permission::Read, Some(&head_request.key))?;
+        let response = match self.cache.head(&head_request.key, head_request.touch) {
+            Some(tensor) => HeadResponse {
+                present: true,
+                meta: Some(meta_to_proto(tensor.get_metadata())),
+                byte_size: tensor.logical_byte_size() as u64,
+                stored_byte_size: tensor.byte_size() as u64,
+                attributes: tensor.get_attributes().clone(),
+                version: tensor.version(),
+            },
+            None => HeadResponse::default(),
+        };
+        Ok(Response::new(response))
+    }
+
     async fn get_stats(&self, request: Request<StatsRequest>) -> Result<Response<StatsResponse>, Status> {
+        authorize(grant_of(&request).as_deref(), Permission::Admin, None)?;
         let stats = self.cache.get_stats();
 
         Ok(Response::new(StatsResponse {
@@ -171,30 +656,270 @@ impl RedStone for CacheServer {
             evictions: stats.evictions,
             hit_rate: stats.hit_rate(),
             memory_utilization: stats.memory_utilization(),
+            corruptions: stats.corruptions,
+            bytes_saved: stats.bytes_saved(),
+            logical_bytes: stats.logical_bytes,
+            deduplicated_bytes: stats.deduplicated_bytes,
         }))
     }
 }
 
-pub async fn start_server(addr: String, cache_size: u64) -> Result<(), Box<dyn std::error::Error>> {
-    let addr = addr.parse()?;
-    let cache = Arc::new(TensorCache::new(cache_size)?);
-    let server = CacheServer::new(cache);
+/// Serves until SIGTERM or SIGINT, then shuts down gracefully, see `ServerHandle::shutdown`.
+pub async fn start_server(addr: String, cache_size: u64) -> Result<ShutdownOutcome, Box<dyn std::error::Error>> {
+    start_server_with_options(addr, cache_size, ServerOptions::default()).await
+}
+
+/// Serves until SIGTERM or SIGINT, then shuts down gracefully, see `ServerHandle::shutdown`.
+pub async fn start_server_with_options(addr: String, cache_size: u64, options: ServerOptions) -> Result<ShutdownOutcome, Box<dyn std::error::Error>> {
+    let signal = shutdown_signal()?;
+    let handle = spawn_server(addr, cache_size, options).await?;
+    handle.run_until(signal).await
+}
+
+/// Binds `addr` and serves in the background, returning once the server accepts connections. Port 0 binds
+/// a free port, `ServerHandle::local_addr` tells which.
+pub async fn spawn_server(addr: String, cache_size: u64, options: ServerOptions) -> Result<ServerHandle, Box<dyn std::error::Error>> {
+    let addr: SocketAddr = addr.parse()?;
+    let cache = if options.dedup {
+        Arc::new(TensorCache::with_dedup(cache_size)?)
+    } else {
+        Arc::new(TensorCache::new(cache_size)?)
+    };
+    //dropped tensors are reported through `CacheStats::corruptions`.
+    let scrubber = options.scrub_interval.map(|interval| spawn_scrubber(Arc::clone(&cache), interval, |_| {}));
+    let server = CacheServer::new(Arc::clone(&cache))
+        .with_non_finite_policy(options.non_finite)
+        .with_chunk_size(options.chunk_size);
+    let server = RedStoneServer::new(server)
+        .max_decoding_message_size(options.max_decoding_message_size)
+        .max_encoding_message_size(options.max_encoding_message_size);
+    let health = HealthReporter::new();
+    set_serving(&health, options.serving_on_start).await;
+    //v1alpha is still what older grpcurl and grpc_cli ask for.
+    let reflection = || tonic_reflection::server::Builder::configure()
+        .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+        .register_encoded_file_descriptor_set(tonic_health::pb::FILE_DESCRIPTOR_SET);
+
+    let mut builder = Server::builder();
+    if let Some(tls) = &options.tls {
+        builder = builder.tls_config(tls.to_tonic())?;
+    }
+    if let Some(limit) = options.concurrency_limit_per_connection {
+        builder = builder.concurrency_limit_per_connection(limit);
+    }
+    let (reflection_v1, reflection_v1alpha) = match options.reflection {
+        true => (Some(reflection().build_v1()?), Some(reflection().build_v1alpha()?)),
+        false => (None, None),
+    };
+    let router = builder
+        .initial_stream_window_size(options.stream_window)
+        .initial_connection_window_size(options.connection_window)
+        .max_concurrent_streams(options.max_concurrent_streams)
+        .add_service(InterceptedService::new(server, AuthInterceptor::new(options.auth)))
+        //health and reflection are left out of authentication, so probes and tools need no token.
+        .add_service(HealthServer::new(HealthService::from_health_reporter(health.clone())))
+        .add_optional_service(reflection_v1)
+        .add_optional_service(reflection_v1alpha);
+
+    let incoming = TcpIncoming::bind(addr)?.with_nodelay(Some(true));
+    let local_addr = incoming.local_addr()?;
+    let (shutdown, stop) = oneshot::channel::<()>();
+    let (close, closed) = watch::channel(false);
+    let incoming = incoming.map(move |stream| stream.map(|stream| ClosableStream::new(stream, closed.clone())));
+    //stops accepting once signalled and returns when every open call has finished. A dropped handle
+    //closes the channel without a signal, the server then keeps running.
+    let server = tokio::spawn(router.serve_with_incoming_shutdown(incoming, async {
+        if stop.await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }));
+
+    Ok(ServerHandle {
+        local_addr,
+        cache,
+        health,
+        shutdown,
+        close,
+        server,
+        scrubber,
+        drain_timeout: options.drain_timeout.unwrap_or(DEFAULT_DRAIN_TIMEOUT),
+        hooks: Vec::new(),
+    })
+}
+
+//an accepted connection that fails its reads and writes once `close` is set, tonic keeps serving connections
+//on their own tasks after its serve future is dropped, so this is how a shutdown closes them.
+struct ClosableStream {
+    stream: TcpStream,
+    closed: Pin<Box<dyn Future<Output = ()> + Send>>,
+    is_closed: bool,
+}
+
+impl ClosableStream {
+    fn new(stream: TcpStream, mut close: watch::Receiver<bool>) -> Self {
+        let closed = Box::pin(async move {
+            //a dropped sender means the handle was dropped, the connection then stays open.
+            if close.wait_for(|close| *close).await.is_err() {
+                std::future::pending::<()>().await;
+            }
+        });
+        ClosableStream { stream, closed, is_closed: false }
+    }
+
+    fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Error> {
+        if !self.is_closed && self.closed.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+        self.is_closed = true;
+        Poll::Ready(std::io::Error::new(std::io::ErrorKind::ConnectionAborted, "server shut down"))
+    }
+}
+
+impl AsyncRead for ClosableStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        if let Poll::Ready(e) = self.poll_closed(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ClosableStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        if let Poll::Ready(e) = self.poll_closed(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        if let Poll::Ready(e) = self.poll_closed(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl Connected for ClosableStream {
+    type ConnectInfo = TcpConnectInfo;
 
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.stream.connect_info()
+    }
+}
 
-    Server::builder()
-        .initial_stream_window_size(64*1024*1024)
-        .initial_connection_window_size(64*1024*1024)
-        .add_service(RedStoneServer::new(server))
-        .serve(addr)
-        .await?;
+/// Resolves on the first SIGTERM or SIGINT (ctrl-c).
+pub fn shutdown_signal() -> std::io::Result<impl Future<Output = ()>> {
+    #[cfg(unix)]
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    Ok(async move {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+        #[cfg(not(unix))]
+        let _ = tokio::signal::ctrl_c().await;
+    })
+}
+
+//sets the status of the cache service and of the server as a whole, the `""` service.
+async fn set_serving(health: &HealthReporter, serving: bool) {
+    let status = if serving { ServingStatus::Serving } else { ServingStatus::NotServing };
+    health.set_service_status(SERVICE_NAME, status).await;
+    health.set_service_status("", status).await;
+}
+
+type ShutdownHook = Box<dyn FnOnce(&TensorCache) + Send>;
+
+/// How a graceful shutdown ended, see `ServerHandle::shutdown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownOutcome {
+    /// every open call finished within the drain timeout.
+    Drained,
+    /// the drain timeout elapsed and the remaining connections were closed.
+    TimedOut(Duration),
+}
+
+/// A server running in the background, see `spawn_server`. Dropping the handle leaves the server running.
+pub struct ServerHandle {
+    local_addr: SocketAddr,
+    cache: Arc<TensorCache>,
+    health: HealthReporter,
+    shutdown: oneshot::Sender<()>,
+    close: watch::Sender<bool>,
+    server: JoinHandle<Result<(), tonic::transport::Error>>,
+    scrubber: Option<JoinHandle<()>>,
+    drain_timeout: Duration,
+    hooks: Vec<ShutdownHook>,
+}
 
-    Ok(())
+impl ServerHandle {
+    /// Address the server is bound to, with the actual port when it was started on port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Reports the server as serving or not serving to health checks, without affecting the calls it accepts.
+    /// Load balancers and orchestrators stop sending traffic to a server that is not serving.
+    pub async fn set_serving(&self, serving: bool) {
+        set_serving(&self.health, serving).await;
+    }
+
+    /// Runs `hook` on the cache once the server has drained, in the order hooks were added.
+    pub fn on_shutdown(&mut self, hook: impl FnOnce(&TensorCache) + Send + 'static) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Serves until `signal` resolves and then shuts down, or returns the error the server failed with.
+    pub async fn run_until(mut self, signal: impl Future<Output = ()>) -> Result<ShutdownOutcome, Box<dyn std::error::Error>> {
+        tokio::select! {
+            _ = signal => {}
+            result = &mut self.server => {
+                result??;
+                return Err("Server stopped unexpectedly".into());
+            }
+        }
+        self.shutdown().await
+    }
+
+    /// Reports not serving to health checks, stops accepting connections and waits up to the drain timeout
+    /// for open calls, including `Get` streams, to finish before closing the rest. The shutdown hooks run
+    /// afterwards. Returns whether the open calls drained in time.
+    pub async fn shutdown(mut self) -> Result<ShutdownOutcome, Box<dyn std::error::Error>> {
+        self.set_serving(false).await;
+        let _ = self.shutdown.send(());
+        let outcome = match tokio::time::timeout(self.drain_timeout, &mut self.server).await {
+            Ok(result) => {
+                result??;
+                ShutdownOutcome::Drained
+            }
+            Err(_) => {
+                let _ = self.close.send(true);
+                self.server.abort();
+                ShutdownOutcome::TimedOut(self.drain_timeout)
+            }
+        };
+        if let Some(scrubber) = self.scrubber {
+            scrubber.abort();
+        }
+        for hook in self.hooks {
+            hook(&self.cache);
+        }
+        Ok(outcome)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
     use tokio_stream::StreamExt;
+    use tonic::Code;
 
     #[test]
     fn test_dtype_conversion() {
@@ -226,6 +951,7 @@ mod tests {
             dtype: proto::DType::F32 as i32,
             shape: vec![2, 2],
             layout: proto::StorageLayout::RowMajor as i32,
+            ..Default::default()
         }
     }
 
@@ -240,12 +966,14 @@ mod tests {
         let put_req = PutRequest {
             key: "tensor1".to_string(),
             meta: Some(valid_proto_meta()),
-            data: Bytes::from(valid_tensor_bytes())
+            data: Bytes::from(valid_tensor_bytes()),
+            ..Default::default()
         };
         assert!(server.put(Request::new(put_req)).await.is_ok());
 
         let get_req = GetRequest {
             key: "tensor1".to_string(),
+            ..Default::default()
         };
 
         let get_response = server.get(Request::new(get_req)).await.unwrap();
@@ -253,11 +981,13 @@ mod tests {
 
         let mut data = Vec::new();
         let mut meta = None;
+        let mut checksum = 0;
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.unwrap();
             if meta.is_none() {
                 meta = chunk.meta;
+                checksum = chunk.checksum;
             }
 
             data.extend_from_slice(&chunk.data);
@@ -269,6 +999,7 @@ mod tests {
 
         assert_eq!(data.len(), 16);
         assert!(meta.is_some());
+        assert_eq!(checksum, crate::tensor::checksum::compute(&data));
     }
 
     #[tokio::test]
@@ -278,7 +1009,8 @@ mod tests {
         let put_req = PutRequest {
             key: "dup".to_string(),
             meta: Some(valid_proto_meta()),
-            data: Bytes::from(valid_tensor_bytes())
+            data: Bytes::from(valid_tensor_bytes()),
+            ..Default::default()
         };
 
         server.put(Request::new(put_req.clone())).await.unwrap();
@@ -295,6 +1027,7 @@ mod tests {
 
         let get_req = GetRequest {
             key: "missing".to_string(),
+            ..Default::default()
         };
 
         let response = server.get(Request::new(get_req)).await;
@@ -311,20 +1044,305 @@ mod tests {
             dtype: proto::DType::DtypeUnspecified as i32,
             shape: vec![2, 2],
             layout: proto::StorageLayout::RowMajor as i32,
+            ..Default::default()
         };
 
         let put_req = PutRequest {
             key: "bad".to_string(),
             meta: Some(bad_meta),
             data: Bytes::from(valid_tensor_bytes()),
+            ..Default::default()
         };
 
         let response = server.put(Request::new(put_req)).await;
 
         assert!(response.is_err());
+        let status = response.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            crate::transport::grpc::error_details::tensor_error(&status),
+            Some(TensorError::UnsupportedDType("0".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn grpc_put_checksum_mismatch_fails() {
+        let server = setup_server();
+
+        let put_req = PutRequest {
+            key: "corrupt".to_string(),
+            meta: Some(valid_proto_meta()),
+            data: Bytes::from(valid_tensor_bytes()),
+            checksum: Some(1),
+            ..Default::default()
+        };
+
+        let response = server.put(Request::new(put_req)).await;
+
+        assert_eq!(response.unwrap_err().code(), Code::DataLoss);
+    }
+
+    async fn collect_stream(server: &CacheServer, request: GetRequest) -> (Option<proto::Encoding>, Vec<u8>) {
+        let mut stream = server.get(Request::new(request)).await.unwrap().into_inner();
+        let mut encoding = None;
+        let mut data = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk.unwrap();
+            if chunk.meta.is_some() {
+                encoding = chunk.encoding;
+            }
+            data.extend_from_slice(&chunk.data);
+        }
+        (encoding, data)
+    }
+
+    #[tokio::test]
+    async fn grpc_compressed_put_decodes_per_request_flag() {
+        let server = setup_server();
+
+        let put_req = PutRequest {
+            key: "compressed".to_string(),
+            meta: Some(proto::TensorMeta {
+                dtype: proto::DType::F32 as i32,
+                shape: vec![64],
+                layout: proto::StorageLayout::RowMajor as i32,
+                ..Default::default()
+            }),
+            data: Bytes::from(vec![0u8; 256]),
+            encoding: Some(proto::Encoding {
+                codec: proto::Codec::Lz4 as i32,
+                shuffle: proto::Shuffle::ByteShuffle as i32,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        server.put(Request::new(put_req)).await.unwrap();
+
+        let request = GetRequest { key: "compressed".to_string(), ..Default::default() };
+        let (encoding, data) = collect_stream(&server, request).await;
+        assert!(encoding.is_none());
+        assert_eq!(data, vec![0u8; 256]);
+
+        let request = GetRequest { key: "compressed".to_string(), decode_on_client: true, ..Default::default() };
+        let (encoding, data) = collect_stream(&server, request).await;
+        assert_eq!(encoding.unwrap().codec, proto::Codec::Lz4 as i32);
+        assert!(data.len() < 256);
+    }
+
+    #[tokio::test]
+    async fn grpc_delete_by_tag_removes_matching_entries() {
+        let server = setup_server();
+
+        for (key, version) in [("a", "v1"), ("b", "v2"), ("c", "v1")] {
+            let put_req = PutRequest {
+                key: key.to_string(),
+                meta: Some(valid_proto_meta()),
+                data: Bytes::from(valid_tensor_bytes()),
+                attributes: HashMap::from([("model_version".to_string(), version.to_string())]),
+                ..Default::default()
+            };
+            server.put(Request::new(put_req)).await.unwrap();
+        }
+
+        let mut stream = server.get(Request::new(GetRequest { key: "b".to_string(), ..Default::default() }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap();
+        assert_eq!(first.attributes.get("model_version").map(String::as_str), Some("v2"));
+
+        let request = DeleteByTagRequest { key: "model_version".to_string(), value: "v1".to_string() };
+        let mut keys = server.delete_by_tag(Request::new(request)).await.unwrap().into_inner().keys;
+        keys.sort();
+        assert_eq!(keys, vec!["a".to_string(), "c".to_string()]);
+        assert_eq!(server.get_stats(Request::new(StatsRequest {})).await.unwrap().into_inner().entries, 1);
+
+        let request = DeleteByTagRequest { key: String::new(), value: "v1".to_string() };
+        let response = server.delete_by_tag(Request::new(request)).await;
         assert_eq!(response.unwrap_err().code(), Code::InvalidArgument);
     }
 
+    #[tokio::test]
+    async fn grpc_create_view_reshapes_without_copy() {
+        let server = setup_server();
+        let put_req = PutRequest {
+            key: "square".to_string(),
+            meta: Some(valid_proto_meta()),
+            data: Bytes::from(valid_tensor_bytes()),
+            ..Default::default()
+        };
+        server.put(Request::new(put_req)).await.unwrap();
+
+        let view = |source: &str, key: &str, shape: Vec<u64>| CreateViewRequest {
+            source_key: source.to_string(),
+            key: key.to_string(),
+            shape,
+        };
+        server.create_view(Request::new(view("square", "line", vec![4]))).await.unwrap();
+        let (_, data) = collect_stream(&server, GetRequest { key: "line".to_string(), ..Default::default() }).await;
+        assert_eq!(data, valid_tensor_bytes());
+        let stats = server.get_stats(Request::new(StatsRequest {})).await.unwrap().into_inner();
+        assert_eq!((stats.entries, stats.memory_used), (2, 16));
+
+        let status = server.create_view(Request::new(view("square", "bad", vec![3]))).await.unwrap_err();
+        assert_eq!(
+            crate::transport::grpc::error_details::tensor_error(&status),
+            Some(TensorError::LengthMismatch { expected: 12, actual: 16 })
+        );
+        let status = server.create_view(Request::new(view("missing", "bad", vec![4]))).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        let status = server.create_view(Request::new(view("square", "line", vec![4]))).await.unwrap_err();
+        assert_eq!(status.code(), Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn grpc_non_finite_policy_and_describe() {
+        let server = setup_server().with_non_finite_policy(NonFinitePolicy::Reject);
+        let put = |key: &str, values: [f32; 4]| PutRequest {
+            key: key.to_string(),
+            meta: Some(valid_proto_meta()),
+            data: Bytes::from(values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>()),
+            ..Default::default()
+        };
+        let status = server.put(Request::new(put("nan", [1.0, f32::NAN, 2.0, 3.0]))).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        server.put(Request::new(put("finite", [1.0, 2.0, 3.0, 6.0]))).await.unwrap();
+
+        let describe = |key: &str| DescribeRequest { key: key.to_string() };
+        let stats = server.describe(Request::new(describe("finite"))).await.unwrap().into_inner();
+        assert_eq!((stats.count, stats.nan_count, stats.inf_count), (4, 0, 0));
+        assert_eq!((stats.min, stats.max, stats.mean), (Some(1.0), Some(6.0), Some(3.0)));
+        let status = server.describe(Request::new(describe("nan"))).await.unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn grpc_sparse_put_validates_and_densifies() {
+        let server = setup_server();
+        let sparse_meta = proto::TensorMeta {
+            dtype: proto::DType::U8 as i32,
+            shape: vec![4, 4],
+            layout: proto::StorageLayout::RowMajor as i32,
+            sparse_format: proto::SparseFormat::Coo as i32,
+            nnz: 1,
+            ..Default::default()
+        };
+
+        let out_of_bounds = PutRequest {
+            key: "bad".to_string(),
+            meta: Some(sparse_meta.clone()),
+            data: crate::tensor::sparse::coo_payload(&[4, 0], &[9]),
+            ..Default::default()
+        };
+        let response = server.put(Request::new(out_of_bounds)).await;
+        let status = response.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            crate::transport::grpc::error_details::tensor_error(&status),
+            Some(TensorError::IndexOutOfBounds { dim: 0, index: 4, extent: 4 })
+        );
+
+        let put_req = PutRequest {
+            key: "sparse".to_string(),
+            meta: Some(sparse_meta),
+            data: crate::tensor::sparse::coo_payload(&[2, 3], &[9]),
+            ..Default::default()
+        };
+        server.put(Request::new(put_req)).await.unwrap();
+
+        let request = GetRequest { key: "sparse".to_string(), ..Default::default() };
+        let (_, data) = collect_stream(&server, request).await;
+        assert_eq!(data.len(), 17);
+
+        let request = GetRequest { key: "sparse".to_string(), densify: true, ..Default::default() };
+        let (_, data) = collect_stream(&server, request).await;
+        let mut expected = vec![0u8; 16];
+        expected[11] = 9;
+        assert_eq!(data, expected);
+    }
+
+    #[tokio::test]
+    async fn grpc_ragged_row_fetch() {
+        let server = setup_server();
+        let rows: [&[u8]; 2] = [&[1, 2, 3], &[4]];
+        let (meta, payload) = crate::tensor::ragged::pack_rows(DType::U8, &[], &rows).unwrap();
+        let put_req = PutRequest {
+            key: "tokens".to_string(),
+            meta: Some(meta_to_proto(&meta)),
+            data: payload,
+            ..Default::default()
+        };
+        server.put(Request::new(put_req)).await.unwrap();
+
+        let request = GetRequest { key: "tokens".to_string(), row: Some(0), ..Default::default() };
+        let (_, data) = collect_stream(&server, request).await;
+        assert_eq!(data, vec![1, 2, 3]);
+
+        let request = GetRequest { key: "tokens".to_string(), row: Some(2), ..Default::default() };
+        let response = server.get(Request::new(request)).await;
+        assert_eq!(response.unwrap_err().code(), Code::OutOfRange);
+    }
+
+    #[tokio::test]
+    async fn grpc_empty_tensor_streams_one_chunk() {
+        let server = setup_server();
+
+        let put_req = PutRequest {
+            key: "empty".to_string(),
+            meta: Some(proto::TensorMeta {
+                dtype: proto::DType::F32 as i32,
+                shape: vec![0, 3],
+                layout: proto::StorageLayout::RowMajor as i32,
+                ..Default::default()
+            }),
+            data: Bytes::new(),
+            ..Default::default()
+        };
+        server.put(Request::new(put_req)).await.unwrap();
+
+        let request = GetRequest { key: "empty".to_string(), ..Default::default() };
+        let chunks: Vec<_> = server.get(Request::new(request)).await.unwrap().into_inner().collect().await;
+        assert_eq!(chunks.len(), 1);
+        let chunk = chunks[0].as_ref().unwrap();
+        assert!(chunk.done);
+        assert!(chunk.data.is_empty());
+        assert_eq!(chunk.meta.as_ref().unwrap().shape, vec![0, 3]);
+    }
+
+    #[tokio::test]
+    async fn grpc_get_of_a_current_copy_sends_no_payload() {
+        let server = setup_server();
+        let put_req = PutRequest {
+            key: "tensor".to_string(),
+            meta: Some(valid_proto_meta()),
+            data: Bytes::from(valid_tensor_bytes()),
+            ..Default::default()
+        };
+        let version = server.put(Request::new(put_req)).await.unwrap().into_inner().version;
+
+        let request = GetRequest { key: "tensor".to_string(), cached_version: Some(version), ..Default::default() };
+        let chunks: Vec<_> = server.get(Request::new(request)).await.unwrap().into_inner().collect().await;
+        let chunk = chunks[0].as_ref().unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(chunk.unchanged && chunk.done && chunk.data.is_empty() && chunk.meta.is_none());
+
+        let request = GetRequest { key: "tensor".to_string(), cached_version: Some(version - 1), ..Default::default() };
+        let (_, data) = collect_stream(&server, request).await;
+        assert_eq!(data, valid_tensor_bytes());
+
+        //versions line up with the keys, 0 for a key without a copy.
+        let request = MultiGetRequest {
+            keys: vec!["tensor".to_string(), "tensor".to_string()],
+            cached_versions: vec![version, 0],
+            ..Default::default()
+        };
+        let messages: Vec<_> = server.multi_get(Request::new(request)).await.unwrap().into_inner().collect().await;
+        let chunks: Vec<_> = messages.into_iter().map(|message| message.unwrap()).collect();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[0].index == 0 && chunks[0].chunk.as_ref().unwrap().unchanged);
+        assert!(chunks[1].index == 1 && chunks[1].chunk.as_ref().unwrap().data.len() == 16);
+    }
+
     #[tokio::test]
     async fn grpc_stats_endpoint_works() {
         let server = setup_server();
@@ -0,0 +1,453 @@
+//! Configuration file of `redstone_server`. Every field is optional and falls back to the defaults below,
+//! sizes take an integer of bytes or a string like `"64 MiB"` and durations a string like `"30s"`:
+//!
+//! ```toml
+//! listen = "0.0.0.0:50051"
+//! memory_limit = "8 GiB"
+//! chunk_size = "256 KiB"
+//! drain_timeout = "30s"
+//! scrub_interval = "10m"     # "0s" disables the scrubber
+//! non_finite = "reject"      # allow, reject or flag
+//! dedup = true
+//! reflection = false
+//! serving_on_start = true
+//!
+//! [transport]
+//! stream_window = "64 MiB"
+//! connection_window = "256 MiB"
+//! max_decoding_message_size = "4 MiB"
+//! max_encoding_message_size = "4 MiB"
+//! max_concurrent_streams = 512
+//! concurrency_limit_per_connection = 64
+//!
+//! [tls]
+//! cert_path = "server.pem"
+//! key_path = "server.key"
+//! client_ca_path = "clients-ca.pem"
+//!
+//! [auth]
+//! token_file = "tokens.toml"
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use crate::cache::put_options::NonFinitePolicy;
+use crate::transport::grpc::auth::TokenAuth;
+use crate::transport::grpc::client::DEFAULT_PUT_STREAM_THRESHOLD;
+use crate::transport::grpc::server::{ServerOptions, DEFAULT_CHUNK_SIZE, DEFAULT_DRAIN_TIMEOUT, DEFAULT_MAX_MESSAGE_SIZE, DEFAULT_WINDOW_SIZE};
+use crate::transport::grpc::tls::ServerTlsConfig;
+
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:50051";
+pub const DEFAULT_MEMORY_LIMIT: u64 = 1024 * 1024 * 1024;
+/// room left in a message for the key, metadata and framing next to its payload.
+const MESSAGE_OVERHEAD: u64 = 64 * 1024;
+/// largest HTTP/2 flow control window.
+const MAX_WINDOW_SIZE: u64 = (1 << 31) - 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// socket address to listen on.
+    pub listen: String,
+    /// bytes of tensor data the cache holds before evicting.
+    #[serde(with = "size")]
+    pub memory_limit: u64,
+    /// payload bytes per message of a streamed get.
+    #[serde(with = "size")]
+    pub chunk_size: u64,
+    /// how long a shutdown waits for open calls to finish.
+    #[serde(with = "duration")]
+    pub drain_timeout: Duration,
+    /// how often the background scrubber re-verifies every cached tensor, zero disables it.
+    #[serde(with = "duration")]
+    pub scrub_interval: Duration,
+    pub non_finite: NonFinitePolicy,
+    /// store byte-identical payloads once across keys.
+    pub dedup: bool,
+    /// serve gRPC reflection.
+    pub reflection: bool,
+    /// report serving to health checks once started, see `ServerOptions::serving_on_start`.
+    pub serving_on_start: bool,
+    pub transport: TransportConfig,
+    /// serve over TLS when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsConfig>,
+    /// require bearer tokens when set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthConfig>,
+}
+
+/// HTTP/2 and gRPC limits, see the fields of the same name on `ServerOptions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransportConfig {
+    #[serde(with = "size")]
+    pub stream_window: u64,
+    #[serde(with = "size")]
+    pub connection_window: u64,
+    #[serde(with = "size")]
+    pub max_decoding_message_size: u64,
+    #[serde(with = "size")]
+    pub max_encoding_message_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_streams: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub concurrency_limit_per_connection: Option<usize>,
+}
+
+/// PEM files to serve TLS with, clients must present a certificate signed by `client_ca_path` when set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    pub cert_path: String,
+    pub key_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ca_path: Option<String>,
+}
+
+/// Bearer tokens the server accepts, in the format described in `auth`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    pub token_file: String,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            listen: DEFAULT_LISTEN.to_string(),
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            chunk_size: DEFAULT_CHUNK_SIZE as u64,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            scrub_interval: Duration::ZERO,
+            non_finite: NonFinitePolicy::default(),
+            dedup: false,
+            reflection: true,
+            serving_on_start: true,
+            transport: TransportConfig::default(),
+            tls: None,
+            auth: None,
+        }
+    }
+}
+
+impl Default for TransportConfig {
+    fn default() -> Self {
+        Self {
+            stream_window: DEFAULT_WINDOW_SIZE as u64,
+            connection_window: DEFAULT_WINDOW_SIZE as u64,
+            max_decoding_message_size: DEFAULT_MAX_MESSAGE_SIZE as u64,
+            max_encoding_message_size: DEFAULT_MAX_MESSAGE_SIZE as u64,
+            max_concurrent_streams: None,
+            concurrency_limit_per_connection: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    pub fn from_toml(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// The config as TOML, every default spelled out.
+    pub fn to_toml(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    /// Checks the values against each other without touching the TLS and token files.
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let transport = &self.transport;
+        if self.listen.parse::<SocketAddr>().is_err() {
+            return Err(format!("listen '{}' is not a socket address like 127.0.0.1:50051", self.listen).into());
+        }
+        if self.memory_limit == 0 {
+            return Err("memory_limit must be greater than 0".into());
+        }
+        if self.chunk_size == 0 {
+            return Err("chunk_size must be greater than 0".into());
+        }
+        //a streamed get sends one chunk per message.
+        if self.chunk_size + MESSAGE_OVERHEAD > transport.max_encoding_message_size {
+            return Err(format!(
+                "chunk_size of {} does not fit transport.max_encoding_message_size of {}",
+                size::format(self.chunk_size), size::format(transport.max_encoding_message_size),
+            ).into());
+        }
+        for (name, window) in [("stream_window", transport.stream_window), ("connection_window", transport.connection_window)] {
+            if window == 0 || window > MAX_WINDOW_SIZE {
+                return Err(format!("transport.{} must be between 1 and {} bytes", name, MAX_WINDOW_SIZE).into());
+            }
+        }
+        //clients with the default `ConnectOptions` send payloads up to this size as a single message.
+        let smallest_put = DEFAULT_PUT_STREAM_THRESHOLD as u64 + MESSAGE_OVERHEAD;
+        if transport.max_decoding_message_size < smallest_put {
+            return Err(format!(
+                "transport.max_decoding_message_size must be at least {} to accept unary puts",
+                size::format(smallest_put),
+            ).into());
+        }
+        if transport.max_concurrent_streams == Some(0) {
+            return Err("transport.max_concurrent_streams must be greater than 0".into());
+        }
+        if transport.concurrency_limit_per_connection == Some(0) {
+            return Err("transport.concurrency_limit_per_connection must be greater than 0".into());
+        }
+        Ok(())
+    }
+
+    /// Validates the config and builds the server options, reading the TLS and token files if any.
+    pub fn server_options(&self) -> Result<ServerOptions, Box<dyn std::error::Error>> {
+        self.validate()?;
+        let tls = match &self.tls {
+            Some(tls) => Some(
+                ServerTlsConfig::from_pem_files(&tls.cert_path, &tls.key_path, tls.client_ca_path.as_ref())
+                    .map_err(|e| format!("failed to read TLS certificate, key or client CA: {}", e))?,
+            ),
+            None => None,
+        };
+        let auth = match &self.auth {
+            Some(auth) => Some(
+                TokenAuth::load(&auth.token_file)
+                    .map_err(|e| format!("failed to read token file '{}': {}", auth.token_file, e))?,
+            ),
+            None => None,
+        };
+        let transport = &self.transport;
+        Ok(ServerOptions {
+            scrub_interval: Some(self.scrub_interval).filter(|interval| !interval.is_zero()),
+            non_finite: self.non_finite,
+            dedup: self.dedup,
+            tls,
+            auth,
+            drain_timeout: Some(self.drain_timeout),
+            chunk_size: self.chunk_size as usize,
+            stream_window: transport.stream_window as u32,
+            connection_window: transport.connection_window as u32,
+            max_decoding_message_size: transport.max_decoding_message_size as usize,
+            max_encoding_message_size: transport.max_encoding_message_size as usize,
+            max_concurrent_streams: transport.max_concurrent_streams,
+            concurrency_limit_per_connection: transport.concurrency_limit_per_connection,
+            reflection: self.reflection,
+            serving_on_start: self.serving_on_start,
+        })
+    }
+}
+
+/// Parses a size like `4096`, `"256 KiB"`, `"1.5GiB"` or `"10MB"`. `K`, `M`, `G` and `T` alone are binary.
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = (&s[..split], s[split..].trim());
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => return Err(format!("unknown size unit '{}' in '{}'", unit, s)),
+    };
+    if let Ok(value) = number.parse::<u64>() {
+        return value.checked_mul(multiplier).ok_or_else(|| format!("size '{}' is too large", s));
+    }
+    match number.parse::<f64>() {
+        Ok(value) if value.is_finite() && value * (multiplier as f64) < u64::MAX as f64 => {
+            Ok((value * multiplier as f64).round() as u64)
+        }
+        Ok(_) => Err(format!("size '{}' is too large", s)),
+        Err(_) => Err(format!("invalid size '{}', expected bytes or a number with a unit like 64MiB", s)),
+    }
+}
+
+/// Parses a duration like `"30s"`, `"500ms"`, `"10m"` or `"1h"`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let value: u64 = s[..split].parse().map_err(|_| format!("invalid duration '{}', expected a number with a unit like 30s", s))?;
+    let seconds = match s[split..].trim() {
+        "ms" => return Ok(Duration::from_millis(value)),
+        "s" => Some(value),
+        "m" | "min" => value.checked_mul(60),
+        "h" => value.checked_mul(3600),
+        unit => return Err(format!("unknown duration unit '{}' in '{}', expected ms, s, m or h", unit, s)),
+    };
+    seconds.map(Duration::from_secs).ok_or_else(|| format!("duration '{}' is too large", s))
+}
+
+mod size {
+    use serde::de::{self, Visitor};
+    use serde::{Deserializer, Serializer};
+    use std::fmt;
+
+    /// Exact binary units when the value is a whole multiple, plain bytes otherwise.
+    pub fn format(bytes: u64) -> String {
+        for (unit, scale) in [("TiB", 1u64 << 40), ("GiB", 1 << 30), ("MiB", 1 << 20), ("KiB", 1 << 10)] {
+            if bytes > 0 && bytes.is_multiple_of(scale) {
+                return format!("{} {}", bytes / scale, unit);
+            }
+        }
+        format!("{} B", bytes)
+    }
+
+    pub fn serialize<S: Serializer>(bytes: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(*bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        struct SizeVisitor;
+
+        impl Visitor<'_> for SizeVisitor {
+            type Value = u64;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number of bytes or a size like \"64 MiB\"")
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<u64, E> {
+                Ok(value)
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<u64, E> {
+                u64::try_from(value).map_err(|_| E::custom("size must not be negative"))
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<u64, E> {
+                super::parse_size(value).map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(SizeVisitor)
+    }
+}
+
+mod duration {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = duration.as_millis();
+        let formatted = match millis {
+            0 => "0s".to_string(),
+            _ if millis.is_multiple_of(3_600_000) => format!("{}h", millis / 3_600_000),
+            _ if millis.is_multiple_of(60_000) => format!("{}m", millis / 60_000),
+            _ if millis.is_multiple_of(1_000) => format!("{}s", millis / 1_000),
+            _ => format!("{}ms", millis),
+        };
+        serializer.serialize_str(&formatted)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        super::parse_duration(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sizes_and_durations_parse() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("256 KiB").unwrap(), 256 * 1024);
+        assert_eq!(parse_size("64MiB").unwrap(), 64 * 1024 * 1024);
+        assert_eq!(parse_size("1.5 GiB").unwrap(), 3 * 512 * 1024 * 1024);
+        assert_eq!(parse_size("8g").unwrap(), 8 << 30);
+        assert_eq!(parse_size("10MB").unwrap(), 10_000_000);
+        assert!(parse_size("12 parsecs").is_err());
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("99999999 TiB").is_err());
+
+        assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("400000000000000000m").is_err());
+        assert!(parse_duration("400000000000000000h").is_err());
+    }
+
+    #[test]
+    fn test_config_file_round_trips() {
+        let config = ServerConfig::from_toml(r#"
+listen = "0.0.0.0:6000"
+memory_limit = "8 GiB"
+chunk_size = 131072
+scrub_interval = "10m"
+non_finite = "flag"
+dedup = true
+serving_on_start = false
+
+[transport]
+connection_window = "256 MiB"
+max_concurrent_streams = 512
+
+[auth]
+token_file = "tokens.toml"
+"#).unwrap();
+        assert_eq!(config.memory_limit, 8 << 30);
+        assert_eq!(config.chunk_size, 128 * 1024);
+        assert_eq!(config.scrub_interval, Duration::from_secs(600));
+        assert_eq!(config.non_finite, NonFinitePolicy::Flag);
+        assert_eq!(config.transport.connection_window, 256 << 20);
+        assert_eq!(config.transport.stream_window, DEFAULT_WINDOW_SIZE as u64);
+        assert_eq!(config.drain_timeout, DEFAULT_DRAIN_TIMEOUT);
+        assert!(!config.serving_on_start);
+        assert!(config.validate().is_ok());
+        assert!(!ServerConfig { auth: None, ..config.clone() }.server_options().unwrap().serving_on_start);
+        assert!(ServerConfig::default().server_options().unwrap().serving_on_start);
+
+        let printed = config.to_toml().unwrap();
+        assert!(printed.contains("memory_limit = \"8 GiB\""));
+        assert!(printed.contains("chunk_size = \"128 KiB\""));
+        assert!(printed.contains("serving_on_start = false"));
+        assert_eq!(ServerConfig::from_toml(&printed).unwrap(), config);
+        assert_eq!(ServerConfig::from_toml(&ServerConfig::default().to_toml().unwrap()).unwrap(), ServerConfig::default());
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        assert!(ServerConfig::from_toml("memory_limt = \"1 GiB\"").is_err());
+        assert!(ServerConfig::from_toml("[transport]\nwindow = 1").is_err());
+        assert!(ServerConfig::from_toml("drain_timeout = \"400000000000000000m\"").is_err());
+        assert!(ServerConfig::from_toml("memory_limit = -1").is_err());
+
+        let invalid = [
+            ServerConfig { listen: "localhost".to_string(), ..Default::default() },
+            ServerConfig { memory_limit: 0, ..Default::default() },
+            ServerConfig { chunk_size: 4 << 20, ..Default::default() },
+            ServerConfig {
+                transport: TransportConfig { stream_window: 1 << 31, ..Default::default() },
+                ..Default::default()
+            },
+            ServerConfig {
+                transport: TransportConfig { max_decoding_message_size: 1 << 20, ..Default::default() },
+                ..Default::default()
+            },
+            ServerConfig {
+                transport: TransportConfig { concurrency_limit_per_connection: Some(0), ..Default::default() },
+                ..Default::default()
+            },
+        ];
+        for config in invalid {
+            assert!(config.validate().is_err(), "accepted {:?}", config);
+        }
+
+        let missing = ServerConfig {
+            auth: Some(AuthConfig { token_file: "/nonexistent/tokens.toml".to_string() }),
+            ..Default::default()
+        };
+        assert!(missing.validate().is_ok());
+        assert!(missing.server_options().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Chunk by chunk reads of a tensor, for consumers that copy into their own buffers or write to a file and
+//! should not hold the whole payload in memory first.
+//! The payload is verified as it streams, a length or checksum mismatch is reported in place of the last
+//! chunk, so a reader that consumed every chunk without an error has the exact bytes that were put.
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use bytes::Bytes;
+use futures::Stream;
+use tonic::Streaming;
+use crate::error::client_error::ClientError;
+use crate::error::tensor_error::TensorError;
+use crate::proto::GetResponseChunk;
+use crate::tensor::checksum::StreamingChecksum;
+use crate::tensor::meta::TensorMeta;
+
+/// The raw bytes of one tensor as an async stream of chunks, returned by `RemoteCacheClient::get_stream`.
+/// The metadata and attributes are available before the first chunk is read.
+pub struct TensorStream {
+    key: String,
+    meta: TensorMeta,
+    attributes: HashMap<String, String>,
+    expected_checksum: u64,
+    expected_len: usize,
+    received: usize,
+    checksum: StreamingChecksum,
+    //data of the first response chunk, which arrived with the metadata.
+    first: Option<(Bytes, bool)>,
+    chunks: Streaming<GetResponseChunk>,
+    finished: bool,
+}
+
+impl TensorStream {
+    pub(crate) fn new(
+        key: String,
+        meta: TensorMeta,
+        first: GetResponseChunk,
+        chunks: Streaming<GetResponseChunk>,
+    ) -> Result<Self, ClientError> {
+        Ok(Self {
+            key,
+            expected_len: meta.total_byte_size()?,
+            meta,
+            attributes: first.attributes,
+            expected_checksum: first.checksum,
+            received: 0,
+            checksum: StreamingChecksum::default(),
+            first: Some((first.data, first.done)),
+            chunks,
+            finished: false,
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn meta(&self) -> &TensorMeta {
+        &self.meta
+    }
+
+    pub fn attributes(&self) -> &HashMap<String, String> {
+        &self.attributes
+    }
+
+    //hashes one chunk, the last one is only handed out once the whole payload verified.
+    fn accept(&mut self, data: Bytes, done: bool) -> Result<Bytes, ClientError> {
+        self.received += data.len();
+        if self.received > self.expected_len {
+            return Err(TensorError::LengthMismatch { expected: self.expected_len, actual: self.received }.into());
+        }
+        self.checksum.update(&data);
+        if done {
+            self.verify()?;
+        }
+        Ok(data)
+    }
+
+    fn verify(&mut self) -> Result<(), ClientError> {
+        self.finished = true;
+        if self.received != self.expected_len {
+            return Err(TensorError::LengthMismatch { expected: self.expected_len, actual: self.received }.into());
+        }
+        let actual = self.checksum.finish();
+        if actual != self.expected_checksum {
+            return Err(ClientError::ChecksumMismatch {
+                key: self.key.clone(),
+                expected: self.expected_checksum,
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Stream for TensorStream {
+    type Item = Result<Bytes, ClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.finished {
+                return Poll::Ready(None);
+            }
+            let (data, done) = match self.first.take() {
+                Some(first) => first,
+                None => match Pin::new(&mut self.chunks).poll_next(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(chunk))) => (chunk.data, chunk.done),
+                    Poll::Ready(Some(Err(status))) => {
+                        self.finished = true;
+                        return Poll::Ready(Some(Err(status.into())));
+                    }
+                    //a stream that ends without its done chunk was cut short.
+                    Poll::Ready(None) => {
+                        return Poll::Ready(self.verify().err().map(Err));
+                    }
+                },
+            };
+            match self.accept(data, done) {
+                Ok(data) if data.is_empty() => continue,
+                Ok(data) => return Poll::Ready(Some(Ok(data))),
+                Err(e) => {
+                    self.finished = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! TLS settings for the server and its clients. Certificates, keys and CA bundles are PEM encoded and held in
+//! memory, `from_pem_files` reads them from disk. A server with a client CA requires every client to present a
+//! certificate signed by it (mutual TLS).
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use tonic::transport::{Certificate, Identity};
+
+/// A certificate chain and its private key.
+#[derive(Clone)]
+pub struct TlsIdentity {
+    pub cert: Vec<u8>,
+    pub key: Vec<u8>,
+}
+
+impl TlsIdentity {
+    pub fn from_pem_files(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self { cert: fs::read(cert_path)?, key: fs::read(key_path)? })
+    }
+
+    fn to_tonic(&self) -> Identity {
+        Identity::from_pem(&self.cert, &self.key)
+    }
+}
+
+//keeps private keys out of logs.
+impl fmt::Debug for TlsIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsIdentity")
+            .field("cert", &format_args!("{} bytes", self.cert.len()))
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// TLS for `start_server_with_options`, see `ServerOptions::tls`.
+#[derive(Debug, Clone)]
+pub struct ServerTlsConfig {
+    /// certificate the server presents to clients.
+    pub identity: TlsIdentity,
+    /// CA bundle that client certificates must chain to. When set, clients without such a certificate are
+    /// rejected during the handshake.
+    pub client_ca: Option<Vec<u8>>,
+}
+
+impl ServerTlsConfig {
+    pub fn new(identity: TlsIdentity) -> Self {
+        Self { identity, client_ca: None }
+    }
+
+    /// Requires clients to present a certificate signed by `client_ca`.
+    pub fn with_client_ca(mut self, client_ca: Vec<u8>) -> Self {
+        self.client_ca = Some(client_ca);
+        self
+    }
+
+    pub fn from_pem_files(
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+        client_ca_path: Option<impl AsRef<Path>>,
+    ) -> io::Result<Self> {
+        Ok(Self {
+            identity: TlsIdentity::from_pem_files(cert_path, key_path)?,
+            client_ca: client_ca_path.map(fs::read).transpose()?,
+        })
+    }
+
+    pub(crate) fn to_tonic(&self) -> tonic::transport::ServerTlsConfig {
+        let config = tonic::transport::ServerTlsConfig::new().identity(self.identity.to_tonic());
+        match &self.client_ca {
+            Some(ca) => config.client_ca_root(Certificate::from_pem(ca)),
+            None => config,
+        }
+    }
+}
+
+/// TLS for `RemoteCacheClient::connect_with_tls` and `ClusterClientConfig::tls`.
+#[derive(Debug, Clone)]
+pub struct ClientTlsConfig {
+    /// CA bundle the server certificate must chain to.
+    pub ca: Vec<u8>,
+    /// certificate presented to servers that require mutual TLS.
+    pub identity: Option<TlsIdentity>,
+    /// name the server certificate must be valid for, the host of the address when None.
+    pub domain: Option<String>,
+}
+
+impl ClientTlsConfig {
+    pub fn new(ca: Vec<u8>) -> Self {
+        Self { ca, identity: None, domain: None }
+    }
+
+    /// Presents `identity` to servers that require mutual TLS.
+    pub fn with_identity(mut self, identity: TlsIdentity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    /// Verifies the server certificate against `domain` instead of the host it is reached at.
+    pub fn with_domain(mut self, domain: impl Into<String>) -> Self {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub(crate) fn to_tonic(&self) -> tonic::transport::ClientTlsConfig {
+        let mut config = tonic::transport::ClientTlsConfig::new().ca_certificate(Certificate::from_pem(&self.ca));
+        if let Some(identity) = &self.identity {
+            config = config.identity(identity.to_tonic());
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain.clone());
+        }
+        config
+    }
+}
@@ -0,0 +1,127 @@
+use redstone::cluster::config::runtime_config::ClusterClientConfig;
+use redstone::cluster::distributed_client::DistributedClient;
+use redstone::cluster::node::Node;
+use redstone::error::client_error::ClientError;
+use redstone::transport::grpc::auth::TokenAuth;
+use redstone::transport::grpc::client::{ConnectOptions, RemoteCacheClient};
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
+use redstone::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+
+const TOKENS: &str = r#"
+[[tokens]]
+name = "trainer"
+token = "train-token"
+permissions = ["read", "write"]
+prefixes = ["features/"]
+
+[[tokens]]
+name = "reader"
+token = "read-token"
+permissions = ["read"]
+
+[[tokens]]
+name = "operator"
+token = "ops-token"
+permissions = ["read", "write", "delete", "admin"]
+"#;
+
+async fn auth_server_setup() -> String {
+    let options = ServerOptions { auth: Some(TokenAuth::from_toml(TOKENS).unwrap()), ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options)
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
+}
+
+async fn connect(addr: &str, token: Option<&str>) -> RemoteCacheClient {
+    let options = ConnectOptions { token: token.map(str::to_string), ..Default::default() };
+    RemoteCacheClient::connect_with_options(addr.to_string(), options).await.expect("Failed to connect")
+}
+
+fn meta() -> TensorMeta {
+    TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::RowMajor).unwrap()
+}
+
+#[tokio::test]
+async fn calls_without_a_known_token_are_unauthenticated() {
+    let addr = auth_server_setup().await;
+
+    let anonymous = connect(&addr, None).await;
+    assert!(matches!(anonymous.get("features/a".to_string()).await, Err(ClientError::Unauthenticated(_))));
+    assert!(matches!(
+        anonymous.put("features/a".to_string(), meta(), vec![0; 16]).await,
+        Err(ClientError::Unauthenticated(_))
+    ));
+
+    let guessing = connect(&addr, Some("guess")).await;
+    let error = guessing.get_stats().await.err().expect("Stats without a valid token");
+    assert!(matches!(error, ClientError::Unauthenticated(_)));
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn tokens_are_limited_to_their_permissions_and_prefixes() {
+    let addr = auth_server_setup().await;
+    let trainer = connect(&addr, Some("train-token")).await;
+
+    trainer.put("features/a".to_string(), meta(), vec![1; 16]).await.expect("Put failed");
+    assert!(trainer.get("features/a".to_string()).await.expect("Get failed").is_some());
+    assert!(trainer.head("features/a".to_string(), false).await.expect("Head failed").is_some());
+    assert!(matches!(
+        trainer.put("labels/a".to_string(), meta(), vec![1; 16]).await,
+        Err(ClientError::PermissionDenied(_))
+    ));
+    assert!(matches!(trainer.delete("features/a".to_string()).await, Err(ClientError::PermissionDenied(_))));
+    assert!(matches!(trainer.get_stats().await, Err(ClientError::PermissionDenied(_))));
+
+    //batches report forbidden keys per key.
+    let results = trainer.multi_put(vec![
+        ("features/b".to_string(), meta(), vec![2; 16]),
+        ("labels/b".to_string(), meta(), vec![2; 16]),
+    ]).await.expect("Multi put failed");
+    assert!(results[0].is_ok());
+    assert!(matches!(results[1], Err(ClientError::PermissionDenied(_))));
+
+    let operator = connect(&addr, Some("ops-token")).await;
+    operator.put("labels/c".to_string(), meta(), vec![3; 16]).await.expect("Put failed");
+    let results = trainer.multi_get(vec!["features/b".to_string(), "labels/c".to_string()])
+        .await
+        .expect("Multi get failed");
+    assert!(matches!(results[0], Ok(Some(_))));
+    assert!(matches!(results[1], Err(ClientError::PermissionDenied(_))));
+
+    let reader = connect(&addr, Some("read-token")).await;
+    assert!(reader.get("labels/c".to_string()).await.expect("Get failed").is_some());
+    assert!(matches!(
+        reader.put("labels/d".to_string(), meta(), vec![4; 16]).await,
+        Err(ClientError::PermissionDenied(_))
+    ));
+
+    operator.delete("features/a".to_string()).await.expect("Delete failed");
+    assert_eq!(operator.get_stats().await.expect("Stats failed").entries, 2);
+}
+
+#[tokio::test]
+async fn distributed_client_sends_the_token_from_its_config() {
+    let mut nodes = vec![];
+    for i in 0..2 {
+        nodes.push(Node::new(auth_server_setup().await, format!("node{}", i)));
+    }
+
+    let client = DistributedClient::new_with_config(nodes.clone(), ClusterClientConfig::default().with_token("train-token"));
+    for i in 0..10 {
+        client.put(format!("features/{}", i), meta(), vec![i as u8; 16]).await.expect("Put failed");
+    }
+    for i in 0..10 {
+        let tensor = client.get(&format!("features/{}", i)).await.expect("Get failed").expect("Tensor missing");
+        assert_eq!(tensor.get_data()[0], i as u8);
+    }
+    assert!(matches!(
+        client.put("labels/0".to_string(), meta(), vec![0; 16]).await,
+        Err(ClientError::PermissionDenied(_))
+    ));
+
+    let anonymous = DistributedClient::new_with_config(nodes, ClusterClientConfig::default());
+    assert!(matches!(anonymous.get("features/0").await, Err(ClientError::Unauthenticated(_))));
+}
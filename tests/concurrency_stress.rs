@@ -1,25 +1,19 @@
-use redstone::transport::grpc::server::start_server;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
 use redstone::transport::grpc::client::RemoteCacheClient;
 use redstone::tensor::meta::{TensorMeta, DType, StorageLayout};
+use redstone::cache::put_options::{PutMode, PutOptions};
+use redstone::error::client_error::ClientError;
 
-use tokio::time::{sleep, Duration};
 use std::sync::Arc;
-use rand::{rng, RngExt};
 
 const THREADS: u64 = 20;
 const OPS_PER_THREAD: u64 = 200;
 
-fn random_port() -> u16 {
-    rng().random_range(50060..60000)
-}
-
 #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
 async fn stress_test_concurrent_clients() {
 
    let addr = server_setup().await;
 
-    sleep(Duration::from_millis(300)).await;
-
     let addr_arc = Arc::new(addr);
 
     let mut handles = vec![];
@@ -78,14 +72,103 @@ async fn stress_test_concurrent_clients() {
     assert!(stats.hits+stats.misses>0);
     assert!(stats.memory_used <= stats.memory_limit);
 }
+fn counter_meta() -> TensorMeta {
+    TensorMeta::new(DType::I64, vec![1], StorageLayout::RowMajor).unwrap()
+}
+
+fn put_mode(mode: PutMode) -> PutOptions {
+    PutOptions { mode, ..Default::default() }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn compare_and_swap_increments_lose_no_updates() {
+    const WRITERS: i64 = 8;
+    const INCREMENTS: i64 = 25;
+    let addr = server_setup().await;
+
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Client connect failed");
+    client.put("counter".to_string(), counter_meta(), 0i64.to_le_bytes().to_vec()).await.expect("Put failed");
+    //holds the first version in its l1 cache, the final read through it must still see every increment.
+    client.get("counter".to_string()).await.expect("Get failed").expect("Counter missing");
+
+    let mut handles = vec![];
+    for _ in 0..WRITERS {
+        let addr = addr.clone();
+        handles.push(tokio::spawn(async move {
+            let client = RemoteCacheClient::connect(addr).await.expect("Client connect failed");
+            let mut conflicts = 0;
+            for _ in 0..INCREMENTS {
+                //read, increment and write back only if nobody wrote in between, retrying on conflict.
+                loop {
+                    let current = client.get("counter".to_string()).await.expect("Get failed").expect("Counter missing");
+                    let value = i64::from_le_bytes(current.get_data()[..8].try_into().unwrap());
+                    let next = (value + 1).to_le_bytes().to_vec();
+                    let options = put_mode(PutMode::IfVersion(current.version()));
+                    match client.put_with_options("counter".to_string(), counter_meta(), next, options).await {
+                        Ok(_) => break,
+                        Err(ClientError::VersionMismatch { .. }) => {
+                            conflicts += 1;
+                        }
+                        Err(e) => panic!("Put failed: {}", e),
+                    }
+                }
+            }
+            conflicts
+        }));
+    }
+    let mut conflicts = 0;
+    for handle in handles {
+        conflicts += handle.await.expect("Writer panicked");
+    }
+
+    let counter = client.get("counter".to_string()).await.expect("Get failed").expect("Counter missing");
+    let value = i64::from_le_bytes(counter.get_data()[..8].try_into().unwrap());
+    assert_eq!(value, WRITERS * INCREMENTS, "lost updates with {} conflicts", conflicts);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_overwrites_keep_the_highest_version() {
+    let addr = server_setup().await;
+
+    let mut handles = vec![];
+    for writer in 0..THREADS as i64 {
+        let addr = addr.clone();
+        handles.push(tokio::spawn(async move {
+            let client = RemoteCacheClient::connect(addr).await.expect("Client connect failed");
+            let mut written = vec![];
+            for op in 0..10 {
+                let value = writer * 100 + op;
+                let options = put_mode(PutMode::Overwrite);
+                let version = client.put_with_options("feature".to_string(), counter_meta(), value.to_le_bytes().to_vec(), options)
+                    .await
+                    .expect("Overwrite failed");
+                written.push((version, value));
+            }
+            written
+        }));
+    }
+    let mut written = vec![];
+    for handle in handles {
+        written.extend(handle.await.expect("Writer panicked"));
+    }
+    let mut versions: Vec<u64> = written.iter().map(|(version, _)| *version).collect();
+    versions.sort();
+    versions.dedup();
+    assert_eq!(versions.len(), written.len(), "two writes got the same version");
+
+    //the last write wins, and the last write is the one with the highest version.
+    let (version, value) = *written.iter().max().unwrap();
+    let reader = RemoteCacheClient::connect(addr).await.expect("Client connect failed");
+    let head = reader.head("feature".to_string(), false).await.expect("Head failed").expect("Feature missing");
+    assert_eq!(head.version, version);
+    let feature = reader.get("feature".to_string()).await.expect("Get failed").expect("Feature missing");
+    assert_eq!(feature.version(), version);
+    assert_eq!(i64::from_le_bytes(feature.get_data()[..8].try_into().unwrap()), value);
+}
+
 async fn server_setup() -> String {
-    let port = random_port();
-    let addr = format!("127.0.0.1:{}", port);
-    let server_addr = addr.clone();
-    tokio::spawn(async move {
-        start_server(server_addr, 1024)
-            .await
-            .expect("Server failed");
-    });
-    addr
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
 }
\ No newline at end of file
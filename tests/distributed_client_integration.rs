@@ -1,29 +1,21 @@
-use redstone::transport::grpc::server::start_server;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
 use redstone::cluster::distributed_client::DistributedClient;
 use redstone::cluster::node::Node;
 use redstone::tensor::meta::{TensorMeta, DType, StorageLayout};
+use redstone::tensor::ragged;
 
-use std::time::Duration;
-use tokio::time::sleep;
-use rand::{random_range, rng, RngExt};
 use redstone::transport::grpc::client::RemoteCacheClient;
-
-fn random_port() -> u16 {
-    rng().random_range(51000..60000)
-}
-
-async fn spawn_server() -> String {
-    let port = random_port();
-    let addr = format!("127.0.0.1:{}", port);
-    let server_addr = addr.clone();
-
-    tokio::spawn(async move {
-        start_server(server_addr, 1024 * 1024)
-            .await
-            .expect("Server failed");
-    });
-
-    addr
+use redstone::cache::get_options::GetOptions;
+use redstone::cache::put_options::PutOptions;
+use std::collections::HashMap;
+use tokio_stream::StreamExt;
+use redstone::interop::safetensors::{export_from_cluster, import_into_cluster, SafetensorsOptions};
+
+async fn server_setup() -> String {
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
 }
 
 #[tokio::test]
@@ -188,16 +180,219 @@ async fn distributed_client_equal_distribution() {
 
 }
 
+#[tokio::test]
+async fn distributed_safetensors_round_trip() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes);
+
+    let meta = TensorMeta::new(
+        DType::F32,
+        vec![2, 3],
+        StorageLayout::RowMajor,
+    ).unwrap();
+    let bytes: Vec<u8> = (0..24).collect();
+    let keys: Vec<String> = (0..4).map(|i| format!("ckpt/layer_{}", i)).collect();
+    for key in &keys {
+        client.put(key.clone(), meta.clone(), bytes.clone())
+            .await
+            .expect("PUT failed");
+    }
+
+    let path = std::env::temp_dir().join(format!("redstone_cluster_{}.safetensors", std::process::id()));
+    export_from_cluster(&client, &keys, &path, &SafetensorsOptions::with_prefix("ckpt/"))
+        .await
+        .expect("Export failed");
+
+    let imported = import_into_cluster(&path, &client, &SafetensorsOptions::with_prefix("restored/"))
+        .await
+        .expect("Import failed");
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(imported.len(), keys.len());
+
+    let tensor = client.get("restored/layer_2").await.unwrap().expect("Imported key missing");
+    assert_eq!(tensor.get_metadata().shape(), &[2, 3]);
+    assert_eq!(tensor.get_data().as_ref(), bytes.as_slice());
+}
+
+#[tokio::test]
+async fn distributed_delete_by_tag_flow() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes);
+    let meta = TensorMeta::new(DType::F32, vec![4], StorageLayout::RowMajor).unwrap();
+
+    for i in 0..20 {
+        let version = if i % 2 == 0 { "v1" } else { "v2" };
+        let options = PutOptions {
+            attributes: HashMap::from([("model_version".to_string(), version.to_string())]),
+            ..Default::default()
+        };
+        client.put_with_options(format!("tagged_{}", i), meta.clone(), vec![0u8; 16], options)
+            .await
+            .expect("PUT failed");
+    }
+
+    let tensor = client.get("tagged_1").await.unwrap().unwrap();
+    assert_eq!(tensor.get_attributes().get("model_version").map(String::as_str), Some("v2"));
+
+    let deleted = client.delete_by_tag("model_version", "v1").await.unwrap();
+    assert_eq!(deleted.len(), 10);
+    for i in 0..20 {
+        let exists = client.get(&format!("tagged_{}", i)).await.unwrap().is_some();
+        assert_eq!(exists, i % 2 == 1);
+    }
+}
+
+#[tokio::test]
+async fn distributed_ragged_row_flow() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes);
+
+    //token ids of three sequences of different lengths.
+    let sequences: Vec<Vec<u8>> = [vec![101i64, 7, 102], vec![101, 102], vec![101, 5, 6, 7, 102]]
+        .iter()
+        .map(|ids| ids.iter().flat_map(|id| id.to_le_bytes()).collect())
+        .collect();
+    let rows: Vec<&[u8]> = sequences.iter().map(Vec::as_slice).collect();
+    let (meta, payload) = ragged::pack_rows(DType::I64, &[], &rows).unwrap();
+    client.put("batch".to_string(), meta, payload.to_vec()).await.expect("PUT failed");
+
+    for (i, sequence) in sequences.iter().enumerate() {
+        let row = client.get_row("batch", i).await.unwrap().expect("Row missing");
+        assert_eq!(row.get_metadata().shape(), &[sequence.len() / 8]);
+        assert_eq!(row.get_data().as_ref(), sequence.as_slice());
+    }
+    assert!(client.get_row("batch", 3).await.is_err());
+
+    let whole = client.get("batch").await.unwrap().expect("Batch missing");
+    assert_eq!(whole.get_metadata().ragged_rows(), Some(3));
+}
+
+#[tokio::test]
+async fn distributed_get_casts_on_the_server() {
+    let nodes = initialize_servers().await;
+    let writer = DistributedClient::new_default(nodes.clone());
+
+    let weights = [0.1f32, -1.5, 3.25, 1e5];
+    let data: Vec<u8> = weights.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let meta = TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::RowMajor).unwrap();
+    writer.put("weights".to_string(), meta, data).await.expect("PUT failed");
+
+    //a client that never fetched the tensor, so the cast happens on the server rather than from an l1 copy.
+    let reader = DistributedClient::new_default(nodes);
+    let half = reader.get_as("weights", DType::BF16).await.unwrap().expect("Tensor missing");
+    assert_eq!(half.get_metadata().dtype(), &DType::BF16);
+    assert_eq!(half.get_metadata().shape(), &[2, 2]);
+    assert_eq!(half.get_data().len(), 8);
+    let values: Vec<f32> = half.get_data()
+        .chunks_exact(2)
+        .map(|b| half::bf16::from_le_bytes(b.try_into().unwrap()).to_f32())
+        .collect();
+    for (value, expected) in values.iter().zip(weights) {
+        assert!((value - expected).abs() <= expected.abs() / 128.0);
+    }
+
+    let bytes = reader.get_as("weights", DType::U8).await.unwrap().expect("Tensor missing");
+    assert_eq!(bytes.get_data().as_ref(), &[0, 0, 3, 255]);
+}
+
+#[tokio::test]
+async fn distributed_views_share_the_source_node() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes.clone());
+
+    let data: Vec<u8> = (0..96).collect();
+    let meta = TensorMeta::new(DType::U8, vec![8, 12], StorageLayout::RowMajor).unwrap();
+    client.put("{acts}:flat".to_string(), meta, data.clone()).await.expect("PUT failed");
+    client.create_view("{acts}:flat", "{acts}:3d", vec![2, 4, 12]).await.expect("View failed");
+
+    let view = client.get("{acts}:3d").await.unwrap().expect("View missing");
+    assert_eq!(view.get_metadata().shape(), &[2, 4, 12]);
+    assert_eq!(view.get_data().as_ref(), data.as_slice());
+    let memory_used: u64 = client.get_per_server_stats().await.unwrap().iter().map(|s| s.memory_used).sum();
+    assert_eq!(memory_used, 96);
+
+    client.delete("{acts}:flat").await.unwrap();
+    //the view went with its source, the l1 copy of the view is not served.
+    assert!(client.get("{acts}:3d").await.unwrap().is_none());
+}
+
 async fn initialize_servers() -> Vec<Node> {
 
-    let addr1 = spawn_server().await;
-    let addr2 = spawn_server().await;
-    let addr3 = spawn_server().await;
-    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    let addr1 = server_setup().await;
+    let addr2 = server_setup().await;
+    let addr3 = server_setup().await;
     let nodes = vec![
         Node::new(addr1.clone(), "node1"),
         Node::new(addr2.clone(), "node2"),
         Node::new(addr3.clone(), "node3"),
     ];
     nodes
-}
\ No newline at end of file
+}
+#[tokio::test]
+async fn distributed_describe_reports_non_finite_values() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes);
+
+    let values = [1.0f32, f32::NAN, 3.0, f32::NEG_INFINITY, 5.0];
+    let data: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let meta = TensorMeta::new(DType::F32, vec![5], StorageLayout::RowMajor).unwrap();
+    client.put("activations".to_string(), meta, data).await.expect("PUT failed");
+
+    let stats = client.describe("activations").await.unwrap().expect("Tensor missing");
+    assert_eq!((stats.count, stats.nan_count, stats.inf_count), (5, 1, 1));
+    assert_eq!((stats.min, stats.max, stats.mean), (Some(1.0), Some(5.0), Some(3.0)));
+    assert!(client.describe("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn distributed_get_stream_reads_from_the_owner() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes);
+
+    let data: Vec<u8> = (0..300 * 1024u32).map(|i| (i % 241) as u8).collect();
+    let meta = TensorMeta::new(DType::U8, vec![300, 1024], StorageLayout::RowMajor).unwrap();
+    client.put("frames".to_string(), meta, data.clone()).await.expect("PUT failed");
+
+    let stream = client.get_stream("frames").await.unwrap().expect("Tensor missing");
+    assert_eq!(stream.meta().dtype(), &DType::U8);
+    let chunks: Vec<_> = stream.collect().await;
+    let received: Vec<u8> = chunks.into_iter().flat_map(|chunk| chunk.unwrap()).collect();
+    assert_eq!(received, data);
+    assert!(client.get_stream("missing").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn distributed_batches_fan_out_and_keep_request_order() {
+    let nodes = initialize_servers().await;
+    let client = DistributedClient::new_default(nodes.clone());
+    let meta = TensorMeta::new(DType::U8, vec![8], StorageLayout::RowMajor).unwrap();
+
+    let entries: Vec<_> = (0..60u8).map(|i| (format!("emb:{}", i), meta.clone(), vec![i; 8])).collect();
+    let results = client.multi_put(entries).await.expect("MultiPut failed");
+    assert!(results.iter().all(Result::is_ok));
+    let per_node: Vec<u64> = client.get_per_server_stats().await.unwrap().iter().map(|s| s.entries).collect();
+    assert!(per_node.iter().filter(|&&entries| entries > 0).count() > 1);
+
+    let keys: Vec<String> = (0..60u8).rev().map(|i| format!("emb:{}", i)).chain(["emb:missing".to_string()]).collect();
+    let reader = DistributedClient::new_default(nodes);
+    //cast on the servers, and not kept in the l1 cache in place of the stored tensors.
+    let options = GetOptions { desired_dtype: Some(DType::F32), ..Default::default() };
+    let cast = reader.multi_get_with_options(&keys, options).await.expect("MultiGet failed");
+    for (i, tensor) in cast[..60].iter().enumerate() {
+        let tensor = tensor.as_ref().unwrap().as_ref().expect("Tensor missing");
+        assert_eq!(tensor.get_metadata().dtype(), &DType::F32);
+        assert_eq!(tensor.get_data()[..4], ((59 - i) as f32).to_le_bytes());
+    }
+    assert!(matches!(cast[60], Ok(None)));
+
+    let tensors = reader.multi_get(&keys).await.expect("MultiGet failed");
+    for (i, tensor) in tensors[..60].iter().enumerate() {
+        let tensor = tensor.as_ref().unwrap().as_ref().expect("Tensor missing");
+        assert_eq!(tensor.get_data().as_ref(), &[59 - i as u8; 8]);
+    }
+    assert!(matches!(tensors[60], Ok(None)));
+
+    let deleted = reader.multi_delete(&keys).await.expect("MultiDelete failed");
+    assert_eq!(deleted.iter().filter(|&&d| d).count(), 60);
+    assert!(!deleted[60]);
+}
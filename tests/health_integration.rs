@@ -0,0 +1,123 @@
+use redstone::cluster::config::runtime_config::ClusterClientConfig;
+use redstone::cluster::distributed_client::DistributedClient;
+use redstone::cluster::node::Node;
+use redstone::transport::grpc::auth::TokenAuth;
+use redstone::transport::grpc::client::RemoteCacheClient;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
+use redstone::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+use tonic_health::pb::health_check_response::ServingStatus;
+use tonic_health::pb::health_client::HealthClient;
+use tonic_health::pb::HealthCheckRequest;
+use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1::ServerReflectionRequest;
+use tonic::transport::{Channel, Endpoint};
+use std::time::Duration;
+
+async fn server_setup(options: ServerOptions) -> String {
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options)
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
+}
+
+async fn channel(addr: &str) -> Channel {
+    Endpoint::from_shared(format!("http://{}", addr)).unwrap().connect().await.expect("Failed to connect")
+}
+
+//status of the server as a whole, the `""` service.
+async fn overall_status(health: &mut HealthClient<Channel>) -> ServingStatus {
+    let response = health.check(HealthCheckRequest { service: String::new() }).await.unwrap();
+    response.into_inner().status()
+}
+
+#[tokio::test]
+async fn health_reports_serving_without_a_token() {
+    let tokens = "[[tokens]]\nname = \"reader\"\ntoken = \"read-token\"\npermissions = [\"read\"]\n";
+    let addr = server_setup(ServerOptions { auth: Some(TokenAuth::from_toml(tokens).unwrap()), ..Default::default() }).await;
+
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Failed to connect");
+    assert!(client.health().await.expect("Health check failed"));
+
+    let mut health = HealthClient::new(channel(&addr).await);
+    for service in ["", "redstone.RedStone"] {
+        let response = health.check(HealthCheckRequest { service: service.to_string() }).await.unwrap();
+        assert_eq!(response.into_inner().status(), ServingStatus::Serving);
+    }
+    let unknown = health.check(HealthCheckRequest { service: "redstone.Nothing".to_string() }).await;
+    assert_eq!(unknown.unwrap_err().code(), tonic::Code::NotFound);
+}
+
+#[tokio::test]
+async fn readiness_follows_the_server_handle() {
+    let options = ServerOptions { serving_on_start: false, ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options).await.expect("Server failed");
+    let addr = server.local_addr().to_string();
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Failed to connect");
+    let mut health = HealthClient::new(channel(&addr).await);
+
+    //not serving yet, but the cache already answers, e.g. to be filled before taking traffic.
+    assert!(!client.health().await.unwrap());
+    assert_eq!(overall_status(&mut health).await, ServingStatus::NotServing);
+    let meta = TensorMeta::new(DType::U8, vec![4], StorageLayout::RowMajor).unwrap();
+    client.put("warm".to_string(), meta, vec![1; 4]).await.expect("Put failed");
+
+    server.set_serving(true).await;
+    assert!(client.health().await.unwrap());
+    assert_eq!(overall_status(&mut health).await, ServingStatus::Serving);
+    server.set_serving(false).await;
+    assert!(!client.health().await.unwrap());
+    assert_eq!(overall_status(&mut health).await, ServingStatus::NotServing);
+}
+
+#[tokio::test]
+async fn reflection_lists_the_services() {
+    let addr = server_setup(ServerOptions::default()).await;
+    let mut reflection = ServerReflectionClient::new(channel(&addr).await);
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = reflection.server_reflection_info(tokio_stream::iter(vec![request])).await.unwrap().into_inner();
+    let response = responses.message().await.unwrap().expect("No reflection response");
+
+    let Some(MessageResponse::ListServicesResponse(list)) = response.message_response else {
+        panic!("Unexpected reflection response");
+    };
+    let names: Vec<String> = list.service.into_iter().map(|service| service.name).collect();
+    assert!(names.contains(&"redstone.RedStone".to_string()));
+    assert!(names.contains(&"grpc.health.v1.Health".to_string()));
+}
+
+#[tokio::test]
+async fn distributed_client_removes_nodes_that_fail_probes() {
+    let mut nodes = vec![];
+    for i in 0..2 {
+        nodes.push(Node::new(server_setup(ServerOptions::default()).await, format!("node{}", i)));
+    }
+    //a port that was free a moment ago, nothing listens on it.
+    let unused = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let dead = Node::new(unused.to_string(), "dead");
+    nodes.push(dead.clone());
+    let config = ClusterClientConfig { timeout: Duration::from_secs(1), ..Default::default() };
+    let client = DistributedClient::new_with_config(nodes, config);
+
+    let health = client.check_health().await;
+    assert_eq!(health.len(), 3);
+    for (node, healthy) in &health {
+        assert_eq!(*healthy, node.name != "dead", "unexpected health for {}", node);
+    }
+
+    let removed = client.remove_unhealthy_nodes().await.expect("Failed to remove nodes");
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].name, dead.name);
+
+    //every key now routes to a live node.
+    let meta = TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::RowMajor).unwrap();
+    for i in 0..10 {
+        client.put(format!("key{}", i), meta.clone(), vec![i as u8; 16]).await.expect("Put failed");
+    }
+    assert!(client.check_health().await.iter().all(|(_, healthy)| *healthy));
+}
@@ -0,0 +1,59 @@
+use redstone::transport::grpc::client::RemoteCacheClient;
+use redstone::transport::grpc::server::spawn_server;
+use redstone::transport::grpc::server_config::ServerConfig;
+use redstone::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::ServerReflectionRequest;
+use tonic::transport::Endpoint;
+use tokio_stream::StreamExt;
+
+const CONFIG: &str = r#"
+listen = "127.0.0.1:0"
+memory_limit = "16 MiB"
+chunk_size = "64 KiB"
+drain_timeout = "1s"
+reflection = false
+
+[transport]
+stream_window = "1 MiB"
+connection_window = "4 MiB"
+max_concurrent_streams = 16
+concurrency_limit_per_connection = 8
+"#;
+
+#[tokio::test]
+async fn server_runs_with_the_settings_of_its_config_file() {
+    let config = ServerConfig::from_toml(CONFIG).unwrap();
+    let server = spawn_server(config.listen.clone(), config.memory_limit, config.server_options().unwrap())
+        .await
+        .expect("Server failed");
+    let addr = server.local_addr().to_string();
+
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Failed to connect");
+    let data: Vec<u8> = (0..1024 * 1024u32).map(|i| (i % 253) as u8).collect();
+    let meta = TensorMeta::new(DType::U8, vec![1024, 1024], StorageLayout::RowMajor).unwrap();
+    client.put("weights".to_string(), meta, data.clone()).await.expect("Put failed");
+
+    //the payload arrives in chunk_size pieces.
+    let mut stream = client.get_stream("weights".to_string()).await.expect("Get failed").expect("Tensor missing");
+    let mut chunks = 0;
+    let mut received = Vec::with_capacity(data.len());
+    while let Some(chunk) = stream.next().await {
+        received.extend_from_slice(&chunk.expect("Chunk failed"));
+        chunks += 1;
+    }
+    assert_eq!(received, data);
+    assert_eq!(chunks, 16);
+
+    let channel = Endpoint::from_shared(format!("http://{}", addr)).unwrap().connect().await.unwrap();
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let reflection = ServerReflectionClient::new(channel).server_reflection_info(tokio_stream::iter(vec![request])).await;
+    assert_eq!(reflection.unwrap_err().code(), tonic::Code::Unimplemented);
+
+    server.shutdown().await.expect("Shutdown failed");
+}
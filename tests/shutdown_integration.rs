@@ -0,0 +1,66 @@
+use redstone::proto;
+use redstone::proto::red_stone_client::RedStoneClient;
+use redstone::transport::grpc::client::RemoteCacheClient;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions, ShutdownOutcome};
+use redstone::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_stream::StreamExt;
+
+#[tokio::test]
+async fn shutdown_drains_open_streams_and_runs_hooks() {
+    let mut server = spawn_server("127.0.0.1:0".to_string(), 16 * 1024 * 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    let addr = server.local_addr();
+    assert_ne!(addr.port(), 0);
+    let entries = Arc::new(AtomicU64::new(0));
+    let seen = Arc::clone(&entries);
+    server.on_shutdown(move |cache| seen.store(cache.get_stats().entries + 1, Ordering::SeqCst));
+
+    let client = RemoteCacheClient::connect(addr.to_string()).await.expect("Client failed to connect");
+    let data: Vec<u8> = (0..2 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let meta = TensorMeta::new(DType::U8, vec![2048, 1024], StorageLayout::RowMajor).unwrap();
+    client.put("activations".to_string(), meta, data.clone()).await.expect("Put failed");
+
+    //the stream is open when the shutdown starts and still delivers every chunk.
+    let mut stream = client.get_stream("activations".to_string()).await.expect("Get failed").expect("Tensor missing");
+    let mut received = stream.next().await.expect("No chunk").expect("Chunk failed").to_vec();
+    let drain = async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        while let Some(chunk) = stream.next().await {
+            received.extend_from_slice(&chunk.expect("Chunk failed"));
+        }
+    };
+    let (shutdown, ()) = tokio::join!(server.shutdown(), drain);
+    assert_eq!(shutdown.expect("Shutdown failed"), ShutdownOutcome::Drained);
+    assert_eq!(received, data);
+    assert_eq!(entries.load(Ordering::SeqCst), 2);
+    assert!(client.get_stats().await.is_err());
+    assert!(RemoteCacheClient::connect(addr.to_string()).await.is_err());
+}
+
+#[tokio::test]
+async fn shutdown_gives_up_on_calls_after_the_drain_timeout() {
+    let options = ServerOptions { drain_timeout: Some(Duration::from_millis(200)), ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options).await.expect("Server failed");
+    let mut client = RedStoneClient::connect(format!("http://{}", server.local_addr())).await.unwrap();
+
+    //a streaming put whose client never finishes sending.
+    let header = proto::PutRequest {
+        key: "stuck".to_string(),
+        meta: Some(proto::TensorMeta { dtype: proto::DType::U8 as i32, shape: vec![1024], layout: proto::StorageLayout::RowMajor as i32, ..Default::default() }),
+        ..Default::default()
+    };
+    let requests = tokio_stream::iter(vec![header]).chain(tokio_stream::pending());
+    let stuck = tokio::spawn(async move { client.put_stream(requests).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let started = Instant::now();
+    let outcome = server.shutdown().await.expect("Shutdown failed");
+    assert_eq!(outcome, ShutdownOutcome::TimedOut(Duration::from_millis(200)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(stuck.await.unwrap().is_err());
+}
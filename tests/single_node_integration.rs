@@ -1,23 +1,24 @@
-use redstone::transport::grpc::server::start_server;
-use redstone::transport::grpc::client::RemoteCacheClient;
-use redstone::tensor::meta::{TensorMeta,DType,StorageLayout};
-
-use std::time::Duration;
-use tokio::time::sleep;
-use rand::{rng, RngExt};
+use redstone::proto;
+use redstone::proto::red_stone_client::RedStoneClient;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
+use redstone::transport::grpc::client::{ConnectOptions, RemoteCacheClient};
+use redstone::tensor::meta::{TensorMeta,DType,StorageLayout,SparseFormat};
+use redstone::tensor::sparse;
+use redstone::tensor::codec::{Codec, Encoding, Shuffle};
+use redstone::cache::get_options::GetOptions;
+use redstone::cache::put_options::{NonFinitePolicy, PutMode, PutOptions, NON_FINITE_ATTRIBUTE};
+use redstone::error::client_error::ClientError;
+use redstone::error::tensor_error::TensorError;
 
 use std::sync::Arc;
-fn random_port() -> u16 {
-    rng().random_range(50060..60000)
-}
+use std::time::Duration;
+use tokio_stream::StreamExt;
 
 #[tokio::test]
 async fn single_node_put_get_delete_flow() {
 
     let addr = server_setup().await;
 
-    sleep(Duration::from_millis(200)).await;
-
     let mut client = RemoteCacheClient::connect(addr)
         .await
         .expect("Client failed to connect");
@@ -80,7 +81,6 @@ async fn single_node_put_get_delete_flow() {
 async fn duplicate_put_fails() {
 
     let addr = server_setup().await;
-    sleep(Duration::from_millis(200)).await;
 
     let mut client = RemoteCacheClient::connect(addr)
         .await
@@ -114,8 +114,6 @@ async fn duplicate_put_fails() {
 #[tokio::test]
 async fn test_oom_put() {
     let addr = server_setup().await;
-    //allows server to initialize
-    sleep(Duration::from_millis(200)).await;
 
     let mut client = RemoteCacheClient::connect(addr)
         .await
@@ -153,7 +151,6 @@ async fn test_oom_put() {
 async fn test_invalid_tensor_data() {
 
     let addr = server_setup().await;
-    sleep(Duration::from_millis(200)).await;
 
     let mut client = RemoteCacheClient::connect(addr)
         .await
@@ -173,15 +170,158 @@ async fn test_invalid_tensor_data() {
         .put("invalid_tensor".to_string(), meta, invalid_bytes)
         .await;
 
-    assert!(result.is_err(),"Expected invalid tensor errors but put succeeded");
+    assert!(
+        matches!(result, Err(ClientError::InvalidTensor(TensorError::LengthMismatch { expected: 16, actual: 15 }))),
+        "Expected invalid tensor errors but put succeeded"
+    );
+}
+
+
+#[tokio::test]
+async fn scalar_and_empty_tensors_round_trip() {
+    let addr = server_setup().await;
+
+    let writer = RemoteCacheClient::connect(addr.clone())
+        .await
+        .expect("Client failed");
+
+    let scalar = TensorMeta::new(DType::F32, vec![], StorageLayout::RowMajor).unwrap();
+    writer.put("loss".to_string(), scalar, 0.25f32.to_le_bytes().to_vec())
+        .await
+        .expect("Scalar put failed");
+    let empty = TensorMeta::new(DType::I64, vec![0, 16], StorageLayout::RowMajor).unwrap();
+    writer.put("empty_batch".to_string(), empty, Vec::new())
+        .await
+        .expect("Empty put failed");
+
+    //a fresh client so the reads go over the wire.
+    let reader = RemoteCacheClient::connect(addr)
+        .await
+        .expect("Reader failed");
+    let loss = reader.get("loss".to_string()).await.unwrap().expect("Scalar missing");
+    assert!(loss.get_metadata().shape().is_empty());
+    assert_eq!(loss.get_data().as_ref(), &0.25f32.to_le_bytes());
+
+    let batch = reader.get("empty_batch".to_string()).await.unwrap().expect("Empty tensor missing");
+    assert_eq!(batch.get_metadata().shape(), &[0, 16]);
+    assert!(batch.get_data().is_empty());
+
+    let stats = reader.get_stats().await.unwrap();
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.memory_used, 4);
+}
+
+#[tokio::test]
+async fn sparse_tensor_round_trips_and_densifies() {
+    //the dense form is 4 MB, densified gets are limited to the cache's memory.
+    let server = spawn_server("127.0.0.1:0".to_string(), 16 * 1024 * 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    let addr = server.local_addr().to_string();
+
+    let writer = RemoteCacheClient::connect(addr.clone())
+        .await
+        .expect("Client failed");
+
+    //a 1000x1000 interaction matrix with three non zero entries.
+    let meta = TensorMeta::new_sparse(DType::F32, vec![1000, 1000], SparseFormat::Csr, 3).unwrap();
+    let rows = [0usize, 10, 999];
+    let row_pointers: Vec<i64> = (0..=1000)
+        .map(|end| rows.iter().filter(|&&row| row < end).count() as i64)
+        .collect();
+    let values: Vec<u8> = [1.0f32, 2.0, 3.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let payload = sparse::csr_payload(&row_pointers, &[5, 0, 999], &values);
+    writer.put_with_options("interactions".to_string(), meta, payload.to_vec(), PutOptions {
+        encoding: Encoding::new(Codec::Lz4, Shuffle::Byte),
+        ..Default::default()
+    })
+        .await
+        .expect("Sparse put failed");
+
+    let stats = writer.get_stats().await.unwrap();
+    assert!(stats.memory_used < payload.len() as u64);
+
+    let reader = RemoteCacheClient::connect(addr)
+        .await
+        .expect("Reader failed");
+    let stored = reader.get("interactions".to_string()).await.unwrap().expect("Key missing");
+    assert_eq!(stored.get_metadata().sparsity().unwrap().nnz, 3);
+    assert_eq!(stored.get_data(), &payload);
+
+    let dense = reader
+        .get_with_options("interactions".to_string(), GetOptions { densify: true, ..Default::default() })
+        .await
+        .unwrap()
+        .expect("Key missing");
+    assert!(dense.get_metadata().sparsity().is_none());
+    let element = |i: usize| f32::from_le_bytes(dense.get_data()[i * 4..i * 4 + 4].try_into().unwrap());
+    assert_eq!(element(5), 1.0);
+    assert_eq!(element(10 * 1000), 2.0);
+    assert_eq!(element(999 * 1000 + 999), 3.0);
+    assert_eq!(dense.get_data().chunks_exact(4).filter(|c| *c != [0u8; 4]).count(), 3);
 }
 
+#[tokio::test]
+async fn densifying_a_huge_sparse_shape_fails_cleanly() {
+    let addr = server_setup().await;
+    let client = RemoteCacheClient::connect(addr).await.expect("Client failed");
+
+    //one value in a 2^60 byte dense shape, cheap to store but impossible to densify.
+    let meta = TensorMeta::new_sparse(DType::U8, vec![1 << 20, 1 << 20, 1 << 20], SparseFormat::Coo, 1).unwrap();
+    client.put("huge".to_string(), meta, sparse::coo_payload(&[0, 0, 0], &[1]).to_vec()).await.expect("Sparse put failed");
+
+    let result = client.get_with_options("huge".to_string(), GetOptions { densify: true, ..Default::default() }).await;
+    assert!(matches!(result, Err(ClientError::InvalidTensor(TensorError::TooLarge { size, .. })) if size == 1 << 60));
+    assert!(client.get("huge".to_string()).await.expect("Get failed").is_some());
+}
+
+#[tokio::test]
+async fn compressed_put_round_trips_with_either_decoder() {
+    let addr = server_setup().await;
+
+    let client = RemoteCacheClient::connect(addr.clone())
+        .await
+        .expect("Client failed");
+
+    let meta = TensorMeta::new(
+        DType::F32,
+        vec![1024],
+        StorageLayout::RowMajor,
+    ).unwrap();
+    let data: Vec<u8> = (0..1024u32)
+        .flat_map(|i| if i % 5 == 0 { (i as f32).to_le_bytes() } else { [0u8; 4] })
+        .collect();
+
+    let options = PutOptions {
+        encoding: Encoding::new(Codec::Zstd, Shuffle::Byte),
+        ..Default::default()
+    };
+    client
+        .put_with_options("relu".to_string(), meta, data.clone(), options)
+        .await
+        .expect("Compressed put failed");
+
+    let stats = client.get_stats().await.expect("Stats failed");
+    assert!(stats.memory_used < data.len() as u64);
+
+    //fresh clients so the reads are not served from the L1 cache of the writer.
+    for decode_on_client in [false, true] {
+        let reader = RemoteCacheClient::connect(addr.clone())
+            .await
+            .expect("Reader failed");
+        let tensor = reader
+            .get_with_options("relu".to_string(), GetOptions { decode_on_client, ..Default::default() })
+            .await
+            .expect("Get failed")
+            .expect("Key missing");
+        assert_eq!(tensor.get_data().as_ref(), data.as_slice());
+    }
+}
 
 #[tokio::test]
 async fn test_concurrent_clients_race_conditions() {
     //testing concurrent access to a key.
     let addr = server_setup().await;
-    sleep(Duration::from_millis(200)).await;
 
     let addr_arc = Arc::new(addr);
 
@@ -229,14 +369,261 @@ async fn test_concurrent_clients_race_conditions() {
     let _ = client.get("race_key".to_string()).await.unwrap();
 }
 
+#[tokio::test]
+async fn large_put_streams_past_the_message_limit() {
+    let server = spawn_server("127.0.0.1:0".to_string(), 32 * 1024 * 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    let addr = server.local_addr().to_string();
+    let client = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+
+    //6 MB is above tonic's 4 MB default message limit, so a unary put would be rejected.
+    let data: Vec<u8> = (0..6 * 1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+    let meta = TensorMeta::new(DType::U8, vec![6, 1024 * 1024], StorageLayout::RowMajor).unwrap();
+    client.put("shard".to_string(), meta.clone(), data.clone()).await.expect("Streaming put failed");
+
+    let tensor = client.get("shard".to_string()).await.expect("Get failed").expect("Tensor missing");
+    assert_eq!(tensor.get_metadata().shape(), &[6, 1024 * 1024]);
+    assert_eq!(tensor.get_data().as_ref(), data.as_slice());
+
+    let result = client.put("shard".to_string(), meta, data).await;
+    assert!(matches!(result, Err(ClientError::KeyAlreadyExists(key)) if key == "shard"));
+}
+
+#[tokio::test]
+async fn put_stream_threshold_fits_the_server_message_limit() {
+    let options = ServerOptions { max_decoding_message_size: 256 * 1024, ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 4 * 1024 * 1024, options).await.expect("Server failed");
+    let addr = server.local_addr().to_string();
+    let meta = TensorMeta::new(DType::U8, vec![1024, 1024], StorageLayout::RowMajor).unwrap();
+    let data: Vec<u8> = (0..1024 * 1024u32).map(|i| (i % 251) as u8).collect();
+
+    //the default threshold sends 1 MB as one message, above what this server accepts.
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Client failed to connect");
+    assert!(client.put("default".to_string(), meta.clone(), data.clone()).await.is_err());
+
+    let options = ConnectOptions { put_stream_threshold: 128 * 1024, ..Default::default() };
+    let client = RemoteCacheClient::connect_with_options(addr, options).await.expect("Client failed to connect");
+    client.put("streamed".to_string(), meta, data.clone()).await.expect("Streaming put failed");
+    let small = TensorMeta::new(DType::U8, vec![100 * 1024], StorageLayout::RowMajor).unwrap();
+    client.put("unary".to_string(), small, data[..100 * 1024].to_vec()).await.expect("Unary put failed");
+    let tensor = client.get("streamed".to_string()).await.expect("Get failed").expect("Tensor missing");
+    assert_eq!(tensor.get_data().as_ref(), data.as_slice());
+}
+
+#[tokio::test]
+async fn get_stream_yields_verified_chunks() {
+    let addr = server_setup().await;
+    let client = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+
+    //large enough to span several 256 KB response chunks.
+    let data: Vec<u8> = (0..768 * 1024u32).map(|i| (i % 253) as u8).collect();
+    let meta = TensorMeta::new(DType::U8, vec![768, 1024], StorageLayout::RowMajor).unwrap();
+    client.put("activations".to_string(), meta, data.clone()).await.expect("Put failed");
+
+    let mut stream = client.get_stream("activations".to_string()).await.expect("Get failed").expect("Tensor missing");
+    assert_eq!(stream.meta().shape(), &[768, 1024]);
+    let mut chunks = 0;
+    let mut received = Vec::with_capacity(data.len());
+    while let Some(chunk) = stream.next().await {
+        received.extend_from_slice(&chunk.expect("Chunk failed"));
+        chunks += 1;
+    }
+    assert!(chunks > 1);
+    assert_eq!(received, data);
+
+    assert!(client.get_stream("missing".to_string()).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn batch_rpcs_report_per_key_results() {
+    let addr = server_setup().await;
+    let client = RemoteCacheClient::connect(addr.clone()).await.expect("Client failed to connect");
+    let meta = TensorMeta::new(DType::U8, vec![4], StorageLayout::RowMajor).unwrap();
+    client.put("b".to_string(), meta.clone(), vec![0; 4]).await.expect("Put failed");
+
+    let entries = vec![
+        ("a".to_string(), meta.clone(), vec![1; 4]),
+        ("b".to_string(), meta.clone(), vec![2; 4]),
+        ("c".to_string(), meta.clone(), vec![3; 3]),
+        ("d".to_string(), meta.clone(), vec![4; 4]),
+    ];
+    let results = client.multi_put(entries).await.expect("MultiPut failed");
+    assert!(results[0].is_ok() && results[3].is_ok());
+    assert!(matches!(&results[1], Err(ClientError::KeyAlreadyExists(key)) if key == "b"));
+    assert!(matches!(&results[2], Err(ClientError::InvalidTensor(TensorError::LengthMismatch { expected: 4, actual: 3 }))));
+
+    //a fresh client, so every key is fetched from the server.
+    let reader = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+    let keys: Vec<String> = ["d", "missing", "a", "b"].iter().map(|k| k.to_string()).collect();
+    let tensors = reader.multi_get(keys.clone()).await.expect("MultiGet failed");
+    let values: Vec<Option<u8>> = tensors
+        .into_iter()
+        .map(|tensor| tensor.unwrap().map(|t| t.get_data()[0]))
+        .collect();
+    assert_eq!(values, vec![Some(4), None, Some(1), Some(0)]);
+
+    let deleted = reader.multi_delete(keys.clone()).await.expect("MultiDelete failed");
+    assert_eq!(deleted, vec![true, false, true, true]);
+    assert!(reader.multi_get(keys).await.unwrap().iter().all(|tensor| matches!(tensor, Ok(None))));
+}
+
 async fn server_setup() -> String {
-    let port = random_port();
-    let addr = format!("127.0.0.1:{}", port);
-    let server_addr = addr.clone();
-    tokio::spawn(async move {
-        start_server(server_addr, 1024 * 1024)
-            .await
-            .expect("Server failed");
-    });
-    addr
-}
\ No newline at end of file
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, ServerOptions::default())
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
+}
+#[tokio::test]
+async fn head_returns_metadata_without_touching_the_entry() {
+    let addr = server_setup().await;
+    let client = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+
+    let meta = TensorMeta::new(DType::F32, vec![64, 16], StorageLayout::RowMajor).unwrap();
+    let options = PutOptions {
+        encoding: Encoding::new(Codec::Lz4, Shuffle::Byte),
+        attributes: [("model".to_string(), "bert".to_string())].into(),
+        ..Default::default()
+    };
+    client.put_with_options("features".to_string(), meta.clone(), vec![0; 64 * 16 * 4], options)
+        .await
+        .expect("Put failed");
+
+    let head = client.head("features".to_string(), false).await.expect("Head failed").expect("Tensor missing");
+    assert_eq!(head.meta.shape(), meta.shape());
+    assert_eq!(head.meta.dtype(), &DType::F32);
+    assert_eq!(head.byte_size, 64 * 16 * 4);
+    assert!(head.stored_byte_size < head.byte_size);
+    assert_eq!(head.attributes["model"], "bert");
+    assert!(client.head("missing".to_string(), false).await.expect("Head failed").is_none());
+    assert_eq!(client.get_stats().await.expect("Stats failed").hits, 0);
+
+    client.head("features".to_string(), true).await.expect("Head failed").expect("Tensor missing");
+    assert_eq!(client.get_stats().await.expect("Stats failed").hits, 1);
+}
+
+#[tokio::test]
+async fn put_modes_return_and_check_versions() {
+    let addr = server_setup().await;
+    let writer = RemoteCacheClient::connect(addr.clone()).await.expect("Client failed to connect");
+    let reader = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+    let meta = TensorMeta::new(DType::U8, vec![4], StorageLayout::RowMajor).unwrap();
+    let mode = |mode: PutMode| PutOptions { mode, ..Default::default() };
+
+    let first = writer.put("embedding".to_string(), meta.clone(), vec![1; 4]).await.expect("Put failed");
+    assert_eq!(reader.get("embedding".to_string()).await.unwrap().unwrap().version(), first);
+    let result = writer.put("embedding".to_string(), meta.clone(), vec![2; 4]).await;
+    assert!(matches!(result, Err(ClientError::KeyAlreadyExists(_))));
+
+    let second = writer.put_with_options("embedding".to_string(), meta.clone(), vec![2; 4], mode(PutMode::Overwrite))
+        .await
+        .expect("Overwrite failed");
+    assert!(second > first);
+    let result = writer.put_with_options("embedding".to_string(), meta.clone(), vec![3; 4], mode(PutMode::IfVersion(first))).await;
+    assert!(matches!(result, Err(ClientError::VersionMismatch { expected, actual, .. }) if expected == first && actual == second));
+    let result = writer.put_with_options("missing".to_string(), meta.clone(), vec![3; 4], mode(PutMode::IfVersion(first))).await;
+    assert!(matches!(result, Err(ClientError::GrpcStatus(status)) if status.code() == tonic::Code::NotFound));
+
+    //the reader's l1 copy of the first version is revalidated, so it reads the overwrite.
+    let current = reader.get("embedding".to_string()).await.unwrap().unwrap();
+    assert_eq!((current.version(), current.get_data().as_ref()), (second, &[2u8; 4][..]));
+    let result = reader.put_with_options("embedding".to_string(), meta.clone(), vec![4; 4], mode(PutMode::IfVersion(first))).await;
+    assert!(result.is_err());
+    let third = reader.put_with_options("embedding".to_string(), meta, vec![4; 4], mode(PutMode::IfVersion(current.version())))
+        .await
+        .expect("Swap failed");
+    assert_eq!(writer.head("embedding".to_string(), false).await.unwrap().unwrap().version, third);
+}
+
+#[tokio::test]
+async fn put_failures_are_typed_client_errors() {
+    let options = ServerOptions { non_finite: NonFinitePolicy::Reject, ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options).await.expect("Server failed");
+    let client = RemoteCacheClient::connect(server.local_addr().to_string()).await.expect("Client failed to connect");
+
+    let meta = TensorMeta::new(DType::U8, vec![2 * 1024 * 1024], StorageLayout::RowMajor).unwrap();
+    let result = client.put("huge".to_string(), meta, vec![0; 2 * 1024 * 1024]).await;
+    assert!(matches!(
+        result,
+        Err(ClientError::OutOfMemory { requested_bytes, memory_limit }) if requested_bytes == 2 * 1024 * 1024 && memory_limit == 1024 * 1024
+    ));
+
+    let meta = TensorMeta::new(DType::F32, vec![2], StorageLayout::RowMajor).unwrap();
+    let data = [1.0f32, f32::NAN].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let result = client.put("nan".to_string(), meta.clone(), data).await;
+    assert!(matches!(result, Err(ClientError::TensorRejected { key, .. }) if key == "nan"));
+
+    client.put("flat".to_string(), meta.clone(), vec![0; 8]).await.expect("Put failed");
+    client.put("copy".to_string(), meta, vec![0; 8]).await.expect("Put failed");
+    let result = client.create_view("flat".to_string(), "copy".to_string(), vec![1, 2]).await;
+    assert!(matches!(result, Err(ClientError::KeyAlreadyExists(key)) if key == "copy"));
+}
+
+#[tokio::test]
+async fn gets_after_a_put_return_what_the_server_stored() {
+    let options = ServerOptions { non_finite: NonFinitePolicy::Flag, ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options).await.expect("Server failed");
+    let writer = RemoteCacheClient::connect(server.local_addr().to_string()).await.expect("Client failed to connect");
+    let other = RemoteCacheClient::connect(server.local_addr().to_string()).await.expect("Client failed to connect");
+
+    let meta = TensorMeta::new(DType::F32, vec![2], StorageLayout::RowMajor).unwrap();
+    let data = [1.0f32, f32::NAN].iter().flat_map(|v| v.to_le_bytes()).collect();
+    let version = writer.put("nan".to_string(), meta.clone(), data).await.expect("Put failed");
+    let tensor = writer.get("nan".to_string()).await.unwrap().expect("Tensor missing");
+    assert_eq!(tensor.version(), version);
+    assert_eq!(tensor.get_attributes().get(NON_FINITE_ATTRIBUTE).map(String::as_str), Some("nan=1,inf=0"));
+
+    //replaced by another client while the writer holds it in its l1 cache.
+    let options = PutOptions { mode: PutMode::Overwrite, ..Default::default() };
+    let replaced = other.put_with_options("nan".to_string(), meta, vec![0; 8], options).await.expect("Overwrite failed");
+    let tensors = writer.multi_get(vec!["nan".to_string()]).await.unwrap();
+    let tensor = tensors[0].as_ref().unwrap().as_ref().expect("Tensor missing");
+    assert_eq!((tensor.version(), tensor.get_data().as_ref()), (replaced, &[0u8; 8][..]));
+    let tensor = writer.get("nan".to_string()).await.unwrap().expect("Tensor missing");
+    assert_eq!((tensor.version(), tensor.get_data().as_ref()), (replaced, &[0u8; 8][..]));
+    other.delete("nan".to_string()).await.expect("Delete failed");
+    assert!(writer.get("nan".to_string()).await.unwrap().is_none());
+    assert!(matches!(writer.multi_get(vec!["nan".to_string()]).await.unwrap()[0], Ok(None)));
+}
+
+#[tokio::test]
+async fn streaming_put_is_bounded_by_its_stored_size() {
+    let addr = server_setup().await;
+    let client = RemoteCacheClient::connect(addr).await.expect("Client failed to connect");
+
+    //3 MB is streamed and larger than the 1 MB cache, but compresses to a few KB as it would over Put.
+    let meta = TensorMeta::new(DType::U8, vec![3, 1024, 1024], StorageLayout::RowMajor).unwrap();
+    let options = PutOptions { encoding: Encoding::new(Codec::Lz4, Shuffle::None), ..Default::default() };
+    client.put_with_options("zeros".to_string(), meta.clone(), vec![0; 3 * 1024 * 1024], options)
+        .await
+        .expect("Compressed streaming put failed");
+    assert!(client.get_stats().await.unwrap().memory_used < 1024 * 1024);
+
+    let result = client.put("raw".to_string(), meta, vec![0; 3 * 1024 * 1024]).await;
+    assert!(matches!(result, Err(ClientError::OutOfMemory { requested_bytes, .. }) if requested_bytes == 3 * 1024 * 1024));
+}
+
+#[tokio::test]
+async fn streaming_put_larger_than_the_cache_is_refused_up_front() {
+    let addr = server_setup().await;
+    let mut client = RedStoneClient::connect(format!("http://{}", addr)).await.expect("Client failed to connect");
+
+    //announces a 1 TB tensor, the server must answer without waiting for the payload.
+    let header = proto::PutRequest {
+        key: "huge".to_string(),
+        meta: Some(proto::TensorMeta {
+            dtype: proto::DType::U8 as i32,
+            shape: vec![1 << 40],
+            layout: proto::StorageLayout::RowMajor as i32,
+            ..Default::default()
+        }),
+        data: vec![0; 1024].into(),
+        ..Default::default()
+    };
+    let requests = tokio_stream::iter(vec![header]).chain(tokio_stream::pending());
+    let result = tokio::time::timeout(Duration::from_secs(5), client.put_stream(requests))
+        .await
+        .expect("Server kept buffering");
+    let error = ClientError::from(result.unwrap_err());
+    assert!(matches!(error, ClientError::OutOfMemory { requested_bytes, memory_limit } if requested_bytes == 1 << 40 && memory_limit == 1024 * 1024));
+}
@@ -0,0 +1,153 @@
+use redstone::cluster::config::config_file::ClusterClientFileConfig;
+use redstone::cluster::config::runtime_config::ClusterClientConfig;
+use redstone::cluster::distributed_client::DistributedClient;
+use redstone::cluster::node::Node;
+use redstone::transport::grpc::client::RemoteCacheClient;
+use redstone::transport::grpc::server::{spawn_server, ServerOptions};
+use redstone::transport::grpc::tls::{ClientTlsConfig, ServerTlsConfig, TlsIdentity};
+use redstone::tensor::meta::{DType, StorageLayout, TensorMeta};
+
+use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, DnType, IsCa, KeyPair};
+
+//a self-signed CA and certificates it issued, generated fresh for every test.
+struct TestCa {
+    issuer: CertifiedIssuer<'static, KeyPair>,
+}
+
+impl TestCa {
+    fn new(name: &str) -> Self {
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, name);
+        Self { issuer: CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap() }
+    }
+
+    fn pem(&self) -> Vec<u8> {
+        self.issuer.pem().into_bytes()
+    }
+
+    fn issue(&self, names: &[&str]) -> TlsIdentity {
+        let key = KeyPair::generate().unwrap();
+        let names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        let cert = CertificateParams::new(names).unwrap().signed_by(&key, &self.issuer).unwrap();
+        TlsIdentity { cert: cert.pem().into_bytes(), key: key.serialize_pem().into_bytes() }
+    }
+}
+
+async fn tls_server_setup(tls: ServerTlsConfig) -> String {
+    let options = ServerOptions { tls: Some(tls), ..Default::default() };
+    let server = spawn_server("127.0.0.1:0".to_string(), 1024 * 1024, options)
+        .await
+        .expect("Server failed");
+    server.local_addr().to_string()
+}
+
+fn meta() -> TensorMeta {
+    TensorMeta::new(DType::F32, vec![2, 2], StorageLayout::RowMajor).unwrap()
+}
+
+#[tokio::test]
+async fn tls_client_round_trips_and_plaintext_is_refused() {
+    let ca = TestCa::new("redstone test ca");
+    let addr = tls_server_setup(ServerTlsConfig::new(ca.issue(&["localhost", "127.0.0.1"]))).await;
+
+    let client = RemoteCacheClient::connect_with_tls(addr.clone(), &ClientTlsConfig::new(ca.pem()))
+        .await
+        .expect("TLS client failed to connect");
+    client.put("encrypted".to_string(), meta(), vec![7; 16]).await.expect("Put failed");
+    let tensor = client.get("encrypted".to_string()).await.expect("Get failed").expect("Tensor missing");
+    assert_eq!(tensor.get_data().as_ref(), &[7u8; 16][..]);
+
+    //channels connect lazily on some platforms, so check the first call rather than the connect.
+    let plaintext = match RemoteCacheClient::connect(addr.clone()).await {
+        Ok(client) => client.get_stats().await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    assert!(plaintext.is_err());
+
+    //a server certificate from an unknown CA is rejected.
+    let other_ca = TestCa::new("someone else");
+    assert!(RemoteCacheClient::connect_with_tls(addr, &ClientTlsConfig::new(other_ca.pem())).await.is_err());
+}
+
+#[tokio::test]
+async fn mutual_tls_requires_a_client_certificate_from_the_ca() {
+    let ca = TestCa::new("redstone test ca");
+    let tls = ServerTlsConfig::new(ca.issue(&["localhost"])).with_client_ca(ca.pem());
+    let addr = tls_server_setup(tls).await;
+    let client_tls = ClientTlsConfig::new(ca.pem()).with_domain("localhost");
+
+    let client = RemoteCacheClient::connect_with_tls(addr.clone(), &client_tls.clone().with_identity(ca.issue(&["worker"])))
+        .await
+        .expect("mTLS client failed to connect");
+    client.put("mutual".to_string(), meta(), vec![1; 16]).await.expect("Put failed");
+
+    let anonymous = match RemoteCacheClient::connect_with_tls(addr.clone(), &client_tls).await {
+        Ok(client) => client.get_stats().await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    assert!(anonymous.is_err());
+
+    let stranger = TestCa::new("someone else").issue(&["worker"]);
+    let untrusted = match RemoteCacheClient::connect_with_tls(addr, &client_tls.with_identity(stranger)).await {
+        Ok(client) => client.get_stats().await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    assert!(untrusted.is_err());
+}
+
+#[tokio::test]
+async fn distributed_client_uses_tls_from_its_config() {
+    let ca = TestCa::new("redstone test ca");
+    let mut nodes = vec![];
+    for i in 0..2 {
+        let tls = ServerTlsConfig::new(ca.issue(&["127.0.0.1"])).with_client_ca(ca.pem());
+        nodes.push(Node::new(tls_server_setup(tls).await, format!("node{}", i)));
+    }
+    let client_tls = ClientTlsConfig::new(ca.pem()).with_identity(ca.issue(&["worker"]));
+    let client = DistributedClient::new_with_config(nodes, ClusterClientConfig::default().with_tls(client_tls));
+
+    for i in 0..10 {
+        client.put(format!("key{}", i), meta(), vec![i as u8; 16]).await.expect("Put failed");
+    }
+    for i in 0..10 {
+        let tensor = client.get(&format!("key{}", i)).await.expect("Get failed").expect("Tensor missing");
+        assert_eq!(tensor.get_data()[0], i as u8);
+    }
+}
+
+#[tokio::test]
+async fn file_config_loads_tls_files() {
+    let ca = TestCa::new("redstone test ca");
+    let tls = ServerTlsConfig::new(ca.issue(&["localhost"])).with_client_ca(ca.pem());
+    let addr = tls_server_setup(tls).await;
+
+    let dir = std::env::temp_dir().join(format!("redstone-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let identity = ca.issue(&["worker"]);
+    std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+    std::fs::write(dir.join("client.pem"), &identity.cert).unwrap();
+    std::fs::write(dir.join("client.key"), &identity.key).unwrap();
+    let config = format!(
+        "[[nodes]]\nname = \"node1\"\naddress = \"{}\"\n\n[tls]\nca_path = \"{}\"\ncert_path = \"{}\"\nkey_path = \"{}\"\ndomain = \"localhost\"\n",
+        addr,
+        dir.join("ca.pem").display(),
+        dir.join("client.pem").display(),
+        dir.join("client.key").display(),
+    );
+    std::fs::write(dir.join("cluster.toml"), &config).unwrap();
+
+    let (nodes, runtime_config) = ClusterClientFileConfig::load(dir.join("cluster.toml").to_str().unwrap())
+        .unwrap()
+        .into_runtime()
+        .expect("Failed to load TLS files");
+    let client = DistributedClient::new_with_config(nodes, runtime_config);
+    client.put("from_file".to_string(), meta(), vec![3; 16]).await.expect("Put failed");
+    assert!(client.get("from_file").await.expect("Get failed").is_some());
+
+    //a certificate without its key is a configuration error.
+    std::fs::write(dir.join("cluster.toml"), config.replace(&format!("key_path = \"{}\"\n", dir.join("client.key").display()), "")).unwrap();
+    let unpaired = ClusterClientFileConfig::load(dir.join("cluster.toml").to_str().unwrap()).unwrap().into_runtime();
+    assert!(unpaired.is_err());
+    std::fs::remove_dir_all(dir).unwrap();
+}